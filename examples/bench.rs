@@ -69,6 +69,23 @@ fn main() {
         },
     );
 
+    // 5b. Same conditions through a ConditionCache (warm)
+    let cache = dproj_rs::condition::ConditionCache::new();
+    bench(
+        &format!("parse all {cond_count} conditions (cached)"),
+        iterations,
+        || {
+            for cond in &conditions {
+                std::hint::black_box(cache.parse(cond).unwrap());
+            }
+        },
+    );
+
+    // 5c. Active PG resolution on a fresh Dproj (cold condition cache)
+    bench("parse + active_property_group (cold cache)", iterations, || {
+        Dproj::parse(source.clone()).unwrap().active_property_group().unwrap()
+    });
+
     // 6. Mutation round-trip (set + reparse)
     bench("set_property_value + reparse", iterations, || {
         let mut d = Dproj::parse(source.clone()).unwrap();
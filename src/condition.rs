@@ -6,6 +6,7 @@
 //! - `'$(Config)'=='Debug' And '$(Platform)'=='Win32'`
 //! - `('$(Platform)'=='Win32' and '$(Base)'=='true') or '$(Base_Win32)'!=''`
 //! - `Exists('$(BDS)\Bin\CodeGear.Delphi.Targets')`
+//! - `!HasTrailingSlash('$(OutDir)') and '$(ProductVersion)' >= '22.0'`
 //!
 //! Uses [`chumsky`] for the parsing grammar.
 //!
@@ -14,17 +15,48 @@
 //! ```text
 //! expr       = or_expr
 //! or_expr    = and_expr ('or' and_expr)*
-//! and_expr   = atom ('and' atom)*
-//! atom       = comparison | exists | '(' expr ')'
-//! comparison = quoted ('==' | '!=') quoted
-//! exists     = 'Exists' '(' quoted ')'
-//! quoted     = "'" chars "'"
+//! and_expr   = unary ('and' unary)*
+//! unary      = ('!' | 'not') unary | atom
+//! atom       = function | '(' expr ')' | comparison
+//! function   = ('Exists' | 'HasTrailingSlash') '(' operand ')'
+//! comparison = operand (cmp_op operand)?
+//! cmp_op     = '==' | '!=' | '<=' | '>=' | '<' | '>'
+//! operand    = quoted | property | bare
+//! quoted     = "'" chars "'"     (quotes inside $(…) do not terminate)
+//! property   = '$(' chars ')'     (balanced; may be a property function)
+//! bare       = [A-Za-z0-9_.+-]+      (not a keyword; e.g. true, 16.0)
 //! ```
+//!
+//! `$(…)` references may be property functions such as
+//! `$(ProductVersion.Substring(0, 2))` or
+//! `$([MSBuild]::VersionGreaterThan($(ProductVersion), '22.0'))`; see
+//! [`crate::functions`].
+//!
+//! A lone operand (without a comparison operator) is interpreted as a
+//! boolean: `true`/`on`/`yes` and `false`/`off`/`no`, optionally prefixed
+//! with `!`.
+//!
+//! ## Comparison semantics
+//!
+//! Mirroring MSBuild, `==` and `!=` compare numerically when both sides are
+//! numbers (decimal or `0x` hex), as booleans when both sides are booleans,
+//! and otherwise as case-insensitive strings.  `<`, `>`, `<=` and `>=`
+//! compare numbers or dotted versions (`10.0.2`); any other operands make
+//! the comparison `false`.
 
 #![allow(dead_code)]
 
+use chumsky::error::RichPattern;
+use chumsky::input::InputRef;
+use chumsky::label::LabelError;
 use chumsky::prelude::*;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::filesystem::{self, AssumeExists, FileSystem};
+use crate::functions::{self, Nesting, parse_number, parse_version};
+use crate::vars::VarMap;
 
 // ═══════════════════════════════════════════════════════════════════════════════
 //  AST
@@ -39,8 +71,15 @@ pub enum Expression {
         op: CompareOp,
         rhs: Vec<ExprValue>,
     },
-    /// `Exists('path')` — always evaluates to `true` (no filesystem checks).
+    /// A lone operand interpreted as a boolean, e.g. `'$(Debug)'`, `true`
+    /// or `$(Flag)`.
+    Value(Vec<ExprValue>),
+    /// `Exists('path')` — checked against the [`EvalContext`]'s filesystem.
     Exists(Vec<ExprValue>),
+    /// `HasTrailingSlash('path')` — `true` when the value ends in `\` or `/`.
+    HasTrailingSlash(Vec<ExprValue>),
+    /// `!a` or `not a` (case-insensitive keyword).
+    Not(Box<Expression>),
     /// `a and b` (case-insensitive keyword).
     And(Box<Expression>, Box<Expression>),
     /// `a or b` (case-insensitive keyword).
@@ -54,45 +93,110 @@ pub enum CompareOp {
     Equal,
     /// `!=`
     NotEqual,
+    /// `<`
+    Less,
+    /// `>`
+    Greater,
+    /// `<=`
+    LessOrEqual,
+    /// `>=`
+    GreaterOrEqual,
 }
 
-/// A fragment of a string value that may contain `$(Variable)` references.
+/// A fragment of a string value that may contain `$(…)` references.
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExprValue {
     /// Literal text (no variable expansion needed).
     Literal(String),
     /// A `$(VarName)` reference that will be expanded during evaluation.
     Variable(String),
+    /// A `$(…)` property function such as `$(Foo.Replace('a', 'b'))` or
+    /// `$([MSBuild]::Add(1, 2))`, holding the text between the parentheses.
+    /// See [`crate::functions`].
+    PropertyFunction(String),
+}
+
+impl Expression {
+    /// Names of the variables the condition reads, including those used
+    /// inside property functions, in order of first appearance.  Names
+    /// differing only in case are reported once.
+    ///
+    /// # Example
+    /// ```
+    /// let expr = dproj_rs::condition::parse_condition(
+    ///     "('$(Platform)'=='Win64' and '$(Base)'=='true') or '$(Base_Win64)'!=''",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(expr.referenced_variables(), ["Platform", "Base", "Base_Win64"]);
+    /// ```
+    pub fn referenced_variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables(&self, names: &mut Vec<String>) {
+        let mut operand = |parts: &[ExprValue]| {
+            for part in parts {
+                match part {
+                    ExprValue::Literal(_) => {}
+                    ExprValue::Variable(name) => functions::push_unique(names, name.clone()),
+                    ExprValue::PropertyFunction(body) => {
+                        for name in functions::referenced_properties(body) {
+                            functions::push_unique(names, name);
+                        }
+                    }
+                }
+            }
+        };
+        match self {
+            Expression::Compare { lhs, rhs, .. } => {
+                operand(lhs);
+                operand(rhs);
+            }
+            Expression::Value(parts)
+            | Expression::Exists(parts)
+            | Expression::HasTrailingSlash(parts) => operand(parts),
+            Expression::Not(e) => e.collect_variables(names),
+            Expression::And(a, b) | Expression::Or(a, b) => {
+                a.collect_variables(names);
+                b.collect_variables(names);
+            }
+        }
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
 //  String-part splitting
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Split the raw text between single quotes into [`StringPart`] fragments.
+/// Split the raw text between single quotes into [`ExprValue`] fragments.
 ///
-/// `$(VarName)` sequences become [`StringPart::Variable`]; everything else
-/// becomes [`StringPart::Literal`].
+/// `$(VarName)` sequences become [`ExprValue::Variable`], `$(…)` property
+/// function calls become [`ExprValue::PropertyFunction`]; everything else
+/// becomes [`ExprValue::Literal`].
 fn parse_string_parts(s: &str) -> Vec<ExprValue> {
     let mut parts = Vec::new();
-    let mut literal = String::new();
-    let mut chars = s.chars().peekable();
+    let mut rest = s;
 
-    while let Some(c) = chars.next() {
-        if c == '$' && chars.peek() == Some(&'(') {
-            if !literal.is_empty() {
-                parts.push(ExprValue::Literal(std::mem::take(&mut literal)));
-            }
-            chars.next(); // consume '('
-            let var_name: String = chars.by_ref().take_while(|&ch| ch != ')').collect();
-            parts.push(ExprValue::Variable(var_name));
-        } else {
-            literal.push(c);
+    while let Some(start) = rest.find("$(") {
+        let Some(len) = functions::property_len(&rest[start..]) else {
+            break;
+        };
+        if start > 0 {
+            parts.push(ExprValue::Literal(rest[..start].to_string()));
         }
+        let body = &rest[start + 2..start + len - 1];
+        parts.push(if functions::is_property_name(body.trim()) {
+            ExprValue::Variable(body.trim().to_string())
+        } else {
+            ExprValue::PropertyFunction(body.to_string())
+        });
+        rest = &rest[start + len..];
     }
 
-    if !literal.is_empty() {
-        parts.push(ExprValue::Literal(literal));
+    if !rest.is_empty() {
+        parts.push(ExprValue::Literal(rest.to_string()));
     }
 
     parts
@@ -103,25 +207,79 @@ fn parse_string_parts(s: &str) -> Vec<ExprValue> {
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Build the chumsky parser for MSBuild condition expressions.
-fn condition_parser<'a>() -> impl Parser<'a, &'a str, Expression, extra::Err<Simple<'a, char>>> {
+fn condition_parser<'a>() -> impl Parser<'a, &'a str, Expression, extra::Err<Rich<'a, char>>> {
     recursive(|expr| {
         // ── Single-quoted string value ───────────────────────────────────
+        // Quotes inside `$(…)` (property function arguments) do not end the
+        // string.
         let quoted = just('\'')
-            .ignore_then(none_of('\'').repeated().to_slice())
+            .ignore_then(custom(|inp| {
+                let before = inp.cursor();
+                let mut nesting = Nesting::default();
+                loop {
+                    match inp.peek() {
+                        Some('\'') if !nesting.is_open() => break,
+                        Some(c) => {
+                            inp.skip();
+                            nesting.feed(c);
+                        }
+                        None => {
+                            let close = if nesting.is_open() { "')'" } else { "\"'\"" };
+                            return Err(unterminated(inp, close));
+                        }
+                    }
+                }
+                Ok(inp.slice_since(&before..))
+            }))
             .then_ignore(just('\''))
             .map(parse_string_parts);
 
+        // ── Unquoted $(…) reference ──────────────────────────────────────
+        let property = just("$(")
+            .then(custom(|inp| {
+                let mut nesting = Nesting::default();
+                nesting.feed('$');
+                nesting.feed('(');
+                while nesting.is_open() {
+                    let Some(c) = inp.next() else {
+                        return Err(unterminated(inp, "')'"));
+                    };
+                    nesting.feed(c);
+                }
+                Ok(())
+            }))
+            .to_slice()
+            .map(parse_string_parts);
+
+        // ── Bare word: numbers, true/false, identifiers ──────────────────
+        let bare = any()
+            .filter(|c: &char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '+' | '-'))
+            .repeated()
+            .at_least(1)
+            .to_slice()
+            .filter(|s: &&str| !is_keyword(s))
+            .map(|s: &str| vec![ExprValue::Literal(s.to_string())]);
+
+        let operand = choice((quoted, property, bare)).padded().labelled("operand");
+
         // ── Comparison operators ─────────────────────────────────────────
-        let cmp_op = just("==")
-            .to(CompareOp::Equal)
-            .or(just("!=").to(CompareOp::NotEqual));
+        let cmp_op = choice((
+            just("==").to(CompareOp::Equal),
+            just("!=").to(CompareOp::NotEqual),
+            just("<=").to(CompareOp::LessOrEqual),
+            just(">=").to(CompareOp::GreaterOrEqual),
+            just('<').to(CompareOp::Less),
+            just('>').to(CompareOp::Greater),
+        ))
+        .labelled("comparison operator");
 
-        // ── Comparison:  'lhs' op 'rhs' ─────────────────────────────────
-        let comparison = quoted
-            .padded()
-            .then(cmp_op.padded())
-            .then(quoted.padded())
-            .map(|((lhs, op), rhs)| Expression::Compare { lhs, op, rhs });
+        // ── Comparison:  lhs op rhs,  or a lone boolean operand ─────────
+        let comparison = operand
+            .then(cmp_op.padded().then(operand).or_not())
+            .map(|(lhs, rest)| match rest {
+                Some((op, rhs)) => Expression::Compare { lhs, op, rhs },
+                None => Expression::Value(lhs),
+            });
 
         // ── Case-insensitive alphabetic word (for keyword matching) ──────
         let alpha_word = any()
@@ -130,34 +288,59 @@ fn condition_parser<'a>() -> impl Parser<'a, &'a str, Expression, extra::Err<Sim
             .at_least(1)
             .to_slice();
 
-        // ── Exists('path') ───────────────────────────────────────────────
-        let exists = alpha_word
-            .filter(|s: &&str| s.eq_ignore_ascii_case("exists"))
-            .ignore_then(just('(').padded())
-            .ignore_then(quoted)
+        // ── Exists('path') / HasTrailingSlash('path') ────────────────────
+        let function = alpha_word
+            .filter(|s: &&str| {
+                s.eq_ignore_ascii_case("exists") || s.eq_ignore_ascii_case("hastrailingslash")
+            })
+            .then_ignore(just('(').padded())
+            .then(operand)
             .then_ignore(just(')').padded())
-            .map(Expression::Exists);
+            .map(|(name, arg): (&str, _)| {
+                if name.eq_ignore_ascii_case("exists") {
+                    Expression::Exists(arg)
+                } else {
+                    Expression::HasTrailingSlash(arg)
+                }
+            })
+            .labelled("function");
 
         // ── Parenthesized expression ─────────────────────────────────────
         let paren_expr = expr.delimited_by(just('(').padded(), just(')').padded());
 
         // ── Atom ─────────────────────────────────────────────────────────
-        let atom = choice((comparison, exists, paren_expr)).padded();
+        let atom = choice((function, paren_expr, comparison)).padded();
+
+        // ── '!' / 'not' — binds tighter than 'and' ──────────────────────
+        let not_op = just('!')
+            .and_is(just("!=").not())
+            .ignored()
+            .or(alpha_word
+                .filter(|s: &&str| s.eq_ignore_ascii_case("not"))
+                .ignored())
+            .padded()
+            .labelled("'!'");
+
+        let unary = not_op
+            .repeated()
+            .foldr(atom, |_, e| Expression::Not(Box::new(e)));
 
         // ── 'and' — higher precedence than 'or' ─────────────────────────
         let and_kw = alpha_word
             .filter(|s: &&str| s.eq_ignore_ascii_case("and"))
-            .padded();
+            .padded()
+            .labelled("'and'");
 
-        let and_expr = atom.clone().foldl(
-            and_kw.ignore_then(atom).repeated(),
+        let and_expr = unary.clone().foldl(
+            and_kw.ignore_then(unary).repeated(),
             |lhs, rhs| Expression::And(Box::new(lhs), Box::new(rhs)),
         );
 
         // ── 'or' — lowest precedence ────────────────────────────────────
         let or_kw = alpha_word
             .filter(|s: &&str| s.eq_ignore_ascii_case("or"))
-            .padded();
+            .padded()
+            .labelled("'or'");
 
         and_expr.clone().foldl(
             or_kw.ignore_then(and_expr).repeated(),
@@ -166,57 +349,577 @@ fn condition_parser<'a>() -> impl Parser<'a, &'a str, Expression, extra::Err<Sim
     })
 }
 
+/// Error for a string or `$(…)` reference that runs to the end of input
+/// without its closing delimiter `close`.
+fn unterminated<'a, 'p>(
+    inp: &mut InputRef<'a, 'p, &'a str, extra::Err<Rich<'a, char>>>,
+    close: &'static str,
+) -> Rich<'a, char> {
+    let end = inp.cursor();
+    <Rich<'a, char> as LabelError<'a, &'a str, &'static str>>::expected_found(
+        [close],
+        None,
+        inp.span_since(&end),
+    )
+}
+
+/// Keywords that can never be bare operands.
+fn is_keyword(word: &str) -> bool {
+    ["and", "or", "not"].iter().any(|k| word.eq_ignore_ascii_case(k))
+}
+
 /// Parse a condition attribute string into an [`Expression`] AST.
-pub fn parse_condition(input: &str) -> Result<Expression, String> {
+pub fn parse_condition(input: &str) -> Result<Expression, ConditionError> {
     condition_parser()
         .parse(input)
         .into_result()
         .map_err(|errs| {
-            let messages: Vec<String> = errs.iter().map(|e| format!("{e}")).collect();
-            format!(
-                "Failed to parse condition '{}': {}",
-                input,
-                messages.join("; ")
-            )
+            let err = &errs[0];
+            ConditionError {
+                condition: input.to_string(),
+                span: err.span().into_range(),
+                // `any` only stems from scanning keyword letters and tells
+                // the reader nothing.
+                expected: err
+                    .expected()
+                    .filter(|p| !matches!(p, RichPattern::Any))
+                    .map(|p| p.to_string())
+                    .collect(),
+                found: err.found().copied(),
+                property_group: None,
+            }
         })
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Errors
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// A syntax error in an MSBuild condition.
+///
+/// # Example
+/// ```
+/// let err = dproj_rs::condition::parse_condition("'$(Config)' === 'Debug'").unwrap_err();
+/// assert_eq!(err.span, 14..15);
+/// assert_eq!(err.found, Some('='));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionError {
+    /// The condition text that failed to parse.
+    pub condition: String,
+    /// Byte range of the offending input within [`condition`](Self::condition).
+    /// Empty and at the end of the text when the input ended too early.
+    pub span: std::ops::Range<usize>,
+    /// Descriptions of what the parser would have accepted at `span`, e.g.
+    /// `operand`, `'and'` or `')'`.
+    pub expected: Vec<String>,
+    /// The offending character, or `None` at the end of input.
+    pub found: Option<char>,
+    /// Index of the `<PropertyGroup>` the condition belongs to, when known.
+    pub property_group: Option<usize>,
+}
+
+impl ConditionError {
+    /// Attach the index of the `<PropertyGroup>` the condition belongs to.
+    pub fn with_property_group(mut self, index: usize) -> Self {
+        self.property_group = Some(index);
+        self
+    }
+}
+
+impl std::fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to parse condition '{}'", self.condition)?;
+        if let Some(index) = self.property_group {
+            write!(f, " in PropertyGroup #{index}")?;
+        }
+        write!(f, " at byte {}: ", self.span.start)?;
+        match self.found {
+            Some(c) => write!(f, "found '{c}'")?,
+            None => write!(f, "unexpected end of input")?,
+        }
+        match self.expected.as_slice() {
+            [] => Ok(()),
+            [only] => write!(f, ", expected {only}"),
+            [init @ .., last] => write!(f, ", expected {} or {last}", init.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Compiled condition cache
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Memoizes [`parse_condition`] by condition text.
+///
+/// Building the parser and parsing a condition costs far more than
+/// evaluating the resulting [`Expression`], and a project evaluated for
+/// many configurations and platforms sees the same few conditions over and
+/// over.  The cache is keyed by the exact text, so it stays valid when
+/// conditions are edited, and it can be shared between threads.
+///
+/// # Example
+/// ```
+/// use dproj_rs::condition::ConditionCache;
+///
+/// let cache = ConditionCache::new();
+/// let a = cache.parse("'$(Base)'!=''").unwrap();
+/// let b = cache.parse("'$(Base)'!=''").unwrap();
+/// assert!(std::sync::Arc::ptr_eq(&a, &b));
+/// assert_eq!(cache.len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct ConditionCache {
+    entries: RwLock<HashMap<String, Result<Arc<Expression>, ConditionError>>>,
+}
+
+impl ConditionCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse `condition`, or return the result of an earlier call with the
+    /// same text.  Syntax errors are cached as well.
+    pub fn parse(&self, condition: &str) -> Result<Arc<Expression>, ConditionError> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cached) = entries.get(condition) {
+            return cached.clone();
+        }
+        drop(entries);
+
+        let parsed = parse_condition(condition).map(Arc::new);
+        self.entries
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(condition.to_string())
+            .or_insert(parsed)
+            .clone()
+    }
+
+    /// Number of distinct condition texts parsed so far.
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Whether nothing has been parsed yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget all parsed conditions.
+    pub fn clear(&self) {
+        self.entries.write().unwrap_or_else(PoisonError::into_inner).clear();
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Pretty-printing
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Renders canonical MSBuild condition text, in the style the IDE writes:
+/// operands single-quoted, no spaces around comparison operators, lowercase
+/// `and` / `or`, and `and` groups inside `or` parenthesized.
+///
+/// Parsing the rendered text yields an equal [`Expression`].
+///
+/// # Example
+/// ```
+/// use dproj_rs::condition::parse_condition;
+///
+/// let expr = parse_condition("$(Platform) == Win32 AND NOT Exists('$(BDS)')").unwrap();
+/// assert_eq!(expr.to_string(), "'$(Platform)'=='Win32' and !Exists('$(BDS)')");
+/// ```
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        /// Write `expr`, parenthesized when it is a boolean operator that
+        /// `needs_parens` says would otherwise bind wrongly or unclearly.
+        fn child(
+            f: &mut std::fmt::Formatter<'_>,
+            expr: &Expression,
+            needs_parens: impl Fn(&Expression) -> bool,
+        ) -> std::fmt::Result {
+            if needs_parens(expr) { write!(f, "({expr})") } else { write!(f, "{expr}") }
+        }
+
+        match self {
+            Expression::Compare { lhs, op, rhs } => {
+                write!(f, "'{}'{op}'{}'", Quoted(lhs), Quoted(rhs))
+            }
+            Expression::Value(parts) => write!(f, "'{}'", Quoted(parts)),
+            Expression::Exists(parts) => write!(f, "Exists('{}')", Quoted(parts)),
+            Expression::HasTrailingSlash(parts) => {
+                write!(f, "HasTrailingSlash('{}')", Quoted(parts))
+            }
+            Expression::Not(inner) => {
+                f.write_str("!")?;
+                child(f, inner, |e| matches!(e, Expression::And(..) | Expression::Or(..)))
+            }
+            Expression::And(lhs, rhs) => {
+                child(f, lhs, |e| matches!(e, Expression::Or(..)))?;
+                f.write_str(" and ")?;
+                child(f, rhs, |e| matches!(e, Expression::And(..) | Expression::Or(..)))
+            }
+            Expression::Or(lhs, rhs) => {
+                child(f, lhs, |e| matches!(e, Expression::And(..)))?;
+                f.write_str(" or ")?;
+                child(f, rhs, |e| matches!(e, Expression::And(..) | Expression::Or(..)))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CompareOp::Equal => "==",
+            CompareOp::NotEqual => "!=",
+            CompareOp::Less => "<",
+            CompareOp::Greater => ">",
+            CompareOp::LessOrEqual => "<=",
+            CompareOp::GreaterOrEqual => ">=",
+        })
+    }
+}
+
+/// Renders the fragment as it appears in source: literal text verbatim and
+/// references as `$(…)`.
+impl std::fmt::Display for ExprValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprValue::Literal(s) => f.write_str(s),
+            ExprValue::Variable(name) => write!(f, "$({name})"),
+            ExprValue::PropertyFunction(body) => write!(f, "$({body})"),
+        }
+    }
+}
+
+/// The concatenated source text of an operand (without surrounding quotes).
+struct Quoted<'a>(&'a [ExprValue]);
+
+impl std::fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|part| write!(f, "{part}"))
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 //  Evaluation
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Expand `$(Var)` references in a parsed string expression.
-/// Unknown variables expand to the empty string.
-fn expand_string(parts: &[ExprValue], vars: &HashMap<String, String>) -> String {
+/// Expand `$(…)` references in a parsed string expression, evaluating
+/// property functions in `ctx`.  Unknown variables and failing property
+/// functions expand to the empty string.
+fn expand_string(parts: &[ExprValue], vars: &VarMap, ctx: &EvalContext) -> String {
     parts
         .iter()
         .map(|part| match part {
             ExprValue::Literal(s) => s.clone(),
             ExprValue::Variable(name) => vars.get(name.as_str()).cloned().unwrap_or_default(),
+            ExprValue::PropertyFunction(body) => {
+                functions::evaluate_with(body, vars, &ctx.functions).unwrap_or_default()
+            }
         })
         .collect()
 }
 
+/// Everything besides the variable map that condition evaluation may consult.
+///
+/// Currently this is the filesystem used by `Exists(…)`, the directory
+/// that relative paths are resolved against (normally the directory of the
+/// `.dproj` file) and the [`functions::Context`] of property functions.
+#[derive(Debug, Clone, Copy)]
+pub struct EvalContext<'a> {
+    /// Filesystem queried by `Exists(…)`.
+    pub file_system: &'a dyn FileSystem,
+    /// Base directory for relative `Exists(…)` paths.  When `None`, relative
+    /// paths are passed to the filesystem unchanged.
+    pub base_dir: Option<&'a Path>,
+    /// Context of property functions such as `[MSBuild]::IsOSPlatform`.
+    pub functions: functions::Context<'a>,
+}
+
+impl<'a> EvalContext<'a> {
+    /// Create a context using `file_system` and no base directory.
+    pub fn new(file_system: &'a dyn FileSystem) -> Self {
+        Self { file_system, base_dir: None, functions: functions::Context::default() }
+    }
+
+    /// Resolve relative paths against `base_dir`.
+    pub fn with_base_dir(mut self, base_dir: Option<&'a Path>) -> Self {
+        self.base_dir = base_dir;
+        self
+    }
+
+    /// Evaluate `[MSBuild]::IsOSPlatform` for `os_platform`.
+    pub fn with_os_platform(mut self, os_platform: &'a str) -> Self {
+        self.functions.os_platform = os_platform;
+        self
+    }
+
+    /// MSBuild `Exists()` semantics on an already-expanded path: empty paths
+    /// never exist, relative paths are resolved against the base directory.
+    pub fn exists(&self, path: &str) -> bool {
+        let path = path.trim();
+        if path.is_empty() {
+            return false;
+        }
+        match self.base_dir {
+            Some(dir) if !filesystem::is_absolute(path) => {
+                self.file_system.exists(&dir.join(path))
+            }
+            _ => self.file_system.exists(Path::new(path)),
+        }
+    }
+}
+
+impl Default for EvalContext<'_> {
+    /// The legacy context: every `Exists(…)` check succeeds.
+    fn default() -> Self {
+        Self::new(&AssumeExists)
+    }
+}
+
 /// Evaluate a condition expression against a set of variable bindings.
 ///
 /// `Exists(…)` always evaluates to `true` — filesystem checks are not
-/// performed.
-pub fn evaluate(expr: &Expression, vars: &HashMap<String, String>) -> bool {
+/// performed.  Use [`evaluate_with`] to check paths against a
+/// [`FileSystem`].
+pub fn evaluate(expr: &Expression, vars: &VarMap) -> bool {
+    evaluate_with(expr, vars, &EvalContext::default())
+}
+
+/// Evaluate a condition expression against a set of variable bindings,
+/// resolving `Exists(…)` through `ctx` after `$(Var)` expansion.
+pub fn evaluate_with(
+    expr: &Expression,
+    vars: &VarMap,
+    ctx: &EvalContext,
+) -> bool {
     match expr {
         Expression::Compare { lhs, op, rhs } => {
-            let l = expand_string(lhs, vars);
-            let r = expand_string(rhs, vars);
+            compare(&expand_string(lhs, vars, ctx), *op, &expand_string(rhs, vars, ctx))
+        }
+        Expression::Value(v) => parse_bool(&expand_string(v, vars, ctx)).unwrap_or(false),
+        Expression::Exists(path) => ctx.exists(&expand_string(path, vars, ctx)),
+        Expression::HasTrailingSlash(path) => {
+            expand_string(path, vars, ctx).ends_with(['\\', '/'])
+        }
+        Expression::Not(e) => !evaluate_with(e, vars, ctx),
+        Expression::And(a, b) => evaluate_with(a, vars, ctx) && evaluate_with(b, vars, ctx),
+        Expression::Or(a, b) => evaluate_with(a, vars, ctx) || evaluate_with(b, vars, ctx),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Partial evaluation
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Outcome of [`evaluate_partial`]: a three-valued result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Partial {
+    /// The condition holds whatever values the unknown variables take.
+    True,
+    /// The condition fails whatever values the unknown variables take.
+    False,
+    /// The outcome depends on unknown variables.  Holds the simplified
+    /// condition: decided sub-expressions are dropped and known variables
+    /// are replaced by their values.
+    Residual(Expression),
+}
+
+impl Partial {
+    /// Whether the condition can hold for some values of the unknown
+    /// variables, i.e. it is not [`Partial::False`].
+    pub fn is_possible(&self) -> bool {
+        !matches!(self, Partial::False)
+    }
+
+    fn from_bool(value: bool) -> Self {
+        if value { Partial::True } else { Partial::False }
+    }
+}
+
+/// Evaluate a condition while treating the variables named in `unknown`
+/// (case-insensitively) as undetermined.
+///
+/// Sub-expressions that do not depend on an unknown variable are evaluated
+/// against `vars` as in [`evaluate`]; `and` / `or` short-circuit on decided
+/// operands.  Whatever cannot be decided is returned as a
+/// [`Partial::Residual`].
+///
+/// # Example
+/// ```
+/// use dproj_rs::VarMap;
+/// use dproj_rs::condition::{Partial, evaluate_partial, parse_condition};
+///
+/// let vars: VarMap = [("Platform", "Win64")].into_iter().collect();
+/// let expr = parse_condition("'$(Config)'=='Debug' and '$(Platform)'=='Win64'").unwrap();
+/// let residual = parse_condition("'$(Config)'=='Debug'").unwrap();
+/// assert_eq!(evaluate_partial(&expr, &vars, &["Config"]), Partial::Residual(residual));
+///
+/// let expr = parse_condition("'$(Config)'=='Debug' and '$(Platform)'=='Win32'").unwrap();
+/// assert_eq!(evaluate_partial(&expr, &vars, &["Config"]), Partial::False);
+/// ```
+pub fn evaluate_partial(expr: &Expression, vars: &VarMap, unknown: &[&str]) -> Partial {
+    evaluate_partial_with(expr, vars, unknown, &EvalContext::default())
+}
+
+/// Like [`evaluate_partial`], resolving `Exists(…)` through `ctx`.
+pub fn evaluate_partial_with(
+    expr: &Expression,
+    vars: &VarMap,
+    unknown: &[&str],
+    ctx: &EvalContext,
+) -> Partial {
+    let depends = |parts: &[ExprValue]| depends_on_unknown(parts, unknown);
+    let residual = |parts: &[ExprValue]| substitute_known(parts, vars, unknown, ctx);
+
+    match expr {
+        Expression::Compare { lhs, op, rhs } if depends(lhs) || depends(rhs) => {
+            Partial::Residual(Expression::Compare { lhs: residual(lhs), op: *op, rhs: residual(rhs) })
+        }
+        Expression::Value(v) if depends(v) => Partial::Residual(Expression::Value(residual(v))),
+        Expression::Exists(path) if depends(path) => {
+            Partial::Residual(Expression::Exists(residual(path)))
+        }
+        Expression::HasTrailingSlash(path) if depends(path) => {
+            Partial::Residual(Expression::HasTrailingSlash(residual(path)))
+        }
+        Expression::Compare { .. }
+        | Expression::Value(_)
+        | Expression::Exists(_)
+        | Expression::HasTrailingSlash(_) => Partial::from_bool(evaluate_with(expr, vars, ctx)),
+        Expression::Not(e) => match evaluate_partial_with(e, vars, unknown, ctx) {
+            Partial::True => Partial::False,
+            Partial::False => Partial::True,
+            Partial::Residual(r) => Partial::Residual(Expression::Not(Box::new(r))),
+        },
+        Expression::And(a, b) => match evaluate_partial_with(a, vars, unknown, ctx) {
+            Partial::False => Partial::False,
+            Partial::True => evaluate_partial_with(b, vars, unknown, ctx),
+            Partial::Residual(ra) => match evaluate_partial_with(b, vars, unknown, ctx) {
+                Partial::False => Partial::False,
+                Partial::True => Partial::Residual(ra),
+                Partial::Residual(rb) => {
+                    Partial::Residual(Expression::And(Box::new(ra), Box::new(rb)))
+                }
+            },
+        },
+        Expression::Or(a, b) => match evaluate_partial_with(a, vars, unknown, ctx) {
+            Partial::True => Partial::True,
+            Partial::False => evaluate_partial_with(b, vars, unknown, ctx),
+            Partial::Residual(ra) => match evaluate_partial_with(b, vars, unknown, ctx) {
+                Partial::True => Partial::True,
+                Partial::False => Partial::Residual(ra),
+                Partial::Residual(rb) => {
+                    Partial::Residual(Expression::Or(Box::new(ra), Box::new(rb)))
+                }
+            },
+        },
+    }
+}
+
+fn is_unknown(name: &str, unknown: &[&str]) -> bool {
+    unknown.iter().any(|u| u.eq_ignore_ascii_case(name))
+}
+
+/// Whether any fragment reads one of the `unknown` variables.
+fn depends_on_unknown(parts: &[ExprValue], unknown: &[&str]) -> bool {
+    parts.iter().any(|part| match part {
+        ExprValue::Literal(_) => false,
+        ExprValue::Variable(name) => is_unknown(name, unknown),
+        ExprValue::PropertyFunction(body) => functions::referenced_properties(body)
+            .iter()
+            .any(|name| is_unknown(name, unknown)),
+    })
+}
+
+/// Replace every fragment that does not depend on an unknown variable by
+/// its expanded value, merging adjacent literals.
+fn substitute_known(
+    parts: &[ExprValue],
+    vars: &VarMap,
+    unknown: &[&str],
+    ctx: &EvalContext,
+) -> Vec<ExprValue> {
+    let mut out: Vec<ExprValue> = Vec::new();
+    for part in parts {
+        let part = if depends_on_unknown(std::slice::from_ref(part), unknown) {
+            part.clone()
+        } else {
+            ExprValue::Literal(expand_string(std::slice::from_ref(part), vars, ctx))
+        };
+        match (out.last_mut(), part) {
+            (_, ExprValue::Literal(s)) if s.is_empty() => {}
+            (Some(ExprValue::Literal(prev)), ExprValue::Literal(s)) => prev.push_str(&s),
+            (_, part) => out.push(part),
+        }
+    }
+    out
+}
+
+/// Apply a comparison operator to two expanded operands.
+fn compare(l: &str, op: CompareOp, r: &str) -> bool {
+    match op {
+        CompareOp::Equal => values_equal(l, r),
+        CompareOp::NotEqual => !values_equal(l, r),
+        _ => {
+            let Some(ord) = order(l, r) else {
+                return false;
+            };
             match op {
-                CompareOp::Equal => l == r,
-                CompareOp::NotEqual => l != r,
+                CompareOp::Less => ord.is_lt(),
+                CompareOp::Greater => ord.is_gt(),
+                CompareOp::LessOrEqual => ord.is_le(),
+                CompareOp::GreaterOrEqual => ord.is_ge(),
+                CompareOp::Equal | CompareOp::NotEqual => unreachable!(),
             }
         }
-        Expression::Exists(_) => true,
-        Expression::And(a, b) => evaluate(a, vars) && evaluate(b, vars),
-        Expression::Or(a, b) => evaluate(a, vars) || evaluate(b, vars),
     }
 }
 
+/// MSBuild equality: numeric, then boolean, then case-insensitive string.
+fn values_equal(l: &str, r: &str) -> bool {
+    if let (Some(a), Some(b)) = (parse_number(l), parse_number(r)) {
+        return a == b;
+    }
+    if let (Some(a), Some(b)) = (parse_bool(l), parse_bool(r)) {
+        return a == b;
+    }
+    l.eq_ignore_ascii_case(r) || l.to_lowercase() == r.to_lowercase()
+}
+
+/// MSBuild ordering: numeric, then dotted version.  `None` when the operands
+/// cannot be ordered.
+fn order(l: &str, r: &str) -> Option<std::cmp::Ordering> {
+    if let (Some(a), Some(b)) = (parse_number(l), parse_number(r)) {
+        return a.partial_cmp(&b);
+    }
+    let (a, b) = (parse_version(l)?, parse_version(r)?);
+    Some(a.cmp(&b))
+}
+
+/// Parse an MSBuild boolean: `true`/`on`/`yes`, `false`/`off`/`no`, each
+/// optionally negated with a leading `!`.
+pub(crate) fn parse_bool(s: &str) -> Option<bool> {
+    let s = s.trim();
+    let (negate, word) = match s.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = if ["true", "on", "yes"].iter().any(|w| word.eq_ignore_ascii_case(w)) {
+        true
+    } else if ["false", "off", "no"].iter().any(|w| word.eq_ignore_ascii_case(w)) {
+        false
+    } else {
+        return None;
+    };
+    Some(value != negate)
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 //  Tests
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -255,6 +958,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn string_parts_property_function() {
+        assert_eq!(
+            parse_string_parts("v$(ProductVersion.Replace('.', ''))$(Config)"),
+            vec![
+                ExprValue::Literal("v".into()),
+                ExprValue::PropertyFunction("ProductVersion.Replace('.', '')".into()),
+                ExprValue::Variable("Config".into()),
+            ]
+        );
+    }
+
     #[test]
     fn string_parts_empty() {
         assert_eq!(parse_string_parts(""), Vec::<ExprValue>::new());
@@ -367,13 +1082,174 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_not_operators() {
+        let expr = parse_condition("!Exists('a.optset')").unwrap();
+        assert!(matches!(&expr, Expression::Not(inner) if matches!(**inner, Expression::Exists(_))));
+
+        let expr = parse_condition("Not ('$(Base)'=='')").unwrap();
+        assert!(matches!(expr, Expression::Not(_)));
+
+        // `!=` must still be a comparison, not a negation.
+        let expr = parse_condition("'$(Base)' != ''").unwrap();
+        assert!(matches!(expr, Expression::Compare { op: CompareOp::NotEqual, .. }));
+    }
+
+    #[test]
+    fn parse_not_binds_tighter_than_and() {
+        let expr = parse_condition("!'$(A)'=='1' and '$(B)'=='2'").unwrap();
+        match expr {
+            Expression::And(lhs, _) => assert!(matches!(*lhs, Expression::Not(_))),
+            other => panic!("expected And(Not(..), ..), got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_relational_operators() {
+        for (input, op) in [
+            ("'$(V)' < '2'", CompareOp::Less),
+            ("'$(V)' > '2'", CompareOp::Greater),
+            ("'$(V)' <= '2'", CompareOp::LessOrEqual),
+            ("'$(V)' >= '2'", CompareOp::GreaterOrEqual),
+        ] {
+            match parse_condition(input).unwrap() {
+                Expression::Compare { op: parsed, .. } => assert_eq!(parsed, op, "{input}"),
+                other => panic!("expected Compare for {input}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_unquoted_operands() {
+        let expr = parse_condition("$(Platform) == Win32").unwrap();
+        assert_eq!(
+            expr,
+            Expression::Compare {
+                lhs: vec![ExprValue::Variable("Platform".into())],
+                op: CompareOp::Equal,
+                rhs: vec![ExprValue::Literal("Win32".into())],
+            }
+        );
+
+        let expr = parse_condition("'$(ProductVersion)' >= 22.0").unwrap();
+        assert!(matches!(expr, Expression::Compare { op: CompareOp::GreaterOrEqual, .. }));
+    }
+
+    #[test]
+    fn parse_bare_values() {
+        assert_eq!(
+            parse_condition("true").unwrap(),
+            Expression::Value(vec![ExprValue::Literal("true".into())])
+        );
+        assert_eq!(
+            parse_condition("'$(DCC_Optimize)'").unwrap(),
+            Expression::Value(vec![ExprValue::Variable("DCC_Optimize".into())])
+        );
+        assert!(matches!(
+            parse_condition("$(Debug) and false").unwrap(),
+            Expression::And(_, _)
+        ));
+    }
+
+    #[test]
+    fn parse_property_functions() {
+        let expr = parse_condition("'$(Config.Replace(')', ''))' == 'Debug'").unwrap();
+        match expr {
+            Expression::Compare { lhs, .. } => assert_eq!(
+                lhs,
+                vec![ExprValue::PropertyFunction("Config.Replace(')', '')".into())]
+            ),
+            other => panic!("expected Compare, got {other:?}"),
+        }
+
+        let expr = parse_condition(
+            "$([MSBuild]::VersionGreaterThanOrEquals($(ProductVersion), '22.0')) and '$(Base)'!=''",
+        )
+        .unwrap();
+        match expr {
+            Expression::And(lhs, _) => assert_eq!(
+                *lhs,
+                Expression::Value(vec![ExprValue::PropertyFunction(
+                    "[MSBuild]::VersionGreaterThanOrEquals($(ProductVersion), '22.0')".into()
+                )])
+            ),
+            other => panic!("expected And, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_has_trailing_slash() {
+        let expr = parse_condition("!HasTrailingSlash('$(OutDir)')").unwrap();
+        match expr {
+            Expression::Not(inner) => assert_eq!(
+                *inner,
+                Expression::HasTrailingSlash(vec![ExprValue::Variable("OutDir".into())])
+            ),
+            other => panic!("expected Not(HasTrailingSlash), got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert!(parse_condition("'$(A)' === 'b'").is_err());
+        assert!(parse_condition("'$(A)'=='b' and").is_err());
+        assert!(parse_condition("Exists(").is_err());
+    }
+
+    #[test]
+    fn parse_errors_carry_span_and_expected() {
+        let err = parse_condition("'$(A)'=='b' xor 'c'").unwrap_err();
+        assert_eq!(err.span.start, 12);
+        assert_eq!(err.found, Some('x'));
+        assert!(err.expected.contains(&"'and'".to_string()), "{:?}", err.expected);
+        assert!(err.expected.contains(&"end of input".to_string()), "{:?}", err.expected);
+
+        let err = parse_condition("'$(A)'=='b' and").unwrap_err();
+        assert_eq!(err.span, 15..15);
+        assert_eq!(err.found, None);
+        assert!(err.expected.contains(&"operand".to_string()), "{:?}", err.expected);
+
+        let err = parse_condition("'$(Foo.Trim('x')").unwrap_err();
+        assert_eq!(err.span, 16..16);
+        assert_eq!(err.expected, vec!["')'".to_string()]);
+
+        let err = err.with_property_group(3);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse condition ''$(Foo.Trim('x')' in PropertyGroup #3 at byte 16: \
+             unexpected end of input, expected ')'"
+        );
+    }
+
+    // ── Pretty-printing ──────────────────────────────────────────────────
+
+    #[test]
+    fn display_matches_ide_style() {
+        let canonical = [
+            "'$(Config)'=='Debug'",
+            "'$(Base)'!=''",
+            "('$(Platform)'=='Win32' and '$(Base)'=='true') or '$(Base_Win32)'!=''",
+            "Exists('$(BDS)\\Bin\\CodeGear.Delphi.Targets')",
+            "!HasTrailingSlash('$(OutDir)') and '$(ProductVersion)'>='22.0'",
+            "'$(A)'=='1' and ('$(B)'=='2' or '$(C)'=='3')",
+            "!('$(A)'=='1' or '$(B)'=='2')",
+            "'$(ProductVersion.Substring(0, 2))'=='23'",
+        ];
+        for cond in canonical {
+            assert_eq!(parse_condition(cond).unwrap().to_string(), cond);
+        }
+    }
+
+    #[test]
+    fn display_normalizes_spelling() {
+        let expr = parse_condition("$(Config) == Debug AND NOT ( true )").unwrap();
+        assert_eq!(expr.to_string(), "'$(Config)'=='Debug' and !'true'");
+    }
+
     // ── Evaluation ───────────────────────────────────────────────────────
 
-    fn make_vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
-        pairs
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
-            .collect()
+    fn make_vars(pairs: &[(&str, &str)]) -> VarMap {
+        pairs.iter().copied().collect()
     }
 
     #[test]
@@ -383,6 +1259,13 @@ mod tests {
         assert!(evaluate(&expr, &vars));
     }
 
+    #[test]
+    fn eval_variable_names_ignore_case() {
+        let expr = parse_condition("'$(config)|$(PLATFORM)'=='Debug|Win32'").unwrap();
+        let vars = make_vars(&[("Config", "Debug"), ("Platform", "Win32")]);
+        assert!(evaluate(&expr, &vars));
+    }
+
     #[test]
     fn eval_simple_eq_false() {
         let expr = parse_condition("'$(Config)'=='Release'").unwrap();
@@ -400,7 +1283,7 @@ mod tests {
     #[test]
     fn eval_ne_empty_without_value() {
         let expr = parse_condition("'$(Base)'!=''").unwrap();
-        let vars = HashMap::new();
+        let vars = VarMap::new();
         assert!(!evaluate(&expr, &vars));
     }
 
@@ -445,7 +1328,135 @@ mod tests {
         let expr =
             parse_condition("Exists('$(BDS)\\Bin\\CodeGear.Delphi.Targets')")
                 .unwrap();
-        assert!(evaluate(&expr, &HashMap::new()));
+        assert!(evaluate(&expr, &VarMap::new()));
+    }
+
+    #[test]
+    fn eval_exists_with_memory_fs() {
+        use crate::filesystem::MemoryFileSystem;
+
+        let fs = MemoryFileSystem::new()
+            .with_file(r"C:\Studio\Bin\CodeGear.Delphi.Targets");
+        let ctx = EvalContext::new(&fs);
+        let expr =
+            parse_condition("Exists('$(BDS)\\Bin\\CodeGear.Delphi.Targets')")
+                .unwrap();
+
+        let vars = make_vars(&[("BDS", r"C:\Studio")]);
+        assert!(evaluate_with(&expr, &vars, &ctx));
+
+        let vars = make_vars(&[("BDS", r"C:\Other")]);
+        assert!(!evaluate_with(&expr, &vars, &ctx));
+
+        // An unset variable leaves a relative path that does not exist.
+        assert!(!evaluate_with(&expr, &VarMap::new(), &ctx));
+    }
+
+    #[test]
+    fn eval_exists_resolves_relative_to_base_dir() {
+        use crate::filesystem::MemoryFileSystem;
+
+        let fs = MemoryFileSystem::new().with_file("/projects/app/Project1.deployproj");
+        let base = Path::new("/projects/app");
+        let expr = parse_condition("Exists('$(MSBuildProjectName).deployproj')").unwrap();
+        let vars = make_vars(&[("MSBuildProjectName", "Project1")]);
+
+        assert!(evaluate_with(&expr, &vars, &EvalContext::new(&fs).with_base_dir(Some(base))));
+        assert!(!evaluate_with(&expr, &vars, &EvalContext::new(&fs)));
+    }
+
+    #[test]
+    fn eval_exists_empty_path_is_false() {
+        let expr = parse_condition("Exists('$(Nothing)')").unwrap();
+        let fs = crate::filesystem::AssumeExists;
+        assert!(!evaluate_with(&expr, &VarMap::new(), &EvalContext::new(&fs)));
+    }
+
+    #[test]
+    fn eval_not() {
+        let expr = parse_condition("!('$(Config)'=='Debug')").unwrap();
+        assert!(!evaluate(&expr, &make_vars(&[("Config", "Debug")])));
+        assert!(evaluate(&expr, &make_vars(&[("Config", "Release")])));
+
+        let expr = parse_condition("not Exists('x')").unwrap();
+        assert!(!evaluate(&expr, &VarMap::new()));
+    }
+
+    #[test]
+    fn eval_equality_is_case_insensitive() {
+        let expr = parse_condition("'$(Config)'=='debug'").unwrap();
+        assert!(evaluate(&expr, &make_vars(&[("Config", "Debug")])));
+        assert!(evaluate(&parse_condition("'Ä'=='ä'").unwrap(), &VarMap::new()));
+        assert!(!evaluate(&parse_condition("'Ä'!='ä'").unwrap(), &VarMap::new()));
+    }
+
+    #[test]
+    fn eval_equality_numeric_and_boolean() {
+        let vars = make_vars(&[("V", "1.0"), ("Flag", "True"), ("Hex", "0x10")]);
+        assert!(evaluate(&parse_condition("'$(V)'=='1'").unwrap(), &vars));
+        assert!(evaluate(&parse_condition("'$(Hex)'=='16'").unwrap(), &vars));
+        assert!(evaluate(&parse_condition("'$(Flag)'=='on'").unwrap(), &vars));
+        assert!(!evaluate(&parse_condition("'$(Flag)'!='true'").unwrap(), &vars));
+    }
+
+    #[test]
+    fn eval_relational_numbers() {
+        let vars = make_vars(&[("N", "10")]);
+        assert!(evaluate(&parse_condition("'$(N)' > '9'").unwrap(), &vars));
+        assert!(evaluate(&parse_condition("$(N) >= 10").unwrap(), &vars));
+        assert!(!evaluate(&parse_condition("'$(N)' < '0x0A'").unwrap(), &vars));
+        assert!(evaluate(&parse_condition("'$(N)' <= 0x0A").unwrap(), &vars));
+    }
+
+    #[test]
+    fn eval_relational_versions() {
+        let vars = make_vars(&[("ProductVersion", "23.0.1")]);
+        assert!(evaluate(&parse_condition("'$(ProductVersion)' >= '22.0'").unwrap(), &vars));
+        assert!(evaluate(&parse_condition("'$(ProductVersion)' < '23.0.10'").unwrap(), &vars));
+        assert!(!evaluate(&parse_condition("'$(ProductVersion)' > '23.1'").unwrap(), &vars));
+    }
+
+    #[test]
+    fn eval_relational_non_numeric_is_false() {
+        let vars = make_vars(&[("Config", "Debug")]);
+        assert!(!evaluate(&parse_condition("'$(Config)' < 'Release'").unwrap(), &vars));
+        assert!(!evaluate(&parse_condition("'$(Config)' >= 'Release'").unwrap(), &vars));
+    }
+
+    #[test]
+    fn eval_bare_values() {
+        let vars = make_vars(&[("Debug", "true"), ("Off", "off"), ("Odd", "maybe")]);
+        assert!(evaluate(&parse_condition("$(Debug)").unwrap(), &vars));
+        assert!(evaluate(&parse_condition("'!$(Off)'").unwrap(), &vars));
+        assert!(!evaluate(&parse_condition("'$(Off)'").unwrap(), &vars));
+        assert!(!evaluate(&parse_condition("'$(Odd)'").unwrap(), &vars));
+        assert!(evaluate(&parse_condition("true and !false").unwrap(), &vars));
+    }
+
+    #[test]
+    fn eval_property_functions() {
+        let vars = make_vars(&[("ProductVersion", "23.0"), ("Platform", "Win64")]);
+        let cases = [
+            ("'$(ProductVersion.Substring(0, 2))' == '23'", true),
+            ("'$(Platform.ToLower())' == 'win64'", true),
+            ("'$(Platform.StartsWith('Win'))'", true),
+            ("$([MSBuild]::VersionGreaterThan($(ProductVersion), '22.0'))", true),
+            ("$([MSBuild]::Add(1, 2)) == 4", false),
+            ("'$(Platform.NoSuchMember())' == ''", true),
+        ];
+        for (input, expected) in cases {
+            let expr = parse_condition(input).unwrap();
+            assert_eq!(evaluate(&expr, &vars), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn eval_has_trailing_slash() {
+        let expr = parse_condition("HasTrailingSlash('$(OutDir)')").unwrap();
+        assert!(evaluate(&expr, &make_vars(&[("OutDir", "bin\\")])));
+        assert!(evaluate(&expr, &make_vars(&[("OutDir", "bin/")])));
+        assert!(!evaluate(&expr, &make_vars(&[("OutDir", "bin")])));
+        assert!(!evaluate(&expr, &VarMap::new()));
     }
 
     #[test]
@@ -467,6 +1478,57 @@ mod tests {
         assert!(!evaluate(&expr, &vars));
     }
 
+    // ── Partial evaluation ───────────────────────────────────────────────
+
+    fn partial(input: &str, vars: &VarMap, unknown: &[&str]) -> Partial {
+        evaluate_partial(&parse_condition(input).unwrap(), vars, unknown)
+    }
+
+    fn residual(input: &str) -> Partial {
+        Partial::Residual(parse_condition(input).unwrap())
+    }
+
+    #[test]
+    fn partial_without_unknowns_matches_evaluate() {
+        let vars = make_vars(&[("Config", "Debug")]);
+        assert_eq!(partial("'$(Config)'=='Debug'", &vars, &[]), Partial::True);
+        assert_eq!(partial("'$(Config)'=='Release'", &vars, &[]), Partial::False);
+    }
+
+    #[test]
+    fn partial_short_circuits_decided_operands() {
+        let vars = make_vars(&[("Platform", "Win64"), ("Base", "true")]);
+        let unknown = ["Config", "cfg_1", "Cfg_1_Win64"];
+        let cond = "('$(Platform)'=='Win64' and '$(Cfg_1)'=='true') or '$(Cfg_1_Win64)'!=''";
+        assert_eq!(
+            partial(cond, &vars, &unknown),
+            residual("'$(Cfg_1)'=='true' or '$(Cfg_1_Win64)'!=''")
+        );
+        assert_eq!(
+            partial("'$(Config)'=='Debug' And '$(Platform)'=='Win32'", &vars, &unknown),
+            Partial::False
+        );
+        assert_eq!(partial("'$(Config)'=='Base' or '$(Base)'!=''", &vars, &unknown), Partial::True);
+        assert_eq!(
+            partial("!('$(Config)'=='Debug' and '$(Base)'=='true')", &vars, &unknown),
+            residual("!'$(Config)'=='Debug'")
+        );
+    }
+
+    #[test]
+    fn partial_substitutes_known_variables() {
+        let vars = make_vars(&[("Platform", "Win64"), ("BDS", r"C:\Studio")]);
+        assert_eq!(
+            partial("'$(Platform)|$(Config)'=='Win64|Debug'", &vars, &["Config"]),
+            residual("'Win64|$(Config)'=='Win64|Debug'")
+        );
+        assert_eq!(
+            partial("Exists('$(BDS)\\$(Config.ToLower())')", &vars, &["Config"]),
+            residual("Exists('C:\\Studio\\$(Config.ToLower())')")
+        );
+        assert!(partial("'$(Config)'", &vars, &["CONFIG"]).is_possible());
+    }
+
     // ── Parse every real condition from our dproj files ──────────────────
 
     #[test]
@@ -514,8 +1576,36 @@ mod tests {
             assert!(
                 result.is_ok(),
                 "Failed to parse condition: {cond}\n  Error: {}",
-                result.unwrap_err()
+                result.as_ref().unwrap_err()
+            );
+            let expr = result.unwrap();
+            assert_eq!(parse_condition(&expr.to_string()).unwrap(), expr, "round-trip of {cond}");
+        }
+    }
+
+    /// Conditions taken from CodeGear `.targets` files and option sets.
+    #[test]
+    fn parse_targets_conditions() {
+        let conditions = [
+            "'$(DCC_Quiet)'!='true' and !HasTrailingSlash('$(DCC_DcuOutput)')",
+            "'$(Platform)'=='Win32' Or '$(Platform)'=='Win64'",
+            "!Exists('$(BDS)\\bin\\$(DCC_DCCCompiler).exe')",
+            "'$(BDSVersion)' >= '22.0' and '$(AppType)' != 'Package'",
+            "$(DCC_GenerateStackFrames) == true",
+            "'$(UsingDelphiRTL)'=='true' and ('$(Base_Win64)'!='' or '$(Cfg_1_Win64)'!='')",
+            "'$(MSBuildToolsVersion)' < 4.0",
+            "Not Exists('$(OutputPath)')",
+        ];
+
+        for cond in &conditions {
+            let result = parse_condition(cond);
+            assert!(
+                result.is_ok(),
+                "Failed to parse condition: {cond}\n  Error: {}",
+                result.as_ref().unwrap_err()
             );
+            let expr = result.unwrap();
+            assert_eq!(parse_condition(&expr.to_string()).unwrap(), expr, "round-trip of {cond}");
         }
     }
 }
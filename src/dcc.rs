@@ -0,0 +1,423 @@
+//! Typed views of [`DccOptions`] settings.
+//!
+//! The fields of [`DccOptions`] hold the text of the `DCC_*` elements as
+//! written.  The methods in this module read them as the value the IDE
+//! means — `DCC_Optimize` as a `bool`, `DCC_Alignment` as an
+//! [`Alignment`], `DCC_ImageBase` as a hexadecimal address — and write them
+//! back in the form the IDE uses:
+//!
+//! ```
+//! use dproj_rs::dcc::{Alignment, DebugInfo};
+//! use dproj_rs::dproj::DccOptions;
+//!
+//! let mut dcc = DccOptions::default();
+//! dcc.set_optimize(true);
+//! dcc.set_alignment(Alignment::QuadWord);
+//! dcc.set_image_base(0x0040_0000);
+//! assert_eq!(dcc.optimize.as_deref(), Some("true"));
+//! assert_eq!(dcc.alignment.as_deref(), Some("8"));
+//! assert_eq!(dcc.image_base.as_deref(), Some("00400000"));
+//!
+//! dcc.debug_information = Some("2".into());
+//! assert_eq!(dcc.debug_information(), Ok(Some(DebugInfo::Full)));
+//! dcc.debug_information = Some("maybe".into());
+//! assert!(dcc.debug_information().is_err());
+//! ```
+//!
+//! Getters return `Ok(None)` when the setting is absent or empty and
+//! [`InvalidValue`] when its text cannot be read.  Booleans accept the
+//! MSBuild spellings (`True`, `on`, `no`, …); alignment and debug
+//! information also accept the `true`/`false` of older project versions.
+
+use crate::condition;
+use crate::dproj::DccOptions;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Error
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// A setting whose text is not a valid value of its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidValue {
+    /// The MSBuild tag, e.g. `"DCC_Alignment"`.
+    pub property: String,
+    /// The text as written.
+    pub value: String,
+    /// Description of the accepted values.
+    pub expected: &'static str,
+}
+
+impl std::fmt::Display for InvalidValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid value '{}' for {}: expected {}",
+            self.value, self.property, self.expected
+        )
+    }
+}
+
+impl std::error::Error for InvalidValue {}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Value types
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Record field alignment (`DCC_Alignment`, `{$A}`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// Packed records (`{$A1}`, `{$A-}`).
+    Byte,
+    Word,
+    DoubleWord,
+    /// The default (`{$A8}`, `{$A+}`).
+    QuadWord,
+}
+
+impl Alignment {
+    /// The alignment in bytes.
+    pub fn bytes(self) -> u8 {
+        match self {
+            Self::Byte => 1,
+            Self::Word => 2,
+            Self::DoubleWord => 4,
+            Self::QuadWord => 8,
+        }
+    }
+}
+
+/// Debug information level (`DCC_DebugInformation`, `{$D}`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugInfo {
+    None,
+    /// Line numbers only.
+    Limited,
+    Full,
+}
+
+/// Symbol reference information (`DCC_SymbolReferenceInfo`, `{$Y}`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolReferenceInfo {
+    None,
+    /// Declarations only (`{$YD}`).
+    Definitions,
+    /// Declarations and references (`{$Y+}`).
+    References,
+}
+
+/// Minimum size of enumerated types (`DCC_MinimumEnumSize`, `{$Z}`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumSize {
+    Byte,
+    Word,
+    DoubleWord,
+}
+
+/// Function inlining (`DCC_Inlining`, `{$INLINE}`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inlining {
+    Off,
+    On,
+    /// Also inline small functions not marked `inline`.
+    Auto,
+}
+
+/// Detail of the linker map file (`DCC_MapFile`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapFile {
+    Off,
+    Segments,
+    Publics,
+    Detailed,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Codecs – text ↔ value
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Conversion between the text of a setting and its value.
+trait Codec {
+    type Value;
+    /// Description of the accepted values, for [`InvalidValue`].
+    const EXPECTED: &'static str;
+    fn decode(text: &str) -> Option<Self::Value>;
+    /// The text the IDE writes for `value`.
+    fn encode(value: &Self::Value) -> String;
+}
+
+struct Bool;
+struct Decimal;
+/// Hexadecimal, written with eight digits and no prefix.
+struct Hex;
+/// `min,max`, as in the older `DCC_StackSize`.
+struct DecimalPair;
+
+impl Codec for Bool {
+    type Value = bool;
+    const EXPECTED: &'static str = "true or false";
+    fn decode(text: &str) -> Option<bool> {
+        condition::parse_bool(text)
+    }
+    fn encode(value: &bool) -> String {
+        value.to_string()
+    }
+}
+
+impl Codec for Decimal {
+    type Value = u64;
+    const EXPECTED: &'static str = "a decimal number";
+    fn decode(text: &str) -> Option<u64> {
+        text.parse().ok()
+    }
+    fn encode(value: &u64) -> String {
+        value.to_string()
+    }
+}
+
+impl Codec for Hex {
+    type Value = u64;
+    const EXPECTED: &'static str = "a hexadecimal number";
+    fn decode(text: &str) -> Option<u64> {
+        let digits = text
+            .strip_prefix('$')
+            .or_else(|| text.strip_prefix("0x"))
+            .or_else(|| text.strip_prefix("0X"))
+            .unwrap_or(text);
+        u64::from_str_radix(digits, 16).ok()
+    }
+    fn encode(value: &u64) -> String {
+        format!("{value:08X}")
+    }
+}
+
+impl Codec for DecimalPair {
+    type Value = (u64, u64);
+    const EXPECTED: &'static str = "two decimal numbers separated by a comma";
+    fn decode(text: &str) -> Option<(u64, u64)> {
+        let (min, max) = text.split_once(',')?;
+        Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+    }
+    fn encode(&(min, max): &(u64, u64)) -> String {
+        format!("{min},{max}")
+    }
+}
+
+/// Implement [`Codec`] for an enum written as one of a fixed set of
+/// (case-insensitive) words.  Extra `"text" => Variant` pairs after the
+/// `;` are accepted when reading but never written.
+macro_rules! keyword_codec {
+    ($ty:ident, $expected:literal, { $($text:literal => $variant:ident),+ $(,)? }
+     $(; $($alias:literal => $alias_variant:ident),+)?) => {
+        impl Codec for $ty {
+            type Value = $ty;
+            const EXPECTED: &'static str = $expected;
+            fn decode(text: &str) -> Option<$ty> {
+                $(if text.eq_ignore_ascii_case($text) { return Some($ty::$variant); })+
+                $($(if text.eq_ignore_ascii_case($alias) { return Some($ty::$alias_variant); })+)?
+                None
+            }
+            fn encode(value: &$ty) -> String {
+                match value {
+                    $($ty::$variant => $text,)+
+                }
+                .to_string()
+            }
+        }
+    };
+}
+
+keyword_codec!(Alignment, "1, 2, 4 or 8", {
+    "1" => Byte, "2" => Word, "4" => DoubleWord, "8" => QuadWord,
+}; "false" => Byte, "true" => QuadWord);
+keyword_codec!(DebugInfo, "0, 1 or 2", {
+    "0" => None, "1" => Limited, "2" => Full,
+}; "false" => None, "true" => Full);
+keyword_codec!(SymbolReferenceInfo, "0, 1 or 2", {
+    "0" => None, "1" => Definitions, "2" => References,
+});
+keyword_codec!(EnumSize, "1, 2 or 4", {
+    "1" => Byte, "2" => Word, "4" => DoubleWord,
+});
+keyword_codec!(Inlining, "off, on or auto", {
+    "off" => Off, "on" => On, "auto" => Auto,
+});
+keyword_codec!(MapFile, "0, 1, 2 or 3", {
+    "0" => Off, "1" => Segments, "2" => Publics, "3" => Detailed,
+});
+
+/// Read `text`, the value of `property`, with codec `C`.
+fn decode<C: Codec>(text: &Option<String>, property: &'static str) -> Result<Option<C::Value>, InvalidValue> {
+    let Some(text) = text.as_deref().map(str::trim).filter(|t| !t.is_empty()) else {
+        return Ok(None);
+    };
+    C::decode(text).map(Some).ok_or_else(|| InvalidValue {
+        property: property.to_string(),
+        value: text.to_string(),
+        expected: C::EXPECTED,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Accessors
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Generate a getter named after the field and a `set_` method for each
+/// `field, setter: "Tag" => Value as Codec` line.
+macro_rules! typed_options {
+    ($($field:ident, $setter:ident: $tag:literal => $value:ty as $codec:ty;)+) => {
+        impl DccOptions {
+            $(
+                #[doc = concat!("The value of `", $tag, "`; see the [module documentation](self).")]
+                pub fn $field(&self) -> Result<Option<$value>, InvalidValue> {
+                    decode::<$codec>(&self.$field, $tag)
+                }
+
+                #[doc = concat!("Set `", $tag, "` to the text the IDE writes for `value`.")]
+                pub fn $setter(&mut self, value: $value) {
+                    self.$field = Some(<$codec>::encode(&value));
+                }
+            )+
+        }
+    };
+}
+
+typed_options! {
+    // ── Code generation ──
+    optimize, set_optimize: "DCC_Optimize" => bool as Bool;
+    alignment, set_alignment: "DCC_Alignment" => Alignment as Alignment;
+    minimum_enum_size, set_minimum_enum_size: "DCC_MinimumEnumSize" => EnumSize as EnumSize;
+    code_page, set_code_page: "DCC_CodePage" => u64 as Decimal;
+    inlining, set_inlining: "DCC_Inlining" => Inlining as Inlining;
+    generate_stack_frames, set_generate_stack_frames: "DCC_GenerateStackFrames" => bool as Bool;
+    generate_pic_code, set_generate_pic_code: "DCC_GeneratePICCode" => bool as Bool;
+    generate_android_app_bundle_file, set_generate_android_app_bundle_file: "DCC_GenerateAndroidAppBundleFile" => bool as Bool;
+    generate_osx_universal_binary_file, set_generate_osx_universal_binary_file: "DCC_GenerateOSXUniversalBinaryFile" => bool as Bool;
+
+    // ── Compiler switches ──
+    e, set_e: "DCC_E" => bool as Bool;
+    n, set_n: "DCC_N" => bool as Bool;
+    s, set_s: "DCC_S" => bool as Bool;
+    f, set_f: "DCC_F" => bool as Bool;
+    k, set_k: "DCC_K" => bool as Bool;
+    extended_syntax, set_extended_syntax: "DCC_ExtendedSyntax" => bool as Bool;
+    long_strings, set_long_strings: "DCC_LongStrings" => bool as Bool;
+    open_string_params, set_open_string_params: "DCC_OpenStringParams" => bool as Bool;
+    strict_var_strings, set_strict_var_strings: "DCC_StrictVarStrings" => bool as Bool;
+    typed_at_parameter, set_typed_at_parameter: "DCC_TypedAtParameter" => bool as Bool;
+    full_boolean_evaluations, set_full_boolean_evaluations: "DCC_FullBooleanEvaluations" => bool as Bool;
+    writeable_constants, set_writeable_constants: "DCC_WriteableConstants" => bool as Bool;
+    run_time_type_info, set_run_time_type_info: "DCC_RunTimeTypeInfo" => bool as Bool;
+    pentium_safe_divide, set_pentium_safe_divide: "DCC_PentiumSafeDivide" => bool as Bool;
+
+    // ── Runtime checks ──
+    io_checking, set_io_checking: "DCC_IOChecking" => bool as Bool;
+    integer_overflow_check, set_integer_overflow_check: "DCC_IntegerOverflowCheck" => bool as Bool;
+    range_checking, set_range_checking: "DCC_RangeChecking" => bool as Bool;
+    assertions_at_runtime, set_assertions_at_runtime: "DCC_AssertionsAtRuntime" => bool as Bool;
+    imported_data_references, set_imported_data_references: "DCC_ImportedDataReferences" => bool as Bool;
+
+    // ── Debug ──
+    debug_information, set_debug_information: "DCC_DebugInformation" => DebugInfo as DebugInfo;
+    local_debug_symbols, set_local_debug_symbols: "DCC_LocalDebugSymbols" => bool as Bool;
+    symbol_reference_info, set_symbol_reference_info: "DCC_SymbolReferenceInfo" => SymbolReferenceInfo as SymbolReferenceInfo;
+    debug_dcus, set_debug_dcus: "DCC_DebugDCUs" => bool as Bool;
+    debug_info_in_exe, set_debug_info_in_exe: "DCC_DebugInfoInExe" => bool as Bool;
+    debug_info_in_tds, set_debug_info_in_tds: "DCC_DebugInfoInTds" => bool as Bool;
+    remote_debug, set_remote_debug: "DCC_RemoteDebug" => bool as Bool;
+
+    // ── Warnings & hints ──
+    hints, set_hints: "DCC_Hints" => bool as Bool;
+    warnings, set_warnings: "DCC_Warnings" => bool as Bool;
+    show_general_messages, set_show_general_messages: "DCC_ShowGeneralMessages" => bool as Bool;
+
+    // ── Linker / PE ──
+    console_target, set_console_target: "DCC_ConsoleTarget" => bool as Bool;
+    image_base, set_image_base: "DCC_ImageBase" => u64 as Hex;
+    map_file, set_map_file: "DCC_MapFile" => MapFile as MapFile;
+    stack_size, set_stack_size: "DCC_StackSize" => (u64, u64) as DecimalPair;
+    max_stack_size, set_max_stack_size: "DCC_MaxStackSize" => u64 as Decimal;
+    min_stack_size, set_min_stack_size: "DCC_MinStackSize" => u64 as Decimal;
+    nx_compat, set_nx_compat: "DCC_NXCompat" => bool as Bool;
+    dynamic_base, set_dynamic_base: "DCC_DynamicBase" => bool as Bool;
+    high_entropy_va, set_high_entropy_va: "DCC_HighEntropyVa" => bool as Bool;
+    ts_aware, set_ts_aware: "DCC_TSAware" => bool as Bool;
+    large_address_aware, set_large_address_aware: "DCC_LargeAddressAware" => bool as Bool;
+    allow_undefined, set_allow_undefined: "DCC_AllowUndefined" => bool as Bool;
+
+    // ── Output control ──
+    output_xml_documentation, set_output_xml_documentation: "DCC_OutputXMLDocumentation" => bool as Bool;
+    output_dependencies, set_output_dependencies: "DCC_OutputDependencies" => bool as Bool;
+    output_drc_file, set_output_drc_file: "DCC_OutputDRCFile" => bool as Bool;
+    old_dos_file_names, set_old_dos_file_names: "DCC_OldDosFileNames" => bool as Bool;
+    xml_output, set_xml_output: "DCC_XmlOutput" => bool as Bool;
+    remove_tmp_lnk_file, set_remove_tmp_lnk_file: "DCC_RemoveTmpLnkFile" => bool as Bool;
+    include_dcus_in_uses_completion, set_include_dcus_in_uses_completion: "DCC_IncludeDCUsInUsesCompletion" => bool as Bool;
+    use_msbuild_externally, set_use_msbuild_externally: "DCC_UseMSBuildExternally" => bool as Bool;
+    legacy_ifend, set_legacy_ifend: "DCC_LegacyIFEND" => bool as Bool;
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dproj;
+
+    #[test]
+    fn reads_example_project() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let debug = dproj.active_property_group_for("Debug", "Win32").unwrap().dcc_options;
+        assert_eq!(debug.optimize(), Ok(Some(false)));
+        assert_eq!(debug.alignment(), Ok(Some(Alignment::Byte)));
+        assert_eq!(debug.minimum_enum_size(), Ok(Some(EnumSize::Word)));
+        assert_eq!(debug.inlining(), Ok(Some(Inlining::Auto)));
+        assert_eq!(debug.image_base(), Ok(Some(0x0040_0001)));
+        assert_eq!(debug.max_stack_size(), Ok(Some(1_048_577)));
+        assert_eq!(debug.map_file(), Ok(Some(MapFile::Segments)));
+    }
+
+    #[test]
+    fn accepts_legacy_and_msbuild_spellings() {
+        let mut dcc = DccOptions {
+            optimize: Some(" True ".into()),
+            range_checking: Some("off".into()),
+            alignment: Some("true".into()),
+            debug_information: Some("False".into()),
+            image_base: Some("$00400000".into()),
+            stack_size: Some("16384,1048576".into()),
+            ..Default::default()
+        };
+        assert_eq!(dcc.optimize(), Ok(Some(true)));
+        assert_eq!(dcc.range_checking(), Ok(Some(false)));
+        assert_eq!(dcc.alignment(), Ok(Some(Alignment::QuadWord)));
+        assert_eq!(dcc.debug_information(), Ok(Some(DebugInfo::None)));
+        assert_eq!(dcc.image_base(), Ok(Some(0x0040_0000)));
+        assert_eq!(dcc.stack_size(), Ok(Some((16384, 1_048_576))));
+
+        dcc.set_alignment(Alignment::QuadWord);
+        dcc.set_debug_information(DebugInfo::Limited);
+        dcc.set_stack_size((16384, 2_097_152));
+        assert_eq!(dcc.alignment.as_deref(), Some("8"));
+        assert_eq!(dcc.debug_information.as_deref(), Some("1"));
+        assert_eq!(dcc.stack_size.as_deref(), Some("16384,2097152"));
+    }
+
+    #[test]
+    fn reports_invalid_values() {
+        let dcc = DccOptions {
+            alignment: Some("16".into()),
+            image_base: Some("0x40_0000".into()),
+            max_stack_size: Some("1M".into()),
+            optimize: Some("  ".into()),
+            ..Default::default()
+        };
+        let err = dcc.alignment().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value '16' for DCC_Alignment: expected 1, 2, 4 or 8");
+        assert_eq!(dcc.image_base().unwrap_err().property, "DCC_ImageBase");
+        assert_eq!(dcc.max_stack_size().unwrap_err().value, "1M");
+        assert_eq!(dcc.optimize(), Ok(None));
+        assert_eq!(dcc.range_checking(), Ok(None));
+    }
+}
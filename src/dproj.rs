@@ -1,66 +1,302 @@
 #![allow(dead_code)]
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::condition;
+use crate::filesystem::{AssumeExists, DiskFileSystem, FileSystem};
+use crate::functions;
+use crate::list::PropertyList;
+use crate::local::DprojLocal;
+use crate::optset::{AttachedOptionSet, OptionSet};
+use crate::platform::TargetPlatform;
+use crate::provenance::{PropertyAssignment, Provenance};
+use crate::vars::VarMap;
 
 // ═══════════════════════════════════════════════════════════════════════════════
 //  Helpers
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Expand `$(Var)` references in a raw string value using the given variable
-/// map.  Unknown variables expand to the empty string.
-fn expand_msbuild_vars(s: &str, vars: &HashMap<String, String>) -> String {
-    let mut result = String::with_capacity(s.len());
-    let mut chars = s.chars().peekable();
+/// Expand `$(…)` references — plain variables and property functions — in a
+/// raw string value using the given variable map.  Unknown variables expand
+/// to the empty string.
+fn expand_msbuild_vars(s: &str, vars: &VarMap, ctx: &functions::Context) -> String {
+    functions::expand_with(s, vars, ctx)
+}
 
-    while let Some(c) = chars.next() {
-        if c == '$' && chars.peek() == Some(&'(') {
-            chars.next(); // consume '('
-            let var_name: String = chars.by_ref().take_while(|&ch| ch != ')').collect();
-            if let Some(val) = vars.get(&var_name) {
-                result.push_str(val);
-            }
-        } else {
-            result.push(c);
-        }
-    }
+/// Escape `&`, `<`, `>` and `"` for use in XML text or attribute values.
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The line ending used by `source`: `"\r\n"` if it contains one, else
+/// `"\n"`.
+fn line_ending(source: &str) -> &'static str {
+    if source.contains("\r\n") { "\r\n" } else { "\n" }
+}
 
-    result
+/// The whitespace between the start of the line containing byte `pos` and
+/// `pos`, or `None` if anything else precedes `pos` on that line.
+fn indentation_at(source: &str, pos: usize) -> Option<&str> {
+    let line_start = source[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &source[line_start..pos];
+    prefix.chars().all(|c| c == ' ' || c == '\t').then_some(prefix)
+}
+
+/// Widen `range` to whole lines, including the line break, when nothing but
+/// whitespace shares those lines with it; otherwise return it unchanged.
+/// Used to remove an element without leaving an empty line behind.
+fn line_span(source: &str, range: std::ops::Range<usize>) -> std::ops::Range<usize> {
+    let Some(indent) = indentation_at(source, range.start) else {
+        return range;
+    };
+    let rest = &source[range.end..];
+    let trailing = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    let line_break = match &rest[trailing..] {
+        r if r.starts_with("\r\n") => 2,
+        r if r.starts_with('\n') => 1,
+        "" => 0,
+        _ => return range,
+    };
+    range.start - indent.len()..range.end + trailing + line_break
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
 //  Error
 // ═══════════════════════════════════════════════════════════════════════════════
 
-#[derive(Debug, Clone)]
-pub struct DprojError {
-    pub message: String,
+/// Errors raised while loading, evaluating or editing a project.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DprojError {
+    /// Reading or writing a file failed.
+    Io {
+        /// The file, when known.
+        path: Option<std::path::PathBuf>,
+        source: std::io::Error,
+    },
+    /// A file is not well-formed XML.
+    Xml {
+        /// The file, when known.
+        path: Option<std::path::PathBuf>,
+        /// 1-based line of the error.
+        line: u32,
+        /// 1-based column of the error.
+        column: u32,
+        source: roxmltree::Error,
+    },
+    /// A `Condition` attribute could not be parsed.
+    Condition {
+        /// The `<Import>`ed file containing the condition; `None` for the
+        /// project itself.
+        file: Option<std::path::PathBuf>,
+        source: condition::ConditionError,
+    },
+    /// No `<BuildConfiguration>` has the requested name.
+    ConfigurationNotFound { name: String },
+    /// The operation resolves paths against the project directory, but the
+    /// project was not loaded from a file.
+    NoDirectory {
+        /// What was being resolved, e.g. `"main source"`.
+        operation: &'static str,
+    },
+    /// An element the operation needs is missing.
+    ElementNotFound {
+        /// The element, e.g. `"<MainSource>"`.
+        element: String,
+        /// Where it was looked for, e.g. `"any unconditional PropertyGroup"`.
+        context: String,
+    },
+    /// An element the operation would add is already present.
+    ElementExists {
+        /// The element, e.g. `"<DCC_Define>"`.
+        element: String,
+        /// Where it was found, e.g. `"PropertyGroup #3"`.
+        context: String,
+    },
+    /// A `<PropertyGroup>` index is out of bounds.
+    IndexOutOfBounds { index: usize, len: usize },
+    /// Property values reference each other in a cycle
+    /// ([`EvaluationMode::Deferred`]).
+    ReferenceCycle(functions::ReferenceCycle),
+    /// Property values reference undefined variables
+    /// ([`DprojBuilder::strict_variables`]).
+    UnresolvedVariables(UnresolvedVariables),
+    /// An `<Import>`ed file could not be loaded or evaluated.
+    Import {
+        /// The `Project` attribute, as written.
+        project: String,
+        source: Box<DprojError>,
+    },
+    /// The option set is already attached to the configuration.
+    OptionSetAttached { path: String, key: String },
+    /// The option set is not attached to the configuration.
+    OptionSetNotAttached { path: String, key: String },
+    /// A setting's text is not a valid value of its type.
+    InvalidValue(crate::dcc::InvalidValue),
+    /// A platform name is not a [`TargetPlatform`].
+    UnknownPlatform(crate::platform::UnknownPlatform),
 }
 
 impl DprojError {
-    pub fn new(message: impl Into<String>) -> Self {
-        Self { message: message.into() }
+    /// Attribute an IO or XML error without a path to the file at `path`.
+    pub(crate) fn in_file(self, file: &std::path::Path) -> Self {
+        match self {
+            Self::Io { path: None, source } => Self::Io { path: Some(file.to_path_buf()), source },
+            Self::Xml { path: None, line, column, source } => {
+                Self::Xml { path: Some(file.to_path_buf()), line, column, source }
+            }
+            other => other,
+        }
     }
 }
 
 impl std::fmt::Display for DprojError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        match self {
+            Self::Io { path: Some(path), source } => write!(f, "{}: {source}", path.display()),
+            Self::Io { path: None, source } => write!(f, "IO Error: {source}"),
+            Self::Xml { path: Some(path), source, .. } => {
+                write!(f, "{}: XML Error: {source}", path.display())
+            }
+            Self::Xml { path: None, source, .. } => write!(f, "XML Error: {source}"),
+            Self::Condition { file: Some(file), source } => write!(f, "{}: {source}", file.display()),
+            Self::Condition { file: None, source } => write!(f, "{source}"),
+            Self::ConfigurationNotFound { name } => {
+                write!(f, "Build configuration '{name}' not found")
+            }
+            Self::NoDirectory { operation } => {
+                write!(f, "Cannot resolve {operation}: no directory (use Dproj::from_file)")
+            }
+            Self::ElementNotFound { element, context } => {
+                write!(f, "No {element} element found in {context}")
+            }
+            Self::ElementExists { element, context } => {
+                write!(f, "{context} already has a {element} element")
+            }
+            Self::IndexOutOfBounds { index, len } => {
+                write!(f, "PropertyGroup index {index} out of bounds ({len} PropertyGroups)")
+            }
+            Self::ReferenceCycle(cycle) => write!(f, "{cycle}"),
+            Self::UnresolvedVariables(unresolved) => write!(f, "{unresolved}"),
+            Self::Import { project, source } => write!(f, "Import of \"{project}\": {source}"),
+            Self::OptionSetAttached { path, key } => {
+                write!(f, "Option set '{path}' is already attached to {key}")
+            }
+            Self::OptionSetNotAttached { path, key } => {
+                write!(f, "Option set '{path}' is not attached to {key}")
+            }
+            Self::InvalidValue(invalid) => write!(f, "{invalid}"),
+            Self::UnknownPlatform(unknown) => write!(f, "{unknown}"),
+        }
     }
 }
 
-impl std::error::Error for DprojError {}
+impl std::error::Error for DprojError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Xml { source, .. } => Some(source),
+            Self::Condition { source, .. } => Some(source),
+            Self::ReferenceCycle(cycle) => Some(cycle),
+            Self::UnresolvedVariables(unresolved) => Some(unresolved),
+            Self::Import { source, .. } => Some(source.as_ref()),
+            Self::InvalidValue(invalid) => Some(invalid),
+            Self::UnknownPlatform(unknown) => Some(unknown),
+            _ => None,
+        }
+    }
+}
 
 impl From<roxmltree::Error> for DprojError {
     fn from(error: roxmltree::Error) -> Self {
-        Self::new(format!("XML Error: {error}"))
+        let pos = error.pos();
+        Self::Xml { path: None, line: pos.row, column: pos.col, source: error }
     }
 }
 
 impl From<std::io::Error> for DprojError {
     fn from(error: std::io::Error) -> Self {
-        Self::new(format!("IO Error: {error}"))
+        Self::Io { path: None, source: error }
+    }
+}
+
+impl From<crate::dcc::InvalidValue> for DprojError {
+    fn from(error: crate::dcc::InvalidValue) -> Self {
+        Self::InvalidValue(error)
+    }
+}
+
+impl From<crate::platform::UnknownPlatform> for DprojError {
+    fn from(error: crate::platform::UnknownPlatform) -> Self {
+        Self::UnknownPlatform(error)
+    }
+}
+
+impl From<condition::ConditionError> for DprojError {
+    fn from(error: condition::ConditionError) -> Self {
+        Self::Condition { file: None, source: error }
+    }
+}
+
+/// A `$(…)` reference to a variable that is not defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedReference {
+    /// The referenced variable, e.g. `"BDSCOMMONDIR"`.
+    pub variable: String,
+    /// The property whose value contains the reference.
+    pub property: String,
+    /// Index of the `<PropertyGroup>` the property is assigned in.
+    pub group: usize,
+    /// The `<Import>`ed file containing the group; `None` for the project
+    /// itself.
+    pub file: Option<std::path::PathBuf>,
+}
+
+impl std::fmt::Display for UnresolvedReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "$({}) in {} (PropertyGroup #{}", self.variable, self.property, self.group)?;
+        if let Some(file) = &self.file {
+            write!(f, " of {}", file.display())?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Error returned in [`strict_variables`](DprojBuilder::strict_variables)
+/// mode when property values reference undefined variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedVariables {
+    pub references: Vec<UnresolvedReference>,
+}
+
+impl std::fmt::Display for UnresolvedVariables {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unresolved variables: ")?;
+        for (i, reference) in self.references.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{reference}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnresolvedVariables {}
+
+impl From<UnresolvedVariables> for DprojError {
+    fn from(error: UnresolvedVariables) -> Self {
+        Self::UnresolvedVariables(error)
+    }
+}
+
+impl From<functions::ReferenceCycle> for DprojError {
+    fn from(error: functions::ReferenceCycle) -> Self {
+        Self::ReferenceCycle(error)
     }
 }
 
@@ -82,10 +318,37 @@ pub struct Dproj {
     /// paths (e.g. `MainSource`, `DCC_ExeOutput`).  `None` when created via
     /// [`Dproj::parse`] without a file path.
     directory: Option<std::path::PathBuf>,
+    /// Full path of the `.dproj` file.  `None` when created via
+    /// [`Dproj::parse`].
+    path: Option<std::path::PathBuf>,
     /// External environment variables (e.g. from `rsvars.bat` or the system
     /// environment) that are seeded into the `$(Var)` expansion map before
     /// property group evaluation.
-    env: HashMap<String, String>,
+    env: VarMap,
+    /// Filesystem consulted by `Exists(…)` conditions.  Relative paths are
+    /// resolved against [`directory`](Self::directory).  The real disk for
+    /// [`Dproj::from_file`], [`AssumeExists`] for [`Dproj::parse`], which
+    /// has no directory to resolve against.
+    file_system: Arc<dyn FileSystem>,
+    /// Parsed `Condition` attributes, shared between clones.  Keyed by the
+    /// condition text, so edits to [`project`](Self::project) never see a
+    /// stale expression.
+    conditions: Arc<condition::ConditionCache>,
+    /// How `$(…)` references in property values are expanded.
+    evaluation_mode: EvaluationMode,
+    /// Fail on `$(…)` references to unknown variables instead of expanding
+    /// them to the empty string.
+    strict_variables: bool,
+    /// Evaluate the `<PropertyGroup>`s of `<Import>`ed files.
+    resolve_imports: bool,
+    /// The operating system `[MSBuild]::IsOSPlatform` reports.
+    os_platform: String,
+    /// `MSBuildStartupDirectory`: the current directory for
+    /// [`Dproj::from_file`], unset for [`Dproj::parse`], or as set by
+    /// [`DprojBuilder::startup_directory`].
+    startup_directory: Option<std::path::PathBuf>,
+    /// The `.dproj.local` file, if [`DprojBuilder::local_file`] found one.
+    local: Option<DprojLocal>,
     pub project: DprojProject,
 }
 
@@ -97,19 +360,36 @@ impl Dproj {
             let doc = roxmltree::Document::parse(&source)?;
             DprojProject::parse(doc.root_element())?
         };
-        Ok(Self { source, directory: None, env: HashMap::new(), project })
+        Ok(Self {
+            source,
+            directory: None,
+            path: None,
+            env: VarMap::new(),
+            file_system: Arc::new(AssumeExists),
+            conditions: Arc::default(),
+            evaluation_mode: EvaluationMode::default(),
+            strict_variables: false,
+            resolve_imports: false,
+            os_platform: "Windows".into(),
+            startup_directory: None,
+            local: None,
+            project,
+        })
     }
 
     /// Load a `.dproj` file from disk.
     pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, DprojError> {
         let path = path.as_ref();
         let source = std::fs::read_to_string(path)
-            .map_err(|e| DprojError::new(format!("{}: {e}", path.display())))?;
-        let mut dproj = Self::parse(source)?;
-        dproj.directory = path
-            .canonicalize()
-            .ok()
+            .map_err(|e| DprojError::from(e).in_file(path))?;
+        let mut dproj = Self::parse(source).map_err(|e| e.in_file(path))?;
+        dproj.file_system = Arc::new(DiskFileSystem);
+        dproj.path = path.canonicalize().ok();
+        dproj.directory = dproj
+            .path
+            .as_deref()
             .and_then(|p| p.parent().map(|d| d.to_path_buf()));
+        dproj.startup_directory = std::env::current_dir().ok();
         Ok(dproj)
     }
 
@@ -135,57 +415,162 @@ impl Dproj {
         value: &str,
     ) -> Result<(), DprojError> {
         let doc = roxmltree::Document::parse(&self.source)?;
-
-        let pg_node = doc
-            .root_element()
-            .children()
-            .filter(|n| n.is_element() && n.tag_name().name() == "PropertyGroup")
-            .nth(pg_index)
-            .ok_or_else(|| {
-                DprojError::new(format!(
-                    "PropertyGroup index {pg_index} out of bounds"
-                ))
-            })?;
+        let pg_node = self.property_group_node(&doc, pg_index)?;
 
         let element = pg_node
             .children()
             .find(|n| n.is_element() && n.tag_name().name() == tag)
-            .ok_or_else(|| {
-                DprojError::new(format!(
-                    "Element <{tag}> not found in PropertyGroup[{pg_index}]"
-                ))
+            .ok_or_else(|| DprojError::ElementNotFound {
+                element: format!("<{tag}>"),
+                context: format!("PropertyGroup #{pg_index}"),
             })?;
 
+        let escaped = escape_xml(value);
+        let conditional = element.attribute("Condition").is_some();
         if let Some(text_node) = element.children().find(|n| n.is_text()) {
             // Element has text content – replace just the text span.
             let range = text_node.range();
-            self.source.replace_range(range, value);
+            self.source.replace_range(range, &escaped);
         } else {
             // Self-closing or empty element – rewrite the whole element tag.
             let range = element.range();
             let name = element.tag_name().name();
             let attrs: String = element
                 .attributes()
-                .map(|a| format!(" {}=\"{}\"", a.name(), a.value()))
+                .map(|a| format!(" {}=\"{}\"", a.name(), escape_xml(a.value())))
                 .collect();
             self.source
-                .replace_range(range, &format!("<{name}{attrs}>{value}</{name}>"));
+                .replace_range(range, &format!("<{name}{attrs}>{escaped}</{name}>"));
         }
 
+        if conditional || !self.project.property_groups[pg_index].elements.is_empty() {
+            // The group's element list must follow the edit as well.
+            return self.reparse();
+        }
         // Targeted in-memory update — no full reparse needed.
-        let pg = &mut self.project.property_groups[pg_index];
-        if set_project_property(tag, value, &mut pg.project_properties) { return Ok(()); }
-        if set_dcc_option(tag, value, &mut pg.dcc_options) { return Ok(()); }
-        if set_brcc_option(tag, value, &mut pg.brcc_options) { return Ok(()); }
-        if set_build_event(tag, value, &mut pg.build_events) { return Ok(()); }
-        if set_ver_info(tag, value, &mut pg.ver_info) { return Ok(()); }
-        if set_platform_packaging(tag, value, &mut pg.platform_packaging) { return Ok(()); }
-        if set_debugger_option(tag, value, &mut pg.debugger_options) { return Ok(()); }
-        pg.other.insert(tag.to_string(), value.to_string());
+        self.project.property_groups[pg_index].set(tag, Some(value));
 
         Ok(())
     }
 
+    /// Add a `<tag>value</tag>` element at the end of the `pg_index`-th
+    /// `<PropertyGroup>` (0-based), on its own line with the indentation of
+    /// the group's other elements.  Fails with
+    /// [`DprojError::ElementExists`] if the group already has one.
+    ///
+    /// # Example
+    /// ```
+    /// let source = "<Project>\n    <PropertyGroup>\n        <Config>Debug</Config>\n    </PropertyGroup>\n</Project>";
+    /// let mut dproj = dproj_rs::Dproj::parse(source)?;
+    /// dproj.insert_property(0, "DCC_Define", "DEBUG;$(DCC_Define)")?;
+    /// assert_eq!(
+    ///     dproj.source(),
+    ///     "<Project>\n    <PropertyGroup>\n        <Config>Debug</Config>\n        \
+    ///      <DCC_Define>DEBUG;$(DCC_Define)</DCC_Define>\n    </PropertyGroup>\n</Project>"
+    /// );
+    /// # Ok::<(), dproj_rs::dproj::DprojError>(())
+    /// ```
+    pub fn insert_property(
+        &mut self,
+        pg_index: usize,
+        tag: &str,
+        value: &str,
+    ) -> Result<(), DprojError> {
+        let doc = roxmltree::Document::parse(&self.source)?;
+        let mut source = self.source.clone();
+        let group = self.property_group_node(&doc, pg_index)?;
+        if group.children().any(|n| n.is_element() && n.tag_name().name() == tag) {
+            return Err(DprojError::ElementExists {
+                element: format!("<{tag}>"),
+                context: format!("PropertyGroup #{pg_index}"),
+            });
+        }
+
+        let newline = line_ending(&self.source);
+        let element = format!("<{tag}>{}</{tag}>", escape_xml(value));
+        match group.children().rfind(|n| n.is_element()) {
+            Some(last) => {
+                let text = match indentation_at(&self.source, last.range().start) {
+                    Some(indent) => format!("{newline}{indent}{element}"),
+                    None => element,
+                };
+                source.insert_str(last.range().end, &text);
+            }
+            None => {
+                // Empty or self-closing group – rewrite it with one child,
+                // indented one level deeper than the group.
+                let range = group.range();
+                let attrs: String = group
+                    .attributes()
+                    .map(|a| format!(" {}=\"{}\"", a.name(), escape_xml(a.value())))
+                    .collect();
+                let replacement = match indentation_at(&self.source, range.start) {
+                    Some(indent) => {
+                        let child = if indent.is_empty() { "    " } else { indent };
+                        format!(
+                            "<PropertyGroup{attrs}>{newline}\
+                             {indent}{child}{element}{newline}\
+                             {indent}</PropertyGroup>"
+                        )
+                    }
+                    None => format!("<PropertyGroup{attrs}>{element}</PropertyGroup>"),
+                };
+                source.replace_range(range, &replacement);
+            }
+        }
+        self.replace_source(source)
+    }
+
+    /// Remove the `<tag>` element from the `pg_index`-th `<PropertyGroup>`
+    /// (0-based), together with the line it occupied.  Fails with
+    /// [`DprojError::ElementNotFound`] if the group has none.
+    pub fn remove_property(&mut self, pg_index: usize, tag: &str) -> Result<(), DprojError> {
+        let doc = roxmltree::Document::parse(&self.source)?;
+        let mut source = self.source.clone();
+        let element = self
+            .property_group_node(&doc, pg_index)?
+            .children()
+            .find(|n| n.is_element() && n.tag_name().name() == tag)
+            .ok_or_else(|| DprojError::ElementNotFound {
+                element: format!("<{tag}>"),
+                context: format!("PropertyGroup #{pg_index}"),
+            })?;
+        let range = line_span(&self.source, element.range());
+        source.replace_range(range, "");
+        self.replace_source(source)
+    }
+
+    /// [`set_property_value`](Self::set_property_value) if the
+    /// `pg_index`-th `<PropertyGroup>` has a `<tag>` element, otherwise
+    /// [`insert_property`](Self::insert_property).
+    pub fn set_or_insert_property(
+        &mut self,
+        pg_index: usize,
+        tag: &str,
+        value: &str,
+    ) -> Result<(), DprojError> {
+        match self.set_property_value(pg_index, tag, value) {
+            Err(DprojError::ElementNotFound { .. }) => self.insert_property(pg_index, tag, value),
+            result => result,
+        }
+    }
+
+    /// The `pg_index`-th `<PropertyGroup>` element of `doc`.
+    fn property_group_node<'a, 'input>(
+        &self,
+        doc: &'a roxmltree::Document<'input>,
+        pg_index: usize,
+    ) -> Result<roxmltree::Node<'a, 'input>, DprojError> {
+        doc.root_element()
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "PropertyGroup")
+            .nth(pg_index)
+            .ok_or(DprojError::IndexOutOfBounds {
+                index: pg_index,
+                len: self.project.property_groups.len(),
+            })
+    }
+
     // ─── Listing helpers ─────────────────────────────────────────────────
 
     /// Return the names of all build configurations defined in the project
@@ -204,6 +589,7 @@ impl Dproj {
     ///
     /// Falls back to the `<TargetedPlatforms>` bitmask or the unconditional
     /// `<Platform>` element when the `<Platforms>` section is absent.
+    #[allow(clippy::collapsible_if)]
     pub fn platforms(&self) -> Vec<(&str, bool)> {
         // Primary source: ProjectExtensions > BorlandProject > Platforms
         if let Some(ext) = &self.project.project_extensions {
@@ -218,11 +604,24 @@ impl Dproj {
             }
         }
 
-        // Fallback: unconditional <Platform> element.
+        // Fallback: the <TargetedPlatforms> bitmask.
+        if let Some(mask) = self.targeted_platforms_mask() {
+            return TargetPlatform::from_bitmask(mask)
+                .into_iter()
+                .map(|p| (p.as_str(), true))
+                .collect();
+        }
+
+        // Last resort: unconditional <Platform> element.
         for pg in &self.project.property_groups {
             if pg.condition.is_none() {
-                if let Some(p) = &pg.project_properties.platform {
-                    return vec![(p.as_str(), true)];
+                if let Some(p) = pg
+                    .project_properties
+                    .platform
+                    .as_deref()
+                    .or_else(|| pg.conditional_value("Platform"))
+                {
+                    return vec![(p, true)];
                 }
             }
         }
@@ -230,6 +629,27 @@ impl Dproj {
         Vec::new()
     }
 
+    /// The [`platforms`](Self::platforms) that are enabled and known to
+    /// [`TargetPlatform`], in the same order.
+    pub fn target_platforms(&self) -> Vec<TargetPlatform> {
+        self.platforms()
+            .into_iter()
+            .filter(|&(_, active)| active)
+            .filter_map(|(name, _)| name.parse().ok())
+            .collect()
+    }
+
+    /// The `<TargetedPlatforms>` bitmask of the first unconditional group
+    /// that sets it, if it is a number.
+    fn targeted_platforms_mask(&self) -> Option<u32> {
+        self.project
+            .property_groups
+            .iter()
+            .filter(|pg| pg.condition.is_none())
+            .find_map(|pg| pg.project_properties.targeted_platforms.as_deref())
+            .and_then(|mask| mask.trim().parse().ok())
+    }
+
     /// The project's active configuration name (e.g. `"Debug"`).
     pub fn active_configuration(&self) -> Result<String, DprojError> {
         self.active_config_platform().map(|(c, _)| c)
@@ -240,6 +660,13 @@ impl Dproj {
         self.active_config_platform().map(|(_, p)| p)
     }
 
+    /// The project's [active platform](Self::active_platform) as a
+    /// [`TargetPlatform`].  Fails with [`DprojError::UnknownPlatform`] if
+    /// the name is not one.
+    pub fn active_target_platform(&self) -> Result<TargetPlatform, DprojError> {
+        Ok(self.active_platform()?.parse()?)
+    }
+
     // ─── Path accessors ──────────────────────────────────────────────────
 
     /// The parent directory of the `.dproj` file (set by [`from_file`](Self::from_file)).
@@ -250,16 +677,37 @@ impl Dproj {
         self.directory.as_deref()
     }
 
+    /// Full path of the `.dproj` file (set by [`from_file`](Self::from_file)).
+    ///
+    /// Returns `None` when the `Dproj` was created via [`parse`](Self::parse)
+    /// without a file path.
+    pub fn path(&self) -> Option<&std::path::Path> {
+        self.path.as_deref()
+    }
+
+    /// The user's `.dproj.local` file, when loaded with
+    /// [`DprojBuilder::local_file`] and present.
+    pub fn local(&self) -> Option<&DprojLocal> {
+        self.local.as_ref()
+    }
+
+    /// Path of the `.dproj.local` file next to the project: the project
+    /// path with `.local` appended.
+    fn local_path(&self) -> Option<std::path::PathBuf> {
+        let mut path = self.path.as_ref()?.clone().into_os_string();
+        path.push(".local");
+        Some(path.into())
+    }
+
     /// Resolve the project's main source file (`.dpr` or `.dpk`).
     ///
     /// Reads `<MainSource>` from the first unconditional `<PropertyGroup>` and
     /// resolves it relative to the `.dproj` file's directory.
     pub fn get_main_source(&self) -> Result<std::path::PathBuf, DprojError> {
-        let dir = self.directory.as_deref().ok_or_else(|| {
-            DprojError::new(
-                "Cannot resolve main source: no directory (use Dproj::from_file)",
-            )
-        })?;
+        let dir = self
+            .directory
+            .as_deref()
+            .ok_or(DprojError::NoDirectory { operation: "main source" })?;
 
         for pg in &self.project.property_groups {
             if pg.condition.is_some() {
@@ -270,9 +718,10 @@ impl Dproj {
             }
         }
 
-        Err(DprojError::new(
-            "No <MainSource> element found in any unconditional PropertyGroup",
-        ))
+        Err(DprojError::ElementNotFound {
+            element: "<MainSource>".into(),
+            context: "any unconditional PropertyGroup".into(),
+        })
     }
 
     /// Resolve the project's output executable / library path.
@@ -298,16 +747,16 @@ impl Dproj {
     ///
     /// Same resolution as [`get_exe_path`](Self::get_exe_path) but uses the
     /// merged property group for the given config/platform pair.
+    #[allow(clippy::collapsible_if)]
     pub fn get_exe_path_for(
         &self,
         config: &str,
         platform: &str,
     ) -> Result<std::path::PathBuf, DprojError> {
-        let dir = self.directory.as_deref().ok_or_else(|| {
-            DprojError::new(
-                "Cannot resolve exe path: no directory (use Dproj::from_file)",
-            )
-        })?;
+        let dir = self
+            .directory
+            .as_deref()
+            .ok_or(DprojError::NoDirectory { operation: "exe path" })?;
 
         // active_property_group_for already expands $(Var) references.
         let pg = self.active_property_group_for(config, platform)?;
@@ -323,9 +772,10 @@ impl Dproj {
             return Ok(dir.join(dep_name));
         }
 
-        Err(DprojError::new(format!(
-            "No <DCC_ExeOutput> or <DCC_DependencyCheckOutputName> found for {config}/{platform}",
-        )))
+        Err(DprojError::ElementNotFound {
+            element: "<DCC_ExeOutput> or <DCC_DependencyCheckOutputName>".into(),
+            context: format!("the {config}/{platform} property group"),
+        })
     }
 
     /// Derive the project stem (filename without extension) from
@@ -420,7 +870,7 @@ impl Dproj {
                     .children()
                     .find(|n| n.is_element() && n.tag_name().name() == tag)
                 {
-                    found = Some((pg_index, element.range(), element.children().find(|n| n.is_text()).map(|t| t.range()), tag));
+                    found = Some((pg_index, element.range(), element.children().find(|n| n.is_text()).map(|t| t.range()), tag, element.has_attribute("Condition")));
                     break;
                 }
             }
@@ -430,11 +880,9 @@ impl Dproj {
             pg_index += 1;
         }
 
-        let (pg_idx, elem_range, text_range, tag) = found.ok_or_else(|| {
-            DprojError::new(format!(
-                "No <{}> element found in any unconditional PropertyGroup",
-                candidates.join("> or <")
-            ))
+        let (pg_idx, elem_range, text_range, tag, conditional) = found.ok_or_else(|| DprojError::ElementNotFound {
+            element: format!("<{}>", candidates.join("> or <")),
+            context: "any unconditional PropertyGroup".into(),
         })?;
 
         // Byte-splice the raw source.
@@ -458,6 +906,12 @@ impl Dproj {
                 .replace_range(elem_range, &format!("<{tag}{attrs}>{value}</{tag}>"));
         }
 
+        if conditional || !self.project.property_groups[pg_idx].elements.is_empty() {
+            // A default such as `<Config Condition="'$(Config)'==''">`, or
+            // an element of a group that keeps an element list.
+            return self.reparse();
+        }
+
         // Update in-memory struct.
         update_fn(
             &mut self.project.property_groups[pg_idx].project_properties,
@@ -490,7 +944,14 @@ impl Dproj {
 /// ```
 #[derive(Debug, Clone, Default)]
 pub struct DprojBuilder {
-    env: HashMap<String, String>,
+    env: VarMap,
+    file_system: Option<Arc<dyn FileSystem>>,
+    evaluation_mode: EvaluationMode,
+    strict_variables: bool,
+    resolve_imports: bool,
+    os_platform: Option<String>,
+    startup_directory: Option<std::path::PathBuf>,
+    local_file: bool,
 }
 
 impl DprojBuilder {
@@ -504,11 +965,15 @@ impl DprojBuilder {
     /// Typically used with the result of [`crate::rsvars::parse_rsvars`] or
     /// [`crate::rsvars::parse_rsvars_file`].
     ///
-    /// Later calls override earlier values for the same key.
-    pub fn env(mut self, vars: HashMap<String, String>) -> Self {
-        for (k, v) in vars {
-            self.env.insert(k, v);
-        }
+    /// Accepts a [`VarMap`], a `HashMap<String, String>` or any other
+    /// iterator of name/value pairs.  Later calls override earlier values
+    /// for the same key; keys are compared case-insensitively.
+    pub fn env<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env.extend(vars);
         self
     }
 
@@ -531,16 +996,101 @@ impl DprojBuilder {
         self,
         path: impl AsRef<std::path::Path>,
     ) -> Result<Self, DprojError> {
+        let path = path.as_ref();
         let vars = crate::rsvars::parse_rsvars_file(path)
-            .map_err(|e| DprojError::new(format!("rsvars: {e}")))?;
+            .map_err(|e| DprojError::from(e).in_file(path))?;
         Ok(self.env(vars))
     }
 
+    /// Use `file_system` for `Exists(…)` checks.
+    ///
+    /// Without this, [`from_file`](Self::from_file) checks the real disk and
+    /// [`parse`](Self::parse), which has no project directory, assumes
+    /// every path exists.  Pass a
+    /// [`MemoryFileSystem`](crate::filesystem::MemoryFileSystem) to
+    /// simulate an SDK layout, [`DiskFileSystem`] to check the disk from a
+    /// parsed source, or [`AssumeExists`] to restore the legacy behaviour
+    /// for a loaded file.
+    pub fn file_system(mut self, file_system: impl FileSystem + 'static) -> Self {
+        self.file_system = Some(Arc::new(file_system));
+        self
+    }
+
+    /// Choose how `$(…)` references in property values are expanded; see
+    /// [`EvaluationMode`].  Defaults to [`EvaluationMode::Immediate`].
+    pub fn evaluation_mode(mut self, mode: EvaluationMode) -> Self {
+        self.evaluation_mode = mode;
+        self
+    }
+
+    /// Make [`Dproj::active_property_group_for`] (and everything built on
+    /// it, such as [`Dproj::get_exe_path_for`]) fail with
+    /// [`UnresolvedVariables`] when a property value references a variable
+    /// that is not defined, instead of expanding it to the empty string.
+    /// See [`Dproj::unresolved_references_for`] for what counts as
+    /// unresolved.
+    pub fn strict_variables(mut self, strict: bool) -> Self {
+        self.strict_variables = strict;
+        self
+    }
+
+    /// Follow `<Import>` elements when evaluating property groups.
+    ///
+    /// The `Project` attribute is expanded with the variables known at the
+    /// import, relative paths are resolved against the directory of the
+    /// importing file, and when the import's `Condition` holds the file is
+    /// read through the [`file_system`](Self::file_system).  Its
+    /// `<PropertyGroup>`s (and, recursively, its own imports) are then
+    /// evaluated in place, with `MSBuildThisFile*` describing the imported
+    /// file.  As in MSBuild, a file that has already been imported is
+    /// skipped, and an import whose file cannot be read is an error —
+    /// unless the filesystem does not read files at all
+    /// ([`AssumeExists`]), in which case nothing is imported.
+    /// Wildcards in `Project` are not supported.
+    ///
+    /// Off by default, in which case only option sets (`.optset` imports)
+    /// are followed and other `<Import>` elements are ignored.  An option
+    /// set whose file does not exist is skipped.
+    pub fn resolve_imports(mut self, resolve: bool) -> Self {
+        self.resolve_imports = resolve;
+        self
+    }
+
+    /// The operating system `[MSBuild]::IsOSPlatform` reports, e.g.
+    /// `"Linux"` or `"OSX"`.  Defaults to `"Windows"`, where RAD Studio
+    /// runs, whatever the host; see [`functions::Context`].
+    pub fn os_platform(mut self, os: impl Into<String>) -> Self {
+        self.os_platform = Some(os.into());
+        self
+    }
+
+    /// The directory `$(MSBuildStartupDirectory)` expands to, where the
+    /// build would be started from.  Defaults to the current directory for
+    /// [`from_file`](Self::from_file); left undefined for
+    /// [`parse`](Self::parse), so that parsing never depends on the host.
+    pub fn startup_directory(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.startup_directory = Some(dir.into());
+        self
+    }
+
+    /// Load the user's `Project.dproj.local` file next to the project, if
+    /// it exists, through the [`file_system`](Self::file_system).  Its
+    /// `<PropertyGroup>`s are evaluated after the project's own groups, as
+    /// the CodeGear targets do, so debugger settings such as
+    /// `Debugger_RunParams` and `Debugger_HostApplication` match the IDE.
+    /// The file itself is available from [`Dproj::local`].
+    ///
+    /// Only applies to [`from_file`](Self::from_file), since the location
+    /// derives from the project path.
+    pub fn local_file(mut self, load: bool) -> Self {
+        self.local_file = load;
+        self
+    }
+
     /// Parse a `.dproj` file from its XML source string.
     pub fn parse(self, source: impl Into<String>) -> Result<Dproj, DprojError> {
-        let mut dproj = Dproj::parse(source)?;
-        dproj.env = self.env;
-        Ok(dproj)
+        let dproj = Dproj::parse(source)?;
+        self.apply(dproj)
     }
 
     /// Load a `.dproj` file from disk.
@@ -548,8 +1098,36 @@ impl DprojBuilder {
         self,
         path: impl AsRef<std::path::Path>,
     ) -> Result<Dproj, DprojError> {
-        let mut dproj = Dproj::from_file(path)?;
+        let dproj = Dproj::from_file(path)?;
+        self.apply(dproj)
+    }
+
+    /// Transfer the builder's settings onto a freshly parsed [`Dproj`].
+    fn apply(self, mut dproj: Dproj) -> Result<Dproj, DprojError> {
         dproj.env = self.env;
+        dproj.evaluation_mode = self.evaluation_mode;
+        dproj.strict_variables = self.strict_variables;
+        dproj.resolve_imports = self.resolve_imports;
+        if let Some(os) = self.os_platform {
+            dproj.os_platform = os;
+        }
+        if let Some(dir) = self.startup_directory {
+            dproj.startup_directory = Some(dir);
+        }
+        if let Some(fs) = self.file_system {
+            dproj.file_system = fs;
+        }
+        if self.local_file
+            && let Some(path) = dproj.local_path()
+            && dproj.file_system.exists(&path)
+        {
+            let source = dproj
+                .file_system
+                .read_to_string(&path)
+                .map_err(|e| DprojError::from(e).in_file(&path))?;
+            let local = DprojLocal::parse(&source).map_err(|e| e.in_file(&path))?;
+            dproj.local = Some(local);
+        }
         Ok(dproj)
     }
 }
@@ -562,15 +1140,57 @@ impl DprojBuilder {
 // (bool, integer, path …) because the same XML key can carry different
 // representations across Delphi / RAD Studio versions.  Interpretation of
 // the raw strings is left to the consumer.
+//
+// With the `serde` feature every type implements `Serialize` and
+// `Deserialize`.  Fields are named after their MSBuild tag or attribute
+// (`DCC_Define`, `Condition`, `Include` …), the typed groups of a
+// `<PropertyGroup>` are flattened into it, unset values are omitted and maps
+// are written in key order, so the output is stable enough for snapshots.
+// The untyped elements (`other`, `warning_directives`) are flattened too, so
+// a group serializes like its XML; on the way back they are told apart the
+// way the parser does: `DCC_*` tags are warning directives.
+
+/// Serialize a `HashMap` with its keys in sorted order.
+#[cfg(feature = "serde")]
+fn sorted<S: serde::Serializer>(map: &HashMap<String, String>, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_map(map.iter().collect::<std::collections::BTreeMap<_, _>>())
+}
+
+/// Deserialize the flattened entries of a `<PropertyGroup>` not claimed by
+/// a typed field, keeping those `keep` accepts.
+#[cfg(feature = "serde")]
+fn untyped<'de, D: serde::Deserializer<'de>>(
+    d: D,
+    keep: fn(&str) -> bool,
+) -> Result<HashMap<String, String>, D::Error> {
+    let mut map: HashMap<String, String> = serde::Deserialize::deserialize(d)?;
+    map.retain(|tag, _| keep(tag));
+    Ok(map)
+}
+
+#[cfg(feature = "serde")]
+fn warning_directive_entries<'de, D: serde::Deserializer<'de>>(d: D) -> Result<HashMap<String, String>, D::Error> {
+    untyped(d, |tag| tag.starts_with("DCC_"))
+}
+
+#[cfg(feature = "serde")]
+fn other_entries<'de, D: serde::Deserializer<'de>>(d: D) -> Result<HashMap<String, String>, D::Error> {
+    untyped(d, |tag| !tag.starts_with("DCC_"))
+}
 
 // ─── DprojProject ────────────────────────────────────────────────────────────
 
 /// Root representation of a `.dproj` file (`<Project>`).
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DprojProject {
+    #[cfg_attr(feature = "serde", serde(rename = "PropertyGroup"))]
     pub property_groups: Vec<PropertyGroup>,
+    #[cfg_attr(feature = "serde", serde(rename = "ItemGroup"))]
     pub item_groups: Vec<ItemGroup>,
+    #[cfg_attr(feature = "serde", serde(rename = "ProjectExtensions", skip_serializing_if = "Option::is_none"))]
     pub project_extensions: Option<ProjectExtensions>,
+    #[cfg_attr(feature = "serde", serde(rename = "Import"))]
     pub imports: Vec<Import>,
 }
 
@@ -578,49 +1198,104 @@ pub struct DprojProject {
 
 /// A `<PropertyGroup>` element, optionally gated by a `Condition`.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct PropertyGroup {
+    #[cfg_attr(feature = "serde", serde(rename = "Condition", skip_serializing_if = "Option::is_none"))]
     pub condition: Option<String>,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub project_properties: ProjectProperties,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub dcc_options: DccOptions,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub brcc_options: BrccOptions,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub build_events: BuildEvents,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub ver_info: VerInfo,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub platform_packaging: PlatformPackaging,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub debugger_options: DebuggerOptions,
     /// XML child elements not captured by the typed fields above.
+    #[cfg_attr(feature = "serde", serde(flatten, serialize_with = "sorted", deserialize_with = "other_entries"))]
     pub other: HashMap<String, String>,
+    /// Every child element in document order, each with its own
+    /// `Condition` attribute if it has one (e.g.
+    /// `<Config Condition="'$(Config)'==''">Debug</Config>`).  Only filled
+    /// for groups with at least one such element; the typed fields above
+    /// then hold the unconditional elements alone.  During evaluation the
+    /// elements are applied one at a time, each if its condition holds for
+    /// the properties known at that point, so edits to such a group must
+    /// go through the source-editing methods of [`Dproj`], which keep both
+    /// in step.
+    #[cfg_attr(feature = "serde", serde(rename = "Elements", skip_serializing_if = "Vec::is_empty"))]
+    pub elements: Vec<PropertyElement>,
+}
+
+/// A child element of a `<PropertyGroup>`, see [`PropertyGroup::elements`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyElement {
+    #[cfg_attr(feature = "serde", serde(rename = "Tag"))]
+    pub tag: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Condition", skip_serializing_if = "Option::is_none"))]
+    pub condition: Option<String>,
+    /// The element's text, as written.
+    #[cfg_attr(feature = "serde", serde(rename = "Value"))]
+    pub value: String,
 }
 
 // ─── Project-level properties ────────────────────────────────────────────────
 
 /// Core project metadata that can appear in any `<PropertyGroup>`.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ProjectProperties {
+    #[cfg_attr(feature = "serde", serde(rename = "ProjectGuid", skip_serializing_if = "Option::is_none"))]
     pub project_guid: Option<String>,
     /// `<ProjectVersion>` – MSBuild project-file format version (e.g. "19.5", "20.2").
+    #[cfg_attr(feature = "serde", serde(rename = "ProjectVersion", skip_serializing_if = "Option::is_none"))]
     pub project_version: Option<String>,
     /// `<Version>` – older format version tag (e.g. "7.0").
+    #[cfg_attr(feature = "serde", serde(rename = "Version", skip_serializing_if = "Option::is_none"))]
     pub version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "FrameworkType", skip_serializing_if = "Option::is_none"))]
     pub framework_type: Option<String>,
     /// `<Config>` – newer condition-style config selector.
+    #[cfg_attr(feature = "serde", serde(rename = "Config", skip_serializing_if = "Option::is_none"))]
     pub config: Option<String>,
     /// `<Configuration>` – older condition-style config selector.
+    #[cfg_attr(feature = "serde", serde(rename = "Configuration", skip_serializing_if = "Option::is_none"))]
     pub configuration: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Platform", skip_serializing_if = "Option::is_none"))]
     pub platform: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "ProjectName", skip_serializing_if = "Option::is_none"))]
     pub project_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "TargetedPlatforms", skip_serializing_if = "Option::is_none"))]
     pub targeted_platforms: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "AppType", skip_serializing_if = "Option::is_none"))]
     pub app_type: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "MainSource", skip_serializing_if = "Option::is_none"))]
     pub main_source: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Base", skip_serializing_if = "Option::is_none"))]
     pub base: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "CfgParent", skip_serializing_if = "Option::is_none"))]
     pub cfg_parent: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "SanitizedProjectName", skip_serializing_if = "Option::is_none"))]
     pub sanitized_project_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Custom_Styles", skip_serializing_if = "Option::is_none"))]
     pub custom_styles: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "GenPackage", skip_serializing_if = "Option::is_none"))]
     pub gen_package: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "GenDll", skip_serializing_if = "Option::is_none"))]
     pub gen_dll: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "UsePackages", skip_serializing_if = "Option::is_none"))]
     pub use_packages: Option<String>,
     /// `<Icon_MainIcon>`.
+    #[cfg_attr(feature = "serde", serde(rename = "Icon_MainIcon", skip_serializing_if = "Option::is_none"))]
     pub icon_main_icon: Option<String>,
     /// `<Icns_MainIcns>` (macOS).
+    #[cfg_attr(feature = "serde", serde(rename = "Icns_MainIcns", skip_serializing_if = "Option::is_none"))]
     pub icns_main_icns: Option<String>,
 }
 
@@ -628,347 +1303,601 @@ pub struct ProjectProperties {
 
 /// All `DCC_*` properties from a `<PropertyGroup>`.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DccOptions {
     // ── Compiler identity (older format) ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DCCCompiler", skip_serializing_if = "Option::is_none"))]
     pub dcc_compiler: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DependencyCheckOutputName", skip_serializing_if = "Option::is_none"))]
     pub dependency_check_output_name: Option<String>,
 
     // ── Output paths ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DcuOutput", skip_serializing_if = "Option::is_none"))]
     pub dcu_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ExeOutput", skip_serializing_if = "Option::is_none"))]
     pub exe_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DcpOutput", skip_serializing_if = "Option::is_none"))]
     pub dcp_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_BplOutput", skip_serializing_if = "Option::is_none"))]
     pub bpl_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ObjOutput", skip_serializing_if = "Option::is_none"))]
     pub obj_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_HppOutput", skip_serializing_if = "Option::is_none"))]
     pub hpp_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_BpiOutput", skip_serializing_if = "Option::is_none"))]
     pub bpi_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_CBuilderOutput", skip_serializing_if = "Option::is_none"))]
     pub cbuilder_output: Option<String>,
 
     // ── Search paths ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_UnitSearchPath", skip_serializing_if = "Option::is_none"))]
     pub unit_search_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ResourcePath", skip_serializing_if = "Option::is_none"))]
     pub resource_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_IncludePath", skip_serializing_if = "Option::is_none"))]
     pub include_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ObjPath", skip_serializing_if = "Option::is_none"))]
     pub obj_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_FrameworkPath", skip_serializing_if = "Option::is_none"))]
     pub framework_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_SysLibRoot", skip_serializing_if = "Option::is_none"))]
     pub sys_lib_root: Option<String>,
 
     // ── Defines & aliases ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Define", skip_serializing_if = "Option::is_none"))]
     pub define: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Namespace", skip_serializing_if = "Option::is_none"))]
     pub namespace: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_UnitAlias", skip_serializing_if = "Option::is_none"))]
     pub unit_alias: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_UsePackage", skip_serializing_if = "Option::is_none"))]
     pub use_package: Option<String>,
 
     // ── Code generation ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Optimize", skip_serializing_if = "Option::is_none"))]
     pub optimize: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Alignment", skip_serializing_if = "Option::is_none"))]
     pub alignment: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_MinimumEnumSize", skip_serializing_if = "Option::is_none"))]
     pub minimum_enum_size: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_CodePage", skip_serializing_if = "Option::is_none"))]
     pub code_page: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Inlining", skip_serializing_if = "Option::is_none"))]
     pub inlining: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_GenerateStackFrames", skip_serializing_if = "Option::is_none"))]
     pub generate_stack_frames: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_GeneratePICCode", skip_serializing_if = "Option::is_none"))]
     pub generate_pic_code: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_GenerateAndroidAppBundleFile", skip_serializing_if = "Option::is_none"))]
     pub generate_android_app_bundle_file: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_GenerateOSXUniversalBinaryFile", skip_serializing_if = "Option::is_none"))]
     pub generate_osx_universal_binary_file: Option<String>,
 
     // ── Compiler switches ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_E", skip_serializing_if = "Option::is_none"))]
     pub e: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_N", skip_serializing_if = "Option::is_none"))]
     pub n: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_S", skip_serializing_if = "Option::is_none"))]
     pub s: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_F", skip_serializing_if = "Option::is_none"))]
     pub f: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_K", skip_serializing_if = "Option::is_none"))]
     pub k: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ExtendedSyntax", skip_serializing_if = "Option::is_none"))]
     pub extended_syntax: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_LongStrings", skip_serializing_if = "Option::is_none"))]
     pub long_strings: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_OpenStringParams", skip_serializing_if = "Option::is_none"))]
     pub open_string_params: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_StrictVarStrings", skip_serializing_if = "Option::is_none"))]
     pub strict_var_strings: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_TypedAtParameter", skip_serializing_if = "Option::is_none"))]
     pub typed_at_parameter: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_FullBooleanEvaluations", skip_serializing_if = "Option::is_none"))]
     pub full_boolean_evaluations: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_WriteableConstants", skip_serializing_if = "Option::is_none"))]
     pub writeable_constants: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_RunTimeTypeInfo", skip_serializing_if = "Option::is_none"))]
     pub run_time_type_info: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_PentiumSafeDivide", skip_serializing_if = "Option::is_none"))]
     pub pentium_safe_divide: Option<String>,
 
     // ── Runtime checks ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_IOChecking", skip_serializing_if = "Option::is_none"))]
     pub io_checking: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_IntegerOverflowCheck", skip_serializing_if = "Option::is_none"))]
     pub integer_overflow_check: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_RangeChecking", skip_serializing_if = "Option::is_none"))]
     pub range_checking: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_AssertionsAtRuntime", skip_serializing_if = "Option::is_none"))]
     pub assertions_at_runtime: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ImportedDataReferences", skip_serializing_if = "Option::is_none"))]
     pub imported_data_references: Option<String>,
 
     // ── Debug ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DebugInformation", skip_serializing_if = "Option::is_none"))]
     pub debug_information: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_LocalDebugSymbols", skip_serializing_if = "Option::is_none"))]
     pub local_debug_symbols: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_SymbolReferenceInfo", skip_serializing_if = "Option::is_none"))]
     pub symbol_reference_info: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DebugDCUs", skip_serializing_if = "Option::is_none"))]
     pub debug_dcus: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DebugInfoInExe", skip_serializing_if = "Option::is_none"))]
     pub debug_info_in_exe: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DebugInfoInTds", skip_serializing_if = "Option::is_none"))]
     pub debug_info_in_tds: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DebugVN", skip_serializing_if = "Option::is_none"))]
     pub debug_vn: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_RemoteDebug", skip_serializing_if = "Option::is_none"))]
     pub remote_debug: Option<String>,
 
     // ── Warnings & hints ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Hints", skip_serializing_if = "Option::is_none"))]
     pub hints: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Warnings", skip_serializing_if = "Option::is_none"))]
     pub warnings: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ShowGeneralMessages", skip_serializing_if = "Option::is_none"))]
     pub show_general_messages: Option<String>,
 
     // ── Individual warning / hint directives ──
     // Stored by XML tag name (e.g. "DCC_UNSAFE_TYPE" → "False").
-    // This catch-all avoids hard-coding ~70 keys that change between versions.
+    // This catch-all avoids hard-coding ~70 keys that change between versions;
+    // `crate::warnings` reads them as typed warning states.
+    #[cfg_attr(
+        feature = "serde",
+        serde(flatten, serialize_with = "sorted", deserialize_with = "warning_directive_entries")
+    )]
     pub warning_directives: HashMap<String, String>,
 
     // ── Linker / PE ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ConsoleTarget", skip_serializing_if = "Option::is_none"))]
     pub console_target: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_Description", skip_serializing_if = "Option::is_none"))]
     pub description: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_AdditionalSwitches", skip_serializing_if = "Option::is_none"))]
     pub additional_switches: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_LinkerOptions", skip_serializing_if = "Option::is_none"))]
     pub linker_options: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_ImageBase", skip_serializing_if = "Option::is_none"))]
     pub image_base: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_MapFile", skip_serializing_if = "Option::is_none"))]
     pub map_file: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_MapFileARM", skip_serializing_if = "Option::is_none"))]
     pub map_file_arm: Option<String>,
     /// Older combined "min,max" format.
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_StackSize", skip_serializing_if = "Option::is_none"))]
     pub stack_size: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_MaxStackSize", skip_serializing_if = "Option::is_none"))]
     pub max_stack_size: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_MinStackSize", skip_serializing_if = "Option::is_none"))]
     pub min_stack_size: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_BaseAddress", skip_serializing_if = "Option::is_none"))]
     pub base_address: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_PEFlags", skip_serializing_if = "Option::is_none"))]
     pub pe_flags: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_PEOptFlags", skip_serializing_if = "Option::is_none"))]
     pub pe_opt_flags: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_PEOSVersion", skip_serializing_if = "Option::is_none"))]
     pub pe_os_version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_PESubSysVersion", skip_serializing_if = "Option::is_none"))]
     pub pe_sub_sys_version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_PEUserVersion", skip_serializing_if = "Option::is_none"))]
     pub pe_user_version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_NXCompat", skip_serializing_if = "Option::is_none"))]
     pub nx_compat: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_DynamicBase", skip_serializing_if = "Option::is_none"))]
     pub dynamic_base: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_HighEntropyVa", skip_serializing_if = "Option::is_none"))]
     pub high_entropy_va: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_TSAware", skip_serializing_if = "Option::is_none"))]
     pub ts_aware: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_LargeAddressAware", skip_serializing_if = "Option::is_none"))]
     pub large_address_aware: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_AllowUndefined", skip_serializing_if = "Option::is_none"))]
     pub allow_undefined: Option<String>,
 
     // ── Output control ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_OutputXMLDocumentation", skip_serializing_if = "Option::is_none"))]
     pub output_xml_documentation: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_OutputDependencies", skip_serializing_if = "Option::is_none"))]
     pub output_dependencies: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_OutputDRCFile", skip_serializing_if = "Option::is_none"))]
     pub output_drc_file: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_OldDosFileNames", skip_serializing_if = "Option::is_none"))]
     pub old_dos_file_names: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_XmlOutput", skip_serializing_if = "Option::is_none"))]
     pub xml_output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_RemoveTmpLnkFile", skip_serializing_if = "Option::is_none"))]
     pub remove_tmp_lnk_file: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_IncludeDCUsInUsesCompletion", skip_serializing_if = "Option::is_none"))]
     pub include_dcus_in_uses_completion: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_UseMSBuildExternally", skip_serializing_if = "Option::is_none"))]
     pub use_msbuild_externally: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_LegacyIFEND", skip_serializing_if = "Option::is_none"))]
     pub legacy_ifend: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_HppOutputARM", skip_serializing_if = "Option::is_none"))]
     pub hpp_output_arm: Option<String>,
 
     // ── Platform-specific minimum versions ──
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_iOSMinimumVersion", skip_serializing_if = "Option::is_none"))]
     pub ios_minimum_version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_macOSArmMinimumVersion", skip_serializing_if = "Option::is_none"))]
     pub macos_arm_minimum_version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCC_macOSMinimumVersion", skip_serializing_if = "Option::is_none"))]
     pub macos_minimum_version: Option<String>,
 }
 
 // ─── BRCC options ────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct BrccOptions {
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_UserSuppliedOptions", skip_serializing_if = "Option::is_none"))]
     pub user_supplied_options: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_CodePage", skip_serializing_if = "Option::is_none"))]
     pub code_page: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_Language", skip_serializing_if = "Option::is_none"))]
     pub language: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_DeleteIncludePath", skip_serializing_if = "Option::is_none"))]
     pub delete_include_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_EnableMultiByte", skip_serializing_if = "Option::is_none"))]
     pub enable_multi_byte: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_CompilerToUse", skip_serializing_if = "Option::is_none"))]
     pub compiler_to_use: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_ResponseFilename", skip_serializing_if = "Option::is_none"))]
     pub response_filename: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_Verbose", skip_serializing_if = "Option::is_none"))]
     pub verbose: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_Defines", skip_serializing_if = "Option::is_none"))]
     pub defines: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_IncludePath", skip_serializing_if = "Option::is_none"))]
     pub include_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BRCC_OutputDir", skip_serializing_if = "Option::is_none"))]
     pub output_dir: Option<String>,
 }
 
 // ─── Build events ────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct BuildEvents {
+    #[cfg_attr(feature = "serde", serde(rename = "PreBuildEvent", skip_serializing_if = "Option::is_none"))]
     pub pre_build_event: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PreBuildEventCancelOnError", skip_serializing_if = "Option::is_none"))]
     pub pre_build_event_cancel_on_error: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PreBuildEventIgnoreExitCode", skip_serializing_if = "Option::is_none"))]
     pub pre_build_event_ignore_exit_code: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PreLinkEvent", skip_serializing_if = "Option::is_none"))]
     pub pre_link_event: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PreLinkEventCancelOnError", skip_serializing_if = "Option::is_none"))]
     pub pre_link_event_cancel_on_error: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PreLinkEventIgnoreExitCode", skip_serializing_if = "Option::is_none"))]
     pub pre_link_event_ignore_exit_code: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PostBuildEvent", skip_serializing_if = "Option::is_none"))]
     pub post_build_event: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PostBuildEventCancelOnError", skip_serializing_if = "Option::is_none"))]
     pub post_build_event_cancel_on_error: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PostBuildEventIgnoreExitCode", skip_serializing_if = "Option::is_none"))]
     pub post_build_event_ignore_exit_code: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PostBuildEventExecuteWhen", skip_serializing_if = "Option::is_none"))]
     pub post_build_event_execute_when: Option<String>,
 }
 
 // ─── Version info ────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct VerInfo {
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_IncludeVerInfo", skip_serializing_if = "Option::is_none"))]
     pub include_ver_info: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_MajorVer", skip_serializing_if = "Option::is_none"))]
     pub major_ver: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_MinorVer", skip_serializing_if = "Option::is_none"))]
     pub minor_ver: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_Release", skip_serializing_if = "Option::is_none"))]
     pub release: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_Build", skip_serializing_if = "Option::is_none"))]
     pub build: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_Debug", skip_serializing_if = "Option::is_none"))]
     pub debug: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_PreRelease", skip_serializing_if = "Option::is_none"))]
     pub pre_release: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_Special", skip_serializing_if = "Option::is_none"))]
     pub special: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_Private", skip_serializing_if = "Option::is_none"))]
     pub private: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_DLL", skip_serializing_if = "Option::is_none"))]
     pub dll: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_AutoGenVersion", skip_serializing_if = "Option::is_none"))]
     pub auto_gen_version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_Locale", skip_serializing_if = "Option::is_none"))]
     pub locale: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "VerInfo_Keys", skip_serializing_if = "Option::is_none"))]
     pub keys: Option<String>,
 }
 
 // ─── Platform / packaging ────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct PlatformPackaging {
+    #[cfg_attr(feature = "serde", serde(rename = "AppDPIAwarenessMode", skip_serializing_if = "Option::is_none"))]
     pub app_dpi_awareness_mode: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "AppEnableRuntimeThemes", skip_serializing_if = "Option::is_none"))]
     pub app_enable_runtime_themes: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "AppExecutionLevel", skip_serializing_if = "Option::is_none"))]
     pub app_execution_level: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "AppExecutionLevelUIAccess", skip_serializing_if = "Option::is_none"))]
     pub app_execution_level_ui_access: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Manifest_File", skip_serializing_if = "Option::is_none"))]
     pub manifest_file: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "OutputExt", skip_serializing_if = "Option::is_none"))]
     pub output_ext: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BT_BuildType", skip_serializing_if = "Option::is_none"))]
     pub bt_build_type: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PF_UWPPublisher", skip_serializing_if = "Option::is_none"))]
     pub pf_uwp_publisher: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PF_UWPPackageName", skip_serializing_if = "Option::is_none"))]
     pub pf_uwp_package_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PF_UWPPackageDisplayName", skip_serializing_if = "Option::is_none"))]
     pub pf_uwp_package_display_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PF_UWPPublisherDisplayName", skip_serializing_if = "Option::is_none"))]
     pub pf_uwp_publisher_display_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "PF_UWPDistributionType", skip_serializing_if = "Option::is_none"))]
     pub pf_uwp_distribution_type: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "UWP_DelphiLogo44", skip_serializing_if = "Option::is_none"))]
     pub uwp_delphi_logo44: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "UWP_DelphiLogo150", skip_serializing_if = "Option::is_none"))]
     pub uwp_delphi_logo150: Option<String>,
 }
 
 // ─── Debugger ────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DebuggerOptions {
+    #[cfg_attr(feature = "serde", serde(rename = "Debugger_IncludeSystemVars", skip_serializing_if = "Option::is_none"))]
     pub include_system_vars: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Debugger_EnvVars", skip_serializing_if = "Option::is_none"))]
     pub env_vars: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Debugger_SymbolSourcePath", skip_serializing_if = "Option::is_none"))]
     pub symbol_source_path: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Debugger_RunParams", skip_serializing_if = "Option::is_none"))]
     pub run_params: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Debugger_HostApplication", skip_serializing_if = "Option::is_none"))]
+    pub host_application: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Debugger_CWD", skip_serializing_if = "Option::is_none"))]
+    pub cwd: Option<String>,
 }
 
 // ─── ItemGroup ───────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ItemGroup {
+    #[cfg_attr(feature = "serde", serde(rename = "DelphiCompile", skip_serializing_if = "Option::is_none"))]
     pub delphi_compile: Option<DelphiCompile>,
+    #[cfg_attr(feature = "serde", serde(rename = "DCCReference"))]
     pub dcc_references: Vec<DccReference>,
+    #[cfg_attr(feature = "serde", serde(rename = "BuildConfiguration"))]
     pub build_configurations: Vec<BuildConfiguration>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DelphiCompile {
+    #[cfg_attr(feature = "serde", serde(rename = "Include"))]
     pub include: String,
+    #[cfg_attr(feature = "serde", serde(rename = "MainSource", skip_serializing_if = "Option::is_none"))]
     pub main_source: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DccReference {
+    #[cfg_attr(feature = "serde", serde(rename = "Include"))]
     pub include: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Form", skip_serializing_if = "Option::is_none"))]
     pub form: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "FormType", skip_serializing_if = "Option::is_none"))]
     pub form_type: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct BuildConfiguration {
+    #[cfg_attr(feature = "serde", serde(rename = "Include"))]
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Key"))]
     pub key: String,
+    #[cfg_attr(feature = "serde", serde(rename = "CfgParent", skip_serializing_if = "Option::is_none"))]
     pub cfg_parent: Option<String>,
 }
 
 // ─── ProjectExtensions ───────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ProjectExtensions {
+    #[cfg_attr(feature = "serde", serde(rename = "Borland.Personality", skip_serializing_if = "Option::is_none"))]
     pub borland_personality: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Borland.ProjectType", skip_serializing_if = "Option::is_none"))]
     pub borland_project_type: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "BorlandProject", skip_serializing_if = "Option::is_none"))]
     pub borland_project: Option<BorlandProject>,
+    #[cfg_attr(feature = "serde", serde(rename = "ProjectFileVersion", skip_serializing_if = "Option::is_none"))]
     pub project_file_version: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct BorlandProject {
+    #[cfg_attr(feature = "serde", serde(rename = "Delphi.Personality", skip_serializing_if = "Option::is_none"))]
     pub delphi_personality: Option<DelphiPersonality>,
+    #[cfg_attr(feature = "serde", serde(rename = "Deployment", skip_serializing_if = "Option::is_none"))]
     pub deployment: Option<Deployment>,
+    #[cfg_attr(feature = "serde", serde(rename = "Platforms"))]
     pub platforms: Vec<Platform>,
+    #[cfg_attr(feature = "serde", serde(rename = "ModelSupport", skip_serializing_if = "Option::is_none"))]
     pub model_support: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "ActiveXProjectInfo", skip_serializing_if = "Option::is_none"))]
     pub active_x_project_info: Option<ActiveXProjectInfo>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DelphiPersonality {
+    #[cfg_attr(feature = "serde", serde(rename = "Parameters"))]
     pub parameters: Vec<NameValuePair>,
+    #[cfg_attr(feature = "serde", serde(rename = "VersionInfo"))]
     pub version_info: Vec<NameValuePair>,
+    #[cfg_attr(feature = "serde", serde(rename = "VersionInfoKeys"))]
     pub version_info_keys: Vec<NameValuePair>,
+    #[cfg_attr(feature = "serde", serde(rename = "TypeLibOptions"))]
     pub type_lib_options: Vec<NameValuePair>,
+    #[cfg_attr(feature = "serde", serde(rename = "Excluded_Packages"))]
     pub excluded_packages: Vec<ExcludedPackage>,
+    #[cfg_attr(feature = "serde", serde(rename = "Source"))]
     pub sources: Vec<NameValuePair>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct NameValuePair {
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Value"))]
     pub value: String,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ExcludedPackage {
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Description"))]
     pub description: String,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ActiveXProjectInfo {
+    #[cfg_attr(feature = "serde", serde(rename = "version", skip_serializing_if = "Option::is_none"))]
     pub version: Option<String>,
 }
 
 // ─── Deployment ──────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct Deployment {
+    #[cfg_attr(feature = "serde", serde(rename = "Version", skip_serializing_if = "Option::is_none"))]
     pub version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "DeployFile"))]
     pub deploy_files: Vec<DeployFile>,
+    #[cfg_attr(feature = "serde", serde(rename = "DeployClass"))]
     pub deploy_classes: Vec<DeployClass>,
+    #[cfg_attr(feature = "serde", serde(rename = "ProjectRoot"))]
     pub project_roots: Vec<ProjectRoot>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DeployFile {
+    #[cfg_attr(feature = "serde", serde(rename = "LocalName"))]
     pub local_name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Configuration", skip_serializing_if = "Option::is_none"))]
     pub configuration: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Class", skip_serializing_if = "Option::is_none"))]
     pub class: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Platform"))]
     pub platforms: Vec<DeployFilePlatform>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DeployFilePlatform {
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "RemoteName", skip_serializing_if = "Option::is_none"))]
     pub remote_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Overwrite", skip_serializing_if = "Option::is_none"))]
     pub overwrite: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DeployClass {
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Required", skip_serializing_if = "Option::is_none"))]
     pub required: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Platform"))]
     pub platforms: Vec<DeployClassPlatform>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DeployClassPlatform {
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "RemoteDir", skip_serializing_if = "Option::is_none"))]
     pub remote_dir: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Operation", skip_serializing_if = "Option::is_none"))]
     pub operation: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "Extensions", skip_serializing_if = "Option::is_none"))]
     pub extensions: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ProjectRoot {
+    #[cfg_attr(feature = "serde", serde(rename = "Platform"))]
     pub platform: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Name"))]
     pub name: String,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct Platform {
+    #[cfg_attr(feature = "serde", serde(rename = "value"))]
     pub value: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Active"))]
     pub active: bool,
 }
 
+impl Platform {
+    /// The platform as a [`TargetPlatform`].
+    pub fn target_platform(&self) -> Result<TargetPlatform, crate::platform::UnknownPlatform> {
+        self.value.parse()
+    }
+}
+
 // ─── Import ──────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct Import {
+    /// The `Project` attribute, before `$(…)` expansion.
+    #[cfg_attr(feature = "serde", serde(rename = "Project"))]
     pub project: String,
+    #[cfg_attr(feature = "serde", serde(rename = "Condition", skip_serializing_if = "Option::is_none"))]
     pub condition: Option<String>,
+    /// Number of `<PropertyGroup>`s preceding the import in the file.  With
+    /// [`DprojBuilder::resolve_imports`] the imported groups are evaluated
+    /// at this point.
+    #[cfg_attr(feature = "serde", serde(rename = "Position"))]
+    pub position: usize,
+}
+
+impl Import {
+    /// Whether the import refers to a RAD Studio option set (`.optset`).
+    pub fn is_option_set(&self) -> bool {
+        self.project.trim_end().to_ascii_lowercase().ends_with(".optset")
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -989,11 +1918,11 @@ macro_rules! merge_options {
 /// Expand `$(Var)` references in every `Some` string field using the given
 /// variable map.
 macro_rules! expand_options {
-    ($self:expr, $vars:expr, $($field:ident),* $(,)?) => {
+    ($self:expr, $vars:expr, $ctx:expr, $($field:ident),* $(,)?) => {
         $(
             if let Some(ref mut v) = $self.$field {
                 if v.contains("$(") {
-                    *v = expand_msbuild_vars(v, $vars);
+                    *v = expand_msbuild_vars(v, $vars, $ctx);
                 }
             }
         )*
@@ -1012,6 +1941,48 @@ macro_rules! collect_tag_values {
 }
 
 impl PropertyGroup {
+    /// All properties assigned by this group, keyed by their MSBuild
+    /// element name (e.g. `"DCC_Define"`), with values as written.
+    pub fn properties(&self) -> VarMap {
+        let mut properties = VarMap::new();
+        self.collect_into_vars(&mut properties);
+        properties
+    }
+
+    /// The value of the first conditional `tag` element, typically a
+    /// default such as `<Platform Condition="'$(Platform)'==''">Win32</Platform>`.
+    fn conditional_value(&self, tag: &str) -> Option<&str> {
+        self.conditional_elements()
+            .find(|e| e.tag.eq_ignore_ascii_case(tag))
+            .map(|e| e.value.as_str())
+    }
+
+    /// The [`elements`](Self::elements) with their own `Condition`.
+    fn conditional_elements(&self) -> impl Iterator<Item = &PropertyElement> {
+        self.elements.iter().filter(|e| e.condition.is_some())
+    }
+
+    /// Names of the variables this group reads: in its `Condition`, in
+    /// element-level conditions and in property values.  Reported in order
+    /// of first appearance; names differing only in case appear once.
+    pub fn referenced_variables(&self) -> Vec<String> {
+        let mut texts: Vec<&str> = self.condition.iter().map(String::as_str).collect();
+        texts.extend(self.elements.iter().filter_map(|e| e.condition.as_deref()));
+        let properties = self.properties();
+        let mut values: Vec<(&str, &str)> = properties.iter().collect();
+        values.sort_unstable();
+        texts.extend(values.into_iter().map(|(_, v)| v));
+        texts.extend(self.conditional_elements().map(|e| e.value.as_str()));
+
+        let mut names = Vec::new();
+        for text in texts {
+            for name in functions::referenced_variables(text) {
+                functions::push_unique(&mut names, name);
+            }
+        }
+        names
+    }
+
     /// Merge `other` into `self`: any field that is `Some` in `other`
     /// overwrites the corresponding field in `self`.
     pub fn merge_from(&mut self, other: &Self) {
@@ -1031,24 +2002,24 @@ impl PropertyGroup {
     /// variable map.  Used during MSBuild-style incremental property
     /// evaluation so that self-referencing list properties (e.g.
     /// `"src;$(DCC_UnitSearchPath)"`) are resolved correctly.
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        self.project_properties.expand_vars(vars);
-        self.dcc_options.expand_vars(vars);
-        self.brcc_options.expand_vars(vars);
-        self.build_events.expand_vars(vars);
-        self.ver_info.expand_vars(vars);
-        self.platform_packaging.expand_vars(vars);
-        self.debugger_options.expand_vars(vars);
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        self.project_properties.expand_vars(vars, ctx);
+        self.dcc_options.expand_vars(vars, ctx);
+        self.brcc_options.expand_vars(vars, ctx);
+        self.build_events.expand_vars(vars, ctx);
+        self.ver_info.expand_vars(vars, ctx);
+        self.platform_packaging.expand_vars(vars, ctx);
+        self.debugger_options.expand_vars(vars, ctx);
         for v in self.other.values_mut() {
             if v.contains("$(") {
-                *v = expand_msbuild_vars(v, vars);
+                *v = expand_msbuild_vars(v, vars, ctx);
             }
         }
     }
 
     /// Push all currently-set property values into the variable map so
     /// that later property groups can reference them with `$(TagName)`.
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         self.project_properties.collect_into_vars(vars);
         self.dcc_options.collect_into_vars(vars);
         self.brcc_options.collect_into_vars(vars);
@@ -1074,8 +2045,8 @@ impl ProjectProperties {
         );
     }
 
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        expand_options!(self, vars,
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        expand_options!(self, vars, ctx,
             project_guid, project_version, version, framework_type,
             config, configuration, platform, project_name,
             targeted_platforms, app_type, main_source, base,
@@ -1085,7 +2056,7 @@ impl ProjectProperties {
         );
     }
 
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         collect_tag_values!(self, vars,
             "ProjectGuid" => project_guid,
             "ProjectVersion" => project_version,
@@ -1152,8 +2123,8 @@ impl DccOptions {
         }
     }
 
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        expand_options!(self, vars,
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        expand_options!(self, vars, ctx,
             dcc_compiler, dependency_check_output_name,
             dcu_output, exe_output, dcp_output, bpl_output,
             obj_output, hpp_output, bpi_output, cbuilder_output,
@@ -1189,12 +2160,12 @@ impl DccOptions {
         );
         for v in self.warning_directives.values_mut() {
             if v.contains("$(") {
-                *v = expand_msbuild_vars(v, vars);
+                *v = expand_msbuild_vars(v, vars, ctx);
             }
         }
     }
 
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         collect_tag_values!(self, vars,
             "DCC_DCCCompiler" => dcc_compiler,
             "DCC_DependencyCheckOutputName" => dependency_check_output_name,
@@ -1306,15 +2277,15 @@ impl BrccOptions {
         );
     }
 
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        expand_options!(self, vars,
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        expand_options!(self, vars, ctx,
             user_supplied_options, code_page, language,
             delete_include_path, enable_multi_byte, compiler_to_use,
             response_filename, verbose, defines, include_path, output_dir,
         );
     }
 
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         collect_tag_values!(self, vars,
             "BRCC_UserSuppliedOptions" => user_supplied_options,
             "BRCC_CodePage" => code_page,
@@ -1343,8 +2314,8 @@ impl BuildEvents {
         );
     }
 
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        expand_options!(self, vars,
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        expand_options!(self, vars, ctx,
             pre_build_event, pre_build_event_cancel_on_error,
             pre_build_event_ignore_exit_code,
             pre_link_event, pre_link_event_cancel_on_error,
@@ -1354,7 +2325,7 @@ impl BuildEvents {
         );
     }
 
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         collect_tag_values!(self, vars,
             "PreBuildEvent" => pre_build_event,
             "PreBuildEventCancelOnError" => pre_build_event_cancel_on_error,
@@ -1379,15 +2350,15 @@ impl VerInfo {
         );
     }
 
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        expand_options!(self, vars,
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        expand_options!(self, vars, ctx,
             include_ver_info, major_ver, minor_ver, release, build,
             debug, pre_release, special, private, dll,
             auto_gen_version, locale, keys,
         );
     }
 
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         collect_tag_values!(self, vars,
             "VerInfo_IncludeVerInfo" => include_ver_info,
             "VerInfo_MajorVer" => major_ver,
@@ -1418,8 +2389,8 @@ impl PlatformPackaging {
         );
     }
 
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        expand_options!(self, vars,
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        expand_options!(self, vars, ctx,
             app_dpi_awareness_mode, app_enable_runtime_themes,
             app_execution_level, app_execution_level_ui_access,
             manifest_file, output_ext, bt_build_type,
@@ -1429,7 +2400,7 @@ impl PlatformPackaging {
         );
     }
 
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         collect_tag_values!(self, vars,
             "AppDPIAwarenessMode" => app_dpi_awareness_mode,
             "AppEnableRuntimeThemes" => app_enable_runtime_themes,
@@ -1453,21 +2424,25 @@ impl DebuggerOptions {
     fn merge_from(&mut self, o: &Self) {
         merge_options!(self, o,
             include_system_vars, env_vars, symbol_source_path, run_params,
+            host_application, cwd,
         );
     }
 
-    fn expand_vars(&mut self, vars: &HashMap<String, String>) {
-        expand_options!(self, vars,
+    fn expand_vars(&mut self, vars: &VarMap, ctx: &functions::Context) {
+        expand_options!(self, vars, ctx,
             include_system_vars, env_vars, symbol_source_path, run_params,
+            host_application, cwd,
         );
     }
 
-    fn collect_into_vars(&self, vars: &mut HashMap<String, String>) {
+    fn collect_into_vars(&self, vars: &mut VarMap) {
         collect_tag_values!(self, vars,
             "Debugger_IncludeSystemVars" => include_system_vars,
             "Debugger_EnvVars" => env_vars,
             "Debugger_SymbolSourcePath" => symbol_source_path,
             "Debugger_RunParams" => run_params,
+            "Debugger_HostApplication" => host_application,
+            "Debugger_CWD" => cwd,
         );
     }
 }
@@ -1476,6 +2451,37 @@ impl DebuggerOptions {
 //  Active property group resolution
 // ═══════════════════════════════════════════════════════════════════════════════
 
+/// How `$(…)` references in property values are expanded when computing
+/// the effective property group.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvaluationMode {
+    /// Expand each value once, when its `<PropertyGroup>` is reached, using
+    /// the variables known at that point.  References to properties
+    /// defined further down expand to the empty string, and `$(…)` inside
+    /// referenced values (e.g. in environment variables) is left as is.
+    #[default]
+    Immediate,
+    /// Evaluate property groups in document order, but keep values
+    /// unexpanded until all groups have been processed, then expand every
+    /// reference recursively.  A reference of a property to itself
+    /// (`src;$(DCC_UnitSearchPath)`) still means its previous value.
+    /// Conditions are evaluated with the recursively expanded values known
+    /// at their position.  Reference cycles are reported as errors.
+    Deferred,
+}
+
+/// The effective property group of one configuration and platform, as
+/// returned by [`Dproj::property_matrix`].
+#[derive(Debug)]
+pub struct MatrixEntry {
+    pub configuration: String,
+    pub platform: String,
+    /// The effective group, or the error
+    /// [`active_property_group_for`](Dproj::active_property_group_for)
+    /// would have returned.
+    pub property_group: Result<PropertyGroup, DprojError>,
+}
+
 impl Dproj {
     /// Build the MSBuild variable map that drives PropertyGroup condition
     /// evaluation for a given *configuration* (e.g. `"Debug"`) and
@@ -1489,15 +2495,27 @@ impl Dproj {
         &self,
         config: &str,
         platform: &str,
-    ) -> Result<HashMap<String, String>, DprojError> {
-        // Start with external environment variables (rsvars, system env, etc.)
-        let mut vars = self.env.clone();
+    ) -> Result<VarMap, DprojError> {
+        self.build_variables(self.base_variables(), config, platform)
+    }
 
-        // Built-in MSBuild-style variables derived from the project stem.
-        if let Some(stem) = self.project_stem() {
-            vars.insert("MSBuildProjectName".to_string(), stem);
-        }
+    /// The variables known before any build variable is set: the external
+    /// environment (rsvars, system env, etc.), overridden by the reserved
+    /// MSBuild properties.
+    fn base_variables(&self) -> VarMap {
+        let mut vars = self.env.clone();
+        vars.extend(self.reserved_properties());
+        vars
+    }
 
+    /// [`resolve_build_variables`](Self::resolve_build_variables) on top of
+    /// `vars`, the [base variables](Self::base_variables).
+    fn build_variables(
+        &self,
+        mut vars: VarMap,
+        config: &str,
+        platform: &str,
+    ) -> Result<VarMap, DprojError> {
         // Config / Platform override anything from the environment.
         vars.insert("Config".to_string(), config.to_string());
         vars.insert("Configuration".to_string(), config.to_string());
@@ -1513,9 +2531,7 @@ impl Dproj {
 
         // Verify the selected config exists.
         if !build_configs.iter().any(|bc| bc.name == config) {
-            return Err(DprojError::new(format!(
-                "Build configuration '{config}' not found"
-            )));
+            return Err(DprojError::ConfigurationNotFound { name: config.to_string() });
         }
 
         // Walk parent chain, setting each key → "true".
@@ -1567,72 +2583,1503 @@ impl Dproj {
         config: &str,
         platform: &str,
     ) -> Result<PropertyGroup, DprojError> {
-        let build_vars = self.resolve_build_variables(config, platform)?;
-        let mut vars = build_vars.clone();
-        let mut result = PropertyGroup::default();
-
-        for pg in &self.project.property_groups {
-            let matches = if let Some(cond) = &pg.condition {
-                let expr = condition::parse_condition(cond)
-                    .map_err(DprojError::new)?;
-                condition::evaluate(&expr, &vars)
-            } else {
-                true
-            };
+        self.effective_property_group(config, platform, &mut EvaluationCache::default())
+    }
 
-            if matches {
-                // Clone and expand $(Var) references using the accumulated
-                // property map so that self-referencing list properties
-                // (e.g. "src;$(DCC_UnitSearchPath)") resolve correctly.
-                let mut expanded = pg.clone();
-                expanded.expand_vars(&vars);
-                result.merge_from(&expanded);
-                // Feed the newly-merged values back into the variable map
-                // so subsequent PGs can reference them.
-                result.collect_into_vars(&mut vars);
-                // Re-assert build variables: the requested config/platform
-                // must always take priority over project-level defaults
-                // (e.g. an unconditional PG may set <Config>Debug</Config>).
-                for (k, v) in &build_vars {
-                    vars.insert(k.clone(), v.clone());
-                }
+    /// Compute the effective property group of every build configuration
+    /// for every platform, as
+    /// [`active_property_group_for`](Self::active_property_group_for) would.
+    ///
+    /// Configurations are those of [`configurations`](Self::configurations)
+    /// and platforms those of [`platforms`](Self::platforms), both in
+    /// document order, configuration-major.  Unless `include_abstract` is
+    /// set, configurations that are the `CfgParent` of another (`Base`) are
+    /// left out.  The environment, the reserved properties and the parsed
+    /// imported files are shared between the combinations instead of being
+    /// recomputed for each one.
+    ///
+    /// Each entry carries its own result, so a combination that fails (for
+    /// example with [`DprojBuilder::strict_variables`]) does not hide the
+    /// others.
+    ///
+    /// # Example
+    /// ```
+    /// # use dproj_rs::Dproj;
+    /// let dproj = Dproj::from_file("example.dproj").unwrap();
+    /// for entry in dproj.property_matrix(false) {
+    ///     let pg = entry.property_group.unwrap();
+    ///     println!("{} {}: {:?}", entry.configuration, entry.platform, pg.dcc_options.define);
+    /// }
+    /// # assert_eq!(dproj.property_matrix(false).len(), 2 * dproj.platforms().len());
+    /// ```
+    pub fn property_matrix(&self, include_abstract: bool) -> Vec<MatrixEntry> {
+        let configs = if include_abstract {
+            self.configurations()
+        } else {
+            self.concrete_configurations()
+        };
+        let platforms = self.platforms();
+        let mut cache = EvaluationCache::default();
+        let mut matrix = Vec::with_capacity(configs.len() * platforms.len());
+        for config in configs {
+            for &(platform, _) in &platforms {
+                matrix.push(MatrixEntry {
+                    configuration: config.to_string(),
+                    platform: platform.to_string(),
+                    property_group: self.effective_property_group(config, platform, &mut cache),
+                });
             }
         }
+        matrix
+    }
 
-        Ok(result)
+    /// [`active_property_group_for`](Self::active_property_group_for),
+    /// sharing `cache` with other evaluations.
+    fn effective_property_group(
+        &self,
+        config: &str,
+        platform: &str,
+        cache: &mut EvaluationCache,
+    ) -> Result<PropertyGroup, DprojError> {
+        if !self.strict_variables {
+            return Ok(self.evaluate_property_groups(config, platform, cache, &mut |_| {})?.0);
+        }
+        let (group, unresolved) = self.evaluate_reporting_unresolved(config, platform, cache)?;
+        if !unresolved.is_empty() {
+            return Err(UnresolvedVariables { references: unresolved }.into());
+        }
+        Ok(group)
     }
 
-    /// Extract the active `(Config, Platform)` from the project's
-    /// unconditional property groups.
-    fn active_config_platform(&self) -> Result<(String, String), DprojError> {
-        let mut config: Option<String> = None;
-        let mut platform: Option<String> = None;
+    /// List the `$(…)` references in the values of the effective property
+    /// group for `config` and `platform` that name no known variable and
+    /// therefore expand to the empty string.
+    ///
+    /// With [`EvaluationMode::Immediate`], a reference is unresolved if the
+    /// variable is not defined by the environment, the build variables or
+    /// an earlier group when its group is reached; with
+    /// [`EvaluationMode::Deferred`], if it is not defined anywhere.  A
+    /// property referencing its own undefined previous value
+    /// (`DEBUG;$(DCC_Define)`) is not reported.  Values overridden by later
+    /// groups are reported as well, since they may still flow into the
+    /// effective value.
+    ///
+    /// # Example
+    /// ```
+    /// # use dproj_rs::Dproj;
+    /// let dproj = Dproj::from_file("example.dproj").unwrap();
+    /// let unresolved = dproj.unresolved_references_for("Debug", "Win32").unwrap();
+    /// assert!(unresolved.iter().any(|r| r.variable == "BDS"));
+    /// ```
+    pub fn unresolved_references_for(
+        &self,
+        config: &str,
+        platform: &str,
+    ) -> Result<Vec<UnresolvedReference>, DprojError> {
+        Ok(self.evaluate_reporting_unresolved(config, platform, &mut EvaluationCache::default())?.1)
+    }
 
-        for pg in &self.project.property_groups {
-            // Only look at unconditional groups for the defaults.
-            if pg.condition.is_some() {
-                continue;
-            }
-            if config.is_none() {
-                config = pg.project_properties.config.clone()
-                    .or_else(|| pg.project_properties.configuration.clone());
-            }
-            if platform.is_none() {
-                platform = pg.project_properties.platform.clone();
-            }
-            if config.is_some() && platform.is_some() {
-                break;
+    /// Evaluate the property groups, collecting unresolved references on
+    /// the way.
+    fn evaluate_reporting_unresolved(
+        &self,
+        config: &str,
+        platform: &str,
+        cache: &mut EvaluationCache,
+    ) -> Result<(PropertyGroup, Vec<UnresolvedReference>), DprojError> {
+        let deferred = self.evaluation_mode == EvaluationMode::Deferred;
+        let mut unresolved = Vec::new();
+        let (group, vars) = self.evaluate_property_groups(config, platform, cache, &mut |contribution| {
+            let properties = contribution.group.properties();
+            let mut values: Vec<(&str, &str)> = properties.iter().collect();
+            values.sort_unstable_by_key(|&(name, _)| name.to_ascii_lowercase());
+            for (property, value) in values {
+                for variable in functions::referenced_variables(value) {
+                    // Deferred references are checked once all groups are in.
+                    if !variable.eq_ignore_ascii_case(property)
+                        && (deferred || !contribution.vars.contains_key(&variable))
+                    {
+                        unresolved.push(UnresolvedReference {
+                            variable,
+                            property: property.to_string(),
+                            group: contribution.index,
+                            file: contribution.file.map(|f| f.to_path_buf()),
+                        });
+                    }
+                }
             }
+        })?;
+        if deferred {
+            unresolved.retain(|r| !vars.contains_key(&r.variable));
         }
+        Ok((group, unresolved))
+    }
 
-        let config = config.ok_or_else(|| {
-            DprojError::new("No default Config/Configuration found in unconditional PropertyGroups")
+    /// Evaluate the project like
+    /// [`active_property_group_for`](Self::active_property_group_for) and
+    /// record, for every effective property, the assignments that produced
+    /// it: group index, conditions and raw text, in evaluation order.
+    ///
+    /// # Example
+    /// ```
+    /// # use dproj_rs::Dproj;
+    /// let dproj = Dproj::from_file("example.dproj").unwrap();
+    /// let provenance = dproj.property_provenance_for("Debug", "Win32").unwrap();
+    /// let exe_output = provenance.get("DCC_ExeOutput").unwrap();
+    /// assert_eq!(exe_output.origin().raw, r".\$(Platform)\$(Config)\DDD");
+    /// ```
+    pub fn property_provenance_for(
+        &self,
+        config: &str,
+        platform: &str,
+    ) -> Result<Provenance, DprojError> {
+        let mut recorded = Vec::new();
+        let mut cache = EvaluationCache::default();
+        let (group, _) = self.evaluate_property_groups(config, platform, &mut cache, &mut |contribution| {
+            let source = contribution.source;
+            let properties = contribution.group.properties();
+            let mut assignments: Vec<(&str, &str)> = properties.iter().collect();
+            assignments.sort_unstable_by_key(|&(name, _)| name.to_ascii_lowercase());
+            for (name, raw) in assignments {
+                let element_condition = contribution
+                    .elements
+                    .iter()
+                    .rev()
+                    .find(|e| e.tag.eq_ignore_ascii_case(name))
+                    .and_then(|e| e.condition.clone());
+                recorded.push((
+                    name.to_string(),
+                    PropertyAssignment {
+                        group: contribution.index,
+                        file: contribution.file.map(|f| f.to_path_buf()),
+                        condition: source.condition.clone(),
+                        element_condition,
+                        raw: raw.to_string(),
+                    },
+                ));
+            }
         })?;
-        let platform = platform.ok_or_else(|| {
-            DprojError::new("No default Platform found in unconditional PropertyGroups")
+        Ok(Provenance::new(group, recorded))
+    }
+
+    /// Same as [`property_provenance_for`](Self::property_provenance_for)
+    /// for the file's default configuration and platform.
+    pub fn property_provenance(&self) -> Result<Provenance, DprojError> {
+        let (config, platform) = self.active_config_platform()?;
+        self.property_provenance_for(&config, &platform)
+    }
+
+    /// Merge the `<PropertyGroup>`s that apply to `config` and `platform`,
+    /// returning the effective group and the final variable map.
+    /// `contribute` is called for every group that applies, in evaluation
+    /// order; see [`Contribution`].
+    fn evaluate_property_groups(
+        &self,
+        config: &str,
+        platform: &str,
+        cache: &mut EvaluationCache,
+        contribute: &mut dyn FnMut(&Contribution),
+    ) -> Result<(PropertyGroup, VarMap), DprojError> {
+        let base = cache.base_vars.get_or_insert_with(|| self.base_variables()).clone();
+        let build_vars = self.build_variables(base, config, platform)?;
+        let local = match (&self.local, self.local_path()) {
+            (Some(local), Some(path)) => {
+                let project = cache.files.entry(crate::filesystem::normalize(&path)).or_insert_with(|| {
+                    Arc::new(DprojProject {
+                        property_groups: local.property_groups.clone(),
+                        ..DprojProject::default()
+                    })
+                });
+                Some((Arc::clone(project), path))
+            }
+            _ => None,
+        };
+        let mut evaluation = Evaluation {
+            dproj: self,
+            deferred: self.evaluation_mode == EvaluationMode::Deferred,
+            ctx: self.eval_context(),
+            vars: build_vars.clone(),
+            build_vars,
+            result: PropertyGroup::default(),
+            imported: Vec::new(),
+            files: &mut cache.files,
+            contribute,
+        };
+        evaluation.project(&self.project, None)?;
+        if let Some((project, path)) = local {
+            evaluation.imported_file(&project, &path)?;
+        }
+        evaluation.finish()
+    }
+
+    /// Determine which `<PropertyGroup>`s can apply when the configuration
+    /// and/or platform are left open.
+    ///
+    /// `None` for `config` stands for every concrete configuration (one
+    /// that is not the `CfgParent` of another), `None` for `platform` for
+    /// every platform in [`platforms`](Self::platforms).  Build variables
+    /// that take the same value for all of these combinations are treated
+    /// as known; the others (`Config`, `Cfg_1`, `Base_Win64`, …) are unknown
+    /// and conditions are evaluated with
+    /// [`evaluate_partial_with`](condition::evaluate_partial_with).
+    ///
+    /// Returns the index of every group whose condition is not certainly
+    /// false, with [`Partial::True`](condition::Partial::True) for groups
+    /// that always apply and the residual condition for the others.
+    /// Conditions are checked against the environment and build variables
+    /// only; values assigned by earlier groups are not taken into account.
+    ///
+    /// # Example
+    /// ```
+    /// # use dproj_rs::Dproj;
+    /// # let dproj = Dproj::from_file("example.dproj").unwrap();
+    /// // Groups that may contribute to any Win64 configuration.
+    /// let groups = dproj.possible_property_groups(None, Some("Win64")).unwrap();
+    /// # assert!(!groups.is_empty());
+    /// ```
+    pub fn possible_property_groups(
+        &self,
+        config: Option<&str>,
+        platform: Option<&str>,
+    ) -> Result<Vec<(usize, condition::Partial)>, DprojError> {
+        let configs = match config {
+            Some(c) => vec![c],
+            None => self.concrete_configurations(),
+        };
+        let platforms = match platform {
+            Some(p) => vec![p],
+            None => self.platforms().into_iter().map(|(p, _)| p).collect(),
+        };
+
+        let base = self.base_variables();
+        let mut candidates = Vec::new();
+        for config in &configs {
+            for platform in &platforms {
+                candidates.push(self.build_variables(base.clone(), config, platform)?);
+            }
+        }
+        let Some((first, rest)) = candidates.split_first() else {
+            return Err(DprojError::ElementNotFound {
+                element: "<BuildConfiguration> or platform".into(),
+                context: "the project".into(),
+            });
+        };
+
+        // Variables that differ from the first candidate in any other one
+        // are unknown.
+        let mut known = first.clone();
+        let mut unknown: Vec<String> = Vec::new();
+        for vars in rest {
+            let differing = vars
+                .iter()
+                .filter(|&(name, value)| first.get(name).map(String::as_str) != Some(value))
+                .map(|(name, _)| name)
+                .chain(first.keys().filter(|name| !vars.contains_key(name)));
+            for name in differing {
+                known.remove(name);
+                if !unknown.iter().any(|u| u.eq_ignore_ascii_case(name)) {
+                    unknown.push(name.to_string());
+                }
+            }
+        }
+        let unknown: Vec<&str> = unknown.iter().map(String::as_str).collect();
+
+        let ctx = self.eval_context();
+        let mut groups = Vec::new();
+        for (index, pg) in self.project.property_groups.iter().enumerate() {
+            let outcome = match &pg.condition {
+                Some(cond) => {
+                    let expr = self.compiled_condition(cond, None, index)?;
+                    condition::evaluate_partial_with(&expr, &known, &unknown, &ctx)
+                }
+                None => condition::Partial::True,
+            };
+            if outcome.is_possible() {
+                groups.push((index, outcome));
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Configurations that are not the `CfgParent` of another
+    /// configuration, e.g. `Debug` and `Release` but not `Base`.
+    fn concrete_configurations(&self) -> Vec<&str> {
+        let build_configs: Vec<&BuildConfiguration> = self
+            .project
+            .item_groups
+            .iter()
+            .flat_map(|ig| &ig.build_configurations)
+            .collect();
+        build_configs
+            .iter()
+            .filter(|bc| {
+                !build_configs
+                    .iter()
+                    .any(|other| other.cfg_parent.as_deref() == Some(bc.name.as_str()))
+            })
+            .map(|bc| bc.name.as_str())
+            .collect()
+    }
+
+    /// MSBuild reserved properties and the RAD Studio project macros, as far
+    /// as they can be derived from the project file.
+    ///
+    /// - `MSBuildProjectName`: the [project stem](Self::project_stem), or
+    ///   the file stem if the project names no main source.
+    /// - With a [`path`](Self::path): `MSBuildProjectFile`,
+    ///   `MSBuildProjectFullPath`, `MSBuildProjectExtension`,
+    ///   `MSBuildProjectDirectory`, `MSBuildProjectDirectoryNoRoot`, the
+    ///   matching `MSBuildThisFile*` properties (as seen from the project
+    ///   file itself) and `PROJECTFILENAME`, `PROJECTEXT`, `PROJECTDIR`,
+    ///   `PROJECTPATH`.  (`PROJECTNAME` is the project's own `ProjectName`
+    ///   property; MSBuild names are case-insensitive.)
+    /// - `MSBuildStartupDirectory`: the
+    ///   [`startup_directory`](DprojBuilder::startup_directory), by default
+    ///   the current directory if the project was read from a file.
+    /// - `MSBuildBinPath` and `MSBuildToolsPath`: `FrameworkDir` from the
+    ///   environment (set by `rsvars.bat`), if present.
+    /// - With [`EvaluationMode::Deferred`] and a path: `OUTPUTNAME` and
+    ///   `OUTPUTDIR`, the latter defined in terms of `DCC_ExeOutput`.  As in
+    ///   MSBuild, where the RAD Studio targets define them after the
+    ///   project's own groups, they expand to nothing in immediate mode.
+    ///
+    /// Directories have no trailing separator, except `MSBuildThisFileDirectory`
+    /// and `MSBuildThisFileDirectoryNoRoot`.
+    fn reserved_properties(&self) -> VarMap {
+        let mut vars = VarMap::new();
+        let file_stem = self
+            .path
+            .as_deref()
+            .and_then(|p| p.file_stem())
+            .map(|s| s.to_string_lossy().into_owned());
+        let stem = self.project_stem().or(file_stem);
+        if let Some(stem) = &stem {
+            vars.insert("MSBuildProjectName", stem.as_str());
+        }
+
+        if let Some(path) = &self.path {
+            let display = |p: &std::path::Path| p.to_string_lossy().into_owned();
+            let file = path.file_name().map(|f| display(f.as_ref())).unwrap_or_default();
+            let extension = path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            let full_path = display(path);
+            let directory = path.parent().map(display).unwrap_or_default();
+            let no_root = path
+                .parent()
+                .map(|dir| {
+                    let relative: std::path::PathBuf = dir
+                        .components()
+                        .filter(|c| matches!(c, std::path::Component::Normal(_)))
+                        .collect();
+                    display(&relative)
+                })
+                .unwrap_or_default();
+
+            vars.insert("MSBuildProjectFile", file.as_str());
+            vars.insert("MSBuildProjectFullPath", full_path.as_str());
+            vars.insert("MSBuildProjectExtension", extension.as_str());
+            vars.insert("MSBuildProjectDirectory", directory.as_str());
+            vars.insert("MSBuildProjectDirectoryNoRoot", no_root.as_str());
+            vars.extend(this_file_properties(&full_path));
+            vars.insert("PROJECTFILENAME", file.as_str());
+            vars.insert("PROJECTEXT", extension.as_str());
+            vars.insert("PROJECTDIR", directory.as_str());
+            vars.insert("PROJECTPATH", full_path.as_str());
+
+            if self.evaluation_mode == EvaluationMode::Deferred {
+                vars.insert("OUTPUTNAME", stem.clone().unwrap_or_default());
+                vars.insert(
+                    "OUTPUTDIR",
+                    "$([MSBuild]::NormalizeDirectory('$(MSBuildProjectDirectory)', '$(DCC_ExeOutput)'))",
+                );
+            }
+        }
+
+        if let Some(dir) = &self.startup_directory {
+            vars.insert("MSBuildStartupDirectory", dir.to_string_lossy().into_owned());
+        }
+        if let Some(framework_dir) = self.env.get("FrameworkDir") {
+            vars.insert("MSBuildBinPath", framework_dir.as_str());
+            vars.insert("MSBuildToolsPath", framework_dir.as_str());
+        }
+        vars
+    }
+
+    /// Build the [`PropertyGraph`](crate::graph::PropertyGraph) of the
+    /// project: which variables every property assignment reads, for cycle
+    /// detection and for finding the variables that must come from
+    /// `rsvars.bat` or the environment.
+    ///
+    /// # Example
+    /// ```
+    /// # use dproj_rs::Dproj;
+    /// let dproj = Dproj::from_file("example.dproj").unwrap();
+    /// let graph = dproj.property_graph();
+    /// assert!(graph.cycles().is_empty());
+    /// assert!(graph.external_variables().iter().any(|v| v == "BDS"));
+    /// ```
+    pub fn property_graph(&self) -> crate::graph::PropertyGraph {
+        crate::graph::PropertyGraph::build(self, self.supplied_variables())
+    }
+
+    /// Names of the variables [`resolve_build_variables`](Self::resolve_build_variables)
+    /// defines for some configuration and platform, independent of the
+    /// environment.
+    fn supplied_variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = ["Config", "Configuration", "Platform"].map(String::from).into();
+        names.extend(self.reserved_properties().into_iter().map(|(name, _)| name));
+        let platforms: Vec<&str> = self.platforms().into_iter().map(|(p, _)| p).collect();
+        let build_configs = self
+            .project
+            .item_groups
+            .iter()
+            .flat_map(|ig| &ig.build_configurations);
+        for bc in build_configs {
+            names.push(bc.key.clone());
+            names.extend(platforms.iter().map(|p| format!("{}_{p}", bc.key)));
+        }
+        names
+    }
+
+    /// Parse the condition `cond` of the `index`-th `<PropertyGroup>` (or
+    /// of one of its elements) of `file`, the project itself when `None`,
+    /// reusing earlier parses of the same text.
+    fn compiled_condition(
+        &self,
+        cond: &str,
+        file: Option<&std::path::Path>,
+        index: usize,
+    ) -> Result<Arc<condition::Expression>, DprojError> {
+        self.conditions.parse(cond).map_err(|e| DprojError::Condition {
+            file: file.map(std::path::Path::to_path_buf),
+            source: e.with_property_group(index),
+        })
+    }
+
+    /// Condition evaluation context: the configured filesystem, with
+    /// relative paths resolved against the project directory, and the
+    /// configured OS platform.
+    fn eval_context(&self) -> condition::EvalContext<'_> {
+        condition::EvalContext::new(&*self.file_system)
+            .with_base_dir(self.directory.as_deref())
+            .with_os_platform(&self.os_platform)
+    }
+
+    /// Extract the active `(Config, Platform)` from the project's
+    /// unconditional property groups.
+    fn active_config_platform(&self) -> Result<(String, String), DprojError> {
+        let mut config: Option<String> = None;
+        let mut platform: Option<String> = None;
+
+        for pg in &self.project.property_groups {
+            // Only look at unconditional groups for the defaults.
+            if pg.condition.is_some() {
+                continue;
+            }
+            if config.is_none() {
+                config = pg.project_properties.config.as_deref()
+                    .or(pg.project_properties.configuration.as_deref())
+                    .or_else(|| pg.conditional_value("Config"))
+                    .or_else(|| pg.conditional_value("Configuration"))
+                    .map(String::from);
+            }
+            if platform.is_none() {
+                platform = pg.project_properties.platform.as_deref()
+                    .or_else(|| pg.conditional_value("Platform"))
+                    .map(String::from);
+            }
+            if config.is_some() && platform.is_some() {
+                break;
+            }
+        }
+
+        let config = config.ok_or_else(|| DprojError::ElementNotFound {
+            element: "<Config> or <Configuration>".into(),
+            context: "any unconditional PropertyGroup".into(),
+        })?;
+        let platform = platform.ok_or_else(|| DprojError::ElementNotFound {
+            element: "<Platform>".into(),
+            context: "any unconditional PropertyGroup".into(),
         })?;
 
-        Ok((config, platform))
+        Ok((config, platform))
+    }
+}
+
+/// A `<PropertyGroup>` that applies, as reported to the `contribute`
+/// callback of [`Dproj::evaluate_property_groups`].
+struct Contribution<'a> {
+    /// The file the group was imported from; `None` for the project itself.
+    file: Option<&'a std::path::Path>,
+    /// Index of the group among the `<PropertyGroup>`s of its file.
+    index: usize,
+    /// The group as written.
+    source: &'a PropertyGroup,
+    /// Its elements whose own conditions hold, before expansion.
+    group: &'a PropertyGroup,
+    /// The [`elements`](PropertyGroup::elements) of `source` that were
+    /// applied, in order.
+    elements: &'a [&'a PropertyElement],
+    /// The variables known at that point.
+    vars: &'a VarMap,
+}
+
+/// State of one evaluation of the property groups, in either
+/// [`EvaluationMode`].
+struct Evaluation<'a> {
+    dproj: &'a Dproj,
+    deferred: bool,
+    ctx: condition::EvalContext<'a>,
+    /// Build variables, re-asserted after every group.
+    build_vars: VarMap,
+    /// Immediate mode: expanded values.  Deferred mode: unexpanded values,
+    /// with self-references replaced by the previous value.
+    vars: VarMap,
+    result: PropertyGroup,
+    /// Normalised paths of the files imported so far.
+    imported: Vec<String>,
+    /// Parsed imported files by normalised path, shared with other
+    /// evaluations; see [`EvaluationCache`].
+    files: &'a mut HashMap<String, Arc<DprojProject>>,
+    contribute: &'a mut dyn FnMut(&Contribution),
+}
+
+/// Work shared between evaluations of several configurations and
+/// platforms by [`Dproj::property_matrix`].
+#[derive(Default)]
+struct EvaluationCache {
+    /// [`Dproj::base_variables`], once computed.
+    base_vars: Option<VarMap>,
+    /// Imported files, and the `.dproj.local` groups, by normalised path.
+    files: HashMap<String, Arc<DprojProject>>,
+}
+
+impl Evaluation<'_> {
+    /// Evaluate the groups of `project`, read from `file`, in document
+    /// order, following its imports if enabled.
+    fn project(
+        &mut self,
+        project: &DprojProject,
+        file: Option<&std::path::Path>,
+    ) -> Result<(), DprojError> {
+        let follow = self.dproj.resolve_imports;
+        let mut imports = project
+            .imports
+            .iter()
+            .filter(|i| follow || i.is_option_set())
+            .peekable();
+        for (index, pg) in project.property_groups.iter().enumerate() {
+            while let Some(import) = imports.next_if(|i| i.position <= index) {
+                self.import(import, file)?;
+            }
+            self.group(pg, file, index)?;
+        }
+        for import in imports {
+            self.import(import, file)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `expr` holds for the variables known at this point.
+    fn holds(&self, expr: &condition::Expression) -> Result<bool, DprojError> {
+        if !self.deferred {
+            return Ok(condition::evaluate_with(expr, &self.vars, &self.ctx));
+        }
+        let mut resolver = functions::Resolver::msbuild(&self.vars, self.ctx.functions);
+        for name in expr.referenced_variables() {
+            resolver.resolve(&name)?;
+        }
+        Ok(condition::evaluate_with(expr, &resolver.resolved, &self.ctx))
+    }
+
+    /// Merge `pg`, the `index`-th group of `file`, if its condition holds.
+    fn group(
+        &mut self,
+        pg: &PropertyGroup,
+        file: Option<&std::path::Path>,
+        index: usize,
+    ) -> Result<(), DprojError> {
+        if let Some(cond) = &pg.condition {
+            let expr = self.dproj.compiled_condition(cond, file, index)?;
+            if !self.holds(&expr)? {
+                return Ok(());
+            }
+        }
+
+        if pg.elements.is_empty() {
+            (self.contribute)(&Contribution {
+                file,
+                index,
+                source: pg,
+                group: pg,
+                elements: &[],
+                vars: &self.vars,
+            });
+            return self.apply(pg.clone(), file);
+        }
+
+        // MSBuild evaluates the elements of a group one at a time, in
+        // document order, so each element sees the properties set before
+        // it, e.g. `<Config Condition="'$(Config)'==''">Debug</Config>` is
+        // skipped when a configuration has been requested explicitly.
+        let vars = self.vars.clone();
+        let mut group = PropertyGroup { condition: pg.condition.clone(), ..Default::default() };
+        let mut applied = Vec::new();
+        for element in &pg.elements {
+            if let Some(cond) = &element.condition {
+                let expr = self.dproj.compiled_condition(cond, file, index)?;
+                if !self.holds(&expr)? {
+                    continue;
+                }
+            }
+            let mut single = PropertyGroup::default();
+            single.set(&element.tag, Some(&element.value));
+            self.apply(single, file)?;
+            group.set(&element.tag, Some(&element.value));
+            applied.push(element);
+        }
+        (self.contribute)(&Contribution {
+            file,
+            index,
+            source: pg,
+            group: &group,
+            elements: &applied,
+            vars: &vars,
+        });
+        Ok(())
+    }
+
+    /// Expand the elements of `group`, an applicable group (or element) of
+    /// `file`, and merge them into the result.
+    fn apply(
+        &mut self,
+        mut group: PropertyGroup,
+        file: Option<&std::path::Path>,
+    ) -> Result<(), DprojError> {
+        if self.deferred {
+            for (name, value) in &group.properties() {
+                let previous = self.vars.get(name).map_or("", String::as_str);
+                let mut value = functions::substitute_reference(value, name, previous);
+                // A self-reference inside a property function call cannot be
+                // deferred, and neither can `MSBuildThisFile*` in an imported
+                // file; expand the whole value now.
+                if functions::referenced_variables(&value).iter().any(|n| {
+                    n.eq_ignore_ascii_case(name) || (file.is_some() && is_this_file_property(n))
+                }) {
+                    value = functions::expand_recursive_with(&value, &self.vars, &self.ctx.functions)?;
+                }
+                group.set(name, Some(&value));
+            }
+        } else {
+            // Expand $(Var) references using the accumulated property map so
+            // that self-referencing list properties (e.g.
+            // "src;$(DCC_UnitSearchPath)") resolve correctly.
+            group.expand_vars(&self.vars, &self.ctx.functions);
+        }
+
+        self.result.merge_from(&group);
+        // Feed the newly-merged values back into the variable map so
+        // subsequent PGs can reference them.
+        self.result.collect_into_vars(&mut self.vars);
+        // Re-assert build variables: the requested config/platform must
+        // always take priority over project-level defaults (e.g. an
+        // unconditional PG may set <Config>Debug</Config>).
+        for (k, v) in &self.build_vars {
+            self.vars.insert(k.clone(), v.clone());
+        }
+        Ok(())
+    }
+
+    /// Evaluate the file imported by `import`, an element of `importer`.
+    fn import(
+        &mut self,
+        import: &Import,
+        importer: Option<&std::path::Path>,
+    ) -> Result<(), DprojError> {
+        let context = |source: DprojError| DprojError::Import {
+            project: import.project.clone(),
+            source: Box::new(source),
+        };
+        if let Some(cond) = &import.condition {
+            let expr = self.dproj.conditions.parse(cond).map_err(|e| {
+                context(DprojError::Condition {
+                    file: importer.map(std::path::Path::to_path_buf),
+                    source: e,
+                })
+            })?;
+            if !self.holds(&expr)? {
+                return Ok(());
+            }
+        }
+
+        let project = if self.deferred {
+            functions::expand_recursive_with(&import.project, &self.vars, &self.ctx.functions)?
+        } else {
+            expand_msbuild_vars(&import.project, &self.vars, &self.ctx.functions)
+        };
+        let project = project.trim();
+        if project.is_empty() {
+            let empty = std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty path");
+            return Err(context(empty.into()));
+        }
+        let base = match importer {
+            Some(file) => Some(split_file_name(&file.to_string_lossy()).0.to_string()),
+            None => self.dproj.directory.as_deref().map(|d| d.to_string_lossy().into_owned()),
+        };
+        let path = match base {
+            Some(dir) if !dir.is_empty() && !crate::filesystem::is_absolute(project) => {
+                std::path::Path::new(&dir).join(project)
+            }
+            _ => std::path::PathBuf::from(project),
+        };
+
+        if self.was_imported(&path) {
+            return Ok(());
+        }
+        let key = crate::filesystem::normalize(&path);
+        let imported = match self.files.get(&key) {
+            Some(imported) => Arc::clone(imported),
+            None => {
+                let text = match self.dproj.file_system.read_to_string(&path) {
+                    Ok(text) => text,
+                    // A filesystem that cannot read files (`AssumeExists`)
+                    // imports nothing, and a missing option set is skipped
+                    // rather than failing the whole evaluation.
+                    Err(e)
+                        if e.kind() == std::io::ErrorKind::Unsupported
+                            || (e.kind() == std::io::ErrorKind::NotFound && import.is_option_set()) =>
+                    {
+                        return Ok(());
+                    }
+                    Err(e) => return Err(context(DprojError::from(e).in_file(&path))),
+                };
+                let doc = roxmltree::Document::parse(&text)
+                    .map_err(|e| context(DprojError::from(e).in_file(&path)))?;
+                let imported = Arc::new(DprojProject::parse(doc.root_element())?);
+                self.files.insert(key, Arc::clone(&imported));
+                imported
+            }
+        };
+        self.imported_file(&imported, &path)
+    }
+
+    /// Whether the file at `path` is the project itself or has already been
+    /// imported.
+    fn was_imported(&self, path: &std::path::Path) -> bool {
+        let key = crate::filesystem::normalize(path);
+        self.imported.contains(&key)
+            || self
+                .dproj
+                .path
+                .as_deref()
+                .is_some_and(|p| crate::filesystem::normalize(p) == key)
+    }
+
+    /// Evaluate `project`, read from `path`, unless that file has been
+    /// evaluated before: MSBuild skips repeated imports, which also breaks
+    /// import cycles.
+    fn imported_file(
+        &mut self,
+        project: &DprojProject,
+        path: &std::path::Path,
+    ) -> Result<(), DprojError> {
+        if self.was_imported(path) {
+            return Ok(());
+        }
+        self.imported.push(crate::filesystem::normalize(path));
+
+        // MSBuildThisFile* describe the imported file while its groups are
+        // evaluated.
+        let this_file = this_file_properties(&path.to_string_lossy());
+        let outer: Vec<(String, Option<String>)> = this_file
+            .keys()
+            .map(|name| (name.to_string(), self.build_vars.get(name).cloned()))
+            .collect();
+        for (name, value) in &this_file {
+            self.build_vars.insert(name, value);
+            self.vars.insert(name, value);
+        }
+        let evaluated = self.project(project, Some(path));
+        for (name, value) in outer {
+            match value {
+                Some(value) => {
+                    self.build_vars.insert(name.as_str(), value.as_str());
+                    self.vars.insert(name, value);
+                }
+                None => {
+                    self.build_vars.remove(&name);
+                    self.vars.remove(&name);
+                }
+            }
+        }
+        evaluated
+    }
+
+    /// The effective group and the final variable map.  In deferred mode,
+    /// expands the collected values recursively.
+    fn finish(self) -> Result<(PropertyGroup, VarMap), DprojError> {
+        let Self { mut result, vars, deferred, ctx, .. } = self;
+        if !deferred {
+            return Ok((result, vars));
+        }
+        // Resolve in name order so that a cycle is always reported the same
+        // way.
+        let properties = result.properties();
+        let mut names: Vec<&str> = properties.keys().collect();
+        names.sort_unstable_by_key(|name| name.to_ascii_lowercase());
+        let mut resolver = functions::Resolver::msbuild(&vars, ctx.functions);
+        for name in names {
+            resolver.resolve(name)?;
+            for dependency in functions::referenced_variables(&properties[name]) {
+                resolver.resolve(&dependency)?;
+            }
+        }
+        result.expand_vars(&resolver.resolved, &ctx.functions);
+        drop(resolver);
+        Ok((result, vars))
+    }
+}
+
+/// The `MSBuildThisFile*` properties.
+const THIS_FILE_PROPERTIES: [&str; 6] = [
+    "MSBuildThisFile",
+    "MSBuildThisFileName",
+    "MSBuildThisFileExtension",
+    "MSBuildThisFileFullPath",
+    "MSBuildThisFileDirectory",
+    "MSBuildThisFileDirectoryNoRoot",
+];
+
+fn is_this_file_property(name: &str) -> bool {
+    THIS_FILE_PROPERTIES.iter().any(|p| p.eq_ignore_ascii_case(name))
+}
+
+/// Split a Windows or Unix path into its directory, with trailing
+/// separator, and its file name.
+fn split_file_name(path: &str) -> (&str, &str) {
+    match path.rfind(['\\', '/']) {
+        Some(i) => (&path[..=i], &path[i + 1..]),
+        None => ("", path),
+    }
+}
+
+/// The `MSBuildThisFile*` properties of the file at `path`, a Windows or
+/// Unix path.  Directories keep their trailing separator.
+fn this_file_properties(path: &str) -> VarMap {
+    let (directory, file) = split_file_name(path);
+    let (name, extension) = match file.rfind('.') {
+        Some(i) if i > 0 => file.split_at(i),
+        _ => (file, ""),
+    };
+    let no_root = match directory.as_bytes() {
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => &directory[2..],
+        _ => directory,
+    }
+    .trim_start_matches(['\\', '/']);
+
+    let mut vars = VarMap::new();
+    for (property, value) in THIS_FILE_PROPERTIES.into_iter().zip([file, name, extension, path, directory, no_root]) {
+        vars.insert(property, value);
+    }
+    vars
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Option sets
+// ═══════════════════════════════════════════════════════════════════════════════
+
+impl Dproj {
+    /// List the option sets attached to build configurations: every
+    /// `<Import>` of an `.optset` file whose condition references a build
+    /// configuration key (`Cfg_2`) or a configuration/platform key
+    /// (`Cfg_2_Win64`), in document order.
+    ///
+    /// Option sets are part of the effective property group: their imports
+    /// are followed even without [`DprojBuilder::resolve_imports`].
+    pub fn option_sets(&self) -> Vec<AttachedOptionSet> {
+        let build_configs: Vec<&BuildConfiguration> = self
+            .project
+            .item_groups
+            .iter()
+            .flat_map(|ig| &ig.build_configurations)
+            .collect();
+
+        let mut attached = Vec::new();
+        for import in self.project.imports.iter().filter(|i| i.is_option_set()) {
+            let variables = import
+                .condition
+                .as_deref()
+                .map(functions::referenced_variables)
+                .unwrap_or_default();
+            let exact = variables.iter().find_map(|var| {
+                let bc = build_configs.iter().find(|bc| var.eq_ignore_ascii_case(&bc.key))?;
+                Some((var, bc, None))
+            });
+            let found = exact.or_else(|| {
+                variables.iter().find_map(|var| {
+                    build_configs.iter().find_map(|bc| {
+                        let (prefix, rest) = var.split_at_checked(bc.key.len())?;
+                        let platform = rest.strip_prefix('_').filter(|p| !p.is_empty())?;
+                        prefix
+                            .eq_ignore_ascii_case(&bc.key)
+                            .then(|| (var, bc, Some(platform.to_string())))
+                    })
+                })
+            });
+            if let Some((key, bc, platform)) = found {
+                attached.push(AttachedOptionSet {
+                    configuration: bc.name.clone(),
+                    platform,
+                    key: key.clone(),
+                    path: import.project.clone(),
+                });
+            }
+        }
+        attached
+    }
+
+    /// The option sets attached to `config`, including those attached to a
+    /// single platform of it.
+    pub fn option_sets_for(&self, config: &str) -> Vec<AttachedOptionSet> {
+        self.option_sets()
+            .into_iter()
+            .filter(|o| o.configuration == config)
+            .collect()
+    }
+
+    /// Load an attached option set through the configured filesystem.
+    /// `$(…)` references in its path are expanded with the environment and
+    /// relative paths are resolved against the project directory.
+    pub fn load_option_set(&self, attached: &AttachedOptionSet) -> Result<OptionSet, DprojError> {
+        let mut vars = self.env.clone();
+        vars.extend(self.reserved_properties());
+        let path = expand_msbuild_vars(&attached.path, &vars, &self.eval_context().functions);
+        let path = match self.directory.as_deref() {
+            Some(dir) if !crate::filesystem::is_absolute(&path) => dir.join(&path),
+            _ => std::path::PathBuf::from(&path),
+        };
+        let source = self
+            .file_system
+            .read_to_string(&path)
+            .map_err(|e| DprojError::from(e).in_file(&path))?;
+        OptionSet::parse(&source).map_err(|e| e.in_file(&path))
+    }
+
+    /// Attach the option set at `path` to `config`, or to one platform of
+    /// it, the way the IDE does: an `<Import>` is inserted before the
+    /// configuration's `<PropertyGroup>` (`Condition="'$(Cfg_2)'!=''"`, or
+    /// `'$(Cfg_2_Win64)'!=''` for a platform) and `path` is added to the
+    /// group's `<CfgDependentOn>`.  The rest of the source is left
+    /// untouched.
+    ///
+    /// Fails if the configuration or its group does not exist, if the
+    /// option set is already attached, or with [`DprojError::InvalidValue`]
+    /// if `path` contains a `'`, which would end the `Exists('…')`
+    /// condition, or a `;`, which separates `<CfgDependentOn>` entries.
+    pub fn attach_option_set(
+        &mut self,
+        config: &str,
+        platform: Option<&str>,
+        path: &str,
+    ) -> Result<(), DprojError> {
+        if path.contains(['\'', ';']) {
+            return Err(crate::dcc::InvalidValue {
+                property: "CfgDependentOn".into(),
+                value: path.to_string(),
+                expected: "an option set path without ' or ;",
+            }
+            .into());
+        }
+        let key = self.config_key(config, platform)?;
+        if self
+            .option_sets()
+            .iter()
+            .any(|o| o.key.eq_ignore_ascii_case(&key) && o.path.eq_ignore_ascii_case(path))
+        {
+            return Err(DprojError::OptionSetAttached { path: path.to_string(), key });
+        }
+
+        let doc = roxmltree::Document::parse(&self.source)?;
+        let mut source = self.source.clone();
+        let group = self.option_set_group(&doc, &key)?;
+        let newline = line_ending(&self.source);
+        let escaped = escape_xml(path);
+        let group_start = group.range().start;
+        let group_indent = indentation_at(&self.source, group_start).map(str::to_string);
+
+        // Edit back to front so that earlier offsets stay valid.
+        let dependent_on = group
+            .children()
+            .find(|n| n.is_element() && n.tag_name().name() == "CfgDependentOn");
+        let first_child = group.children().find(|n| n.is_element());
+        match (dependent_on, first_child) {
+            (Some(element), _) => {
+                let text = element.text().unwrap_or("");
+                let value = if text.trim().is_empty() {
+                    escaped.clone()
+                } else {
+                    format!("{};{escaped}", escape_xml(text.trim()))
+                };
+                let range = element.range();
+                source.replace_range(range, &format!("<CfgDependentOn>{value}</CfgDependentOn>"));
+            }
+            (None, Some(child)) => {
+                let start = child.range().start;
+                let separator = indentation_at(&self.source, start)
+                    .map(|indent| format!("{newline}{indent}"))
+                    .unwrap_or_default();
+                source.insert_str(
+                    start,
+                    &format!("<CfgDependentOn>{escaped}</CfgDependentOn>{separator}"),
+                );
+            }
+            (None, None) => {
+                let range = group.range();
+                let condition = escape_xml(group.attribute("Condition").unwrap_or_default());
+                let group_indent = group_indent.as_deref().unwrap_or_default();
+                source.replace_range(
+                    range,
+                    &format!(
+                        "<PropertyGroup Condition=\"{condition}\">{newline}\
+                         {group_indent}    <CfgDependentOn>{escaped}</CfgDependentOn>{newline}\
+                         {group_indent}</PropertyGroup>"
+                    ),
+                );
+            }
+        }
+
+        let import = format!(
+            "<Import Project=\"{escaped}\" Condition=\"'$({key})'!='' And Exists('{escaped}')\"/>"
+        );
+        let import = match &group_indent {
+            Some(indent) => format!("{import}{newline}{indent}"),
+            None => import,
+        };
+        source.insert_str(group_start, &import);
+        self.replace_source(source)
+    }
+
+    /// Undo [`attach_option_set`](Self::attach_option_set): remove the
+    /// option set's `<Import>` and its entry in `<CfgDependentOn>`
+    /// (dropping the element when it becomes empty), together with the
+    /// lines they occupied.
+    pub fn detach_option_set(
+        &mut self,
+        config: &str,
+        platform: Option<&str>,
+        path: &str,
+    ) -> Result<(), DprojError> {
+        let key = self.config_key(config, platform)?;
+        let doc = roxmltree::Document::parse(&self.source)?;
+        let mut source = self.source.clone();
+
+        let import = doc
+            .root_element()
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "Import")
+            .find(|n| {
+                n.attribute("Project").is_some_and(|p| p.eq_ignore_ascii_case(path))
+                    && n.attribute("Condition").is_some_and(|c| {
+                        functions::referenced_variables(c)
+                            .iter()
+                            .any(|v| v.eq_ignore_ascii_case(&key))
+                    })
+            })
+            .ok_or_else(|| DprojError::OptionSetNotAttached {
+                path: path.to_string(),
+                key: key.clone(),
+            })?;
+
+        let mut edits: Vec<(std::ops::Range<usize>, String)> =
+            vec![(line_span(&self.source, import.range()), String::new())];
+        if let Ok(group) = self.option_set_group(&doc, &key)
+            && let Some(element) = group
+                .children()
+                .find(|n| n.is_element() && n.tag_name().name() == "CfgDependentOn")
+        {
+            let text = element.text().unwrap_or("");
+            let remaining: Vec<&str> = text
+                .split(';')
+                .map(str::trim)
+                .filter(|p| !p.is_empty() && !p.eq_ignore_ascii_case(path))
+                .collect();
+            if remaining.is_empty() {
+                edits.push((line_span(&self.source, element.range()), String::new()));
+            } else {
+                let value = escape_xml(&remaining.join(";"));
+                edits.push((element.range(), format!("<CfgDependentOn>{value}</CfgDependentOn>")));
+            }
+        }
+
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        for (range, replacement) in edits {
+            source.replace_range(range, &replacement);
+        }
+        self.replace_source(source)
+    }
+
+    /// The variable that selects `config` (and `platform`), e.g. `Cfg_2` or
+    /// `Cfg_2_Win64`.  Option sets and the configuration's settings group
+    /// are conditioned on it.
+    fn config_key(&self, config: &str, platform: Option<&str>) -> Result<String, DprojError> {
+        let key = self
+            .project
+            .item_groups
+            .iter()
+            .flat_map(|ig| &ig.build_configurations)
+            .find(|bc| bc.name == config)
+            .map(|bc| bc.key.clone())
+            .ok_or_else(|| DprojError::ConfigurationNotFound { name: config.to_string() })?;
+        Ok(match platform {
+            Some(platform) => format!("{key}_{platform}"),
+            None => key,
+        })
+    }
+
+    /// The first `<PropertyGroup>` with `Condition="'$(key)'!=''"`.
+    fn option_set_group<'a, 'input>(
+        &self,
+        doc: &'a roxmltree::Document<'input>,
+        key: &str,
+    ) -> Result<roxmltree::Node<'a, 'input>, DprojError> {
+        let wanted = condition::parse_condition(&format!("'$({key})'!=''"))?;
+        doc.root_element()
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "PropertyGroup")
+            .find(|n| {
+                n.attribute("Condition").is_some_and(|c| {
+                    self.conditions
+                        .parse(c)
+                        .is_ok_and(|expr| *expr == wanted)
+                })
+            })
+            .ok_or_else(|| DprojError::ElementNotFound {
+                element: format!("<PropertyGroup Condition=\"'$({key})'!=''\">"),
+                context: "the project".into(),
+            })
+    }
+
+    /// Rebuild [`project`](Self::project) from the source after a
+    /// structural edit.
+    fn reparse(&mut self) -> Result<(), DprojError> {
+        let doc = roxmltree::Document::parse(&self.source)?;
+        self.project = DprojProject::parse(doc.root_element())?;
+        Ok(())
+    }
+
+    /// Replace the source with `source`, an edited copy, and rebuild
+    /// [`project`](Self::project) from it.  If `source` does not parse,
+    /// the error is returned and the project is left unchanged.
+    fn replace_source(&mut self, source: String) -> Result<(), DprojError> {
+        let doc = roxmltree::Document::parse(&source)?;
+        let project = DprojProject::parse(doc.root_element())?;
+        drop(doc);
+        self.source = source;
+        self.project = project;
+        Ok(())
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  List properties
+// ═══════════════════════════════════════════════════════════════════════════════
+
+impl Dproj {
+    /// The `tag` property of the `pg_index`-th `<PropertyGroup>` (0-based),
+    /// as written, split into a [`PropertyList`].  Empty when the group does
+    /// not set `tag`.
+    pub fn property_list(&self, pg_index: usize, tag: &str) -> Result<PropertyList, DprojError> {
+        let pg = self.project.property_groups.get(pg_index).ok_or(
+            DprojError::IndexOutOfBounds {
+                index: pg_index,
+                len: self.project.property_groups.len(),
+            },
+        )?;
+        let value = pg.properties().get(tag).cloned().unwrap_or_default();
+        Ok(PropertyList::parse(tag, &value))
+    }
+
+    /// Add `entry` to the `tag` list of the `pg_index`-th `<PropertyGroup>`,
+    /// before the inherited `$(tag)` reference.  Several entries can be
+    /// added at once, separated by `;`.  Returns `false` if they are
+    /// already in the list.  Only the element's text changes; the
+    /// element is inserted if the group does not have one.
+    ///
+    /// # Example
+    /// ```
+    /// # let source = r#"<Project><PropertyGroup>
+    /// #     <DCC_Define>DEBUG;$(DCC_Define)</DCC_Define>
+    /// # </PropertyGroup></Project>"#;
+    /// let mut dproj = dproj_rs::Dproj::parse(source)?;
+    /// dproj.add_list_entry(0, "DCC_Define", "LOGGING")?;
+    /// assert!(dproj.source().contains("<DCC_Define>DEBUG;LOGGING;$(DCC_Define)</DCC_Define>"));
+    /// # Ok::<(), dproj_rs::dproj::DprojError>(())
+    /// ```
+    pub fn add_list_entry(
+        &mut self,
+        pg_index: usize,
+        tag: &str,
+        entry: &str,
+    ) -> Result<bool, DprojError> {
+        self.edit_list(pg_index, tag, |list| list.add(entry))
+    }
+
+    /// Remove `entry`, or several entries separated by `;`, from the `tag`
+    /// list of the `pg_index`-th `<PropertyGroup>`.  Returns whether any
+    /// was present.
+    pub fn remove_list_entry(
+        &mut self,
+        pg_index: usize,
+        tag: &str,
+        entry: &str,
+    ) -> Result<bool, DprojError> {
+        self.edit_list(pg_index, tag, |list| list.remove(entry))
+    }
+
+    /// Move `entry` to `position` among the own entries of the `tag` list of
+    /// the `pg_index`-th `<PropertyGroup>`.  Returns `false` if the entry is
+    /// not in the list.
+    pub fn move_list_entry(
+        &mut self,
+        pg_index: usize,
+        tag: &str,
+        entry: &str,
+        position: usize,
+    ) -> Result<bool, DprojError> {
+        self.edit_list(pg_index, tag, |list| list.move_entry(entry, position))
+    }
+
+    /// Remove repeated entries from the `tag` list of the `pg_index`-th
+    /// `<PropertyGroup>`, keeping the first occurrence of each.  Returns
+    /// whether anything was removed.
+    pub fn dedup_list(&mut self, pg_index: usize, tag: &str) -> Result<bool, DprojError> {
+        self.edit_list(pg_index, tag, PropertyList::dedup)
+    }
+
+    /// Apply `edit` to a list property and write it back if it changed.
+    fn edit_list(
+        &mut self,
+        pg_index: usize,
+        tag: &str,
+        edit: impl FnOnce(&mut PropertyList) -> bool,
+    ) -> Result<bool, DprojError> {
+        let mut list = self.property_list(pg_index, tag)?;
+        if !edit(&mut list) {
+            return Ok(false);
+        }
+        self.set_or_insert_property(pg_index, tag, &list.to_string())?;
+        Ok(true)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Configuration groups
+// ═══════════════════════════════════════════════════════════════════════════════
+
+impl Dproj {
+    /// Index of the `<PropertyGroup>` holding the settings of `config`, or of
+    /// one platform of it: the group with `Condition="'$(Cfg_2)'!=''"`
+    /// (`'$(Cfg_2_Win64)'!=''` for a platform).  `Ok(None)` if the project
+    /// has no such group.
+    pub fn config_property_group(
+        &self,
+        config: &str,
+        platform: Option<&str>,
+    ) -> Result<Option<usize>, DprojError> {
+        let key = self.config_key(config, platform)?;
+        Ok(self.settings_group_index(&key))
+    }
+
+    /// Set `tag` to `value` in the settings group of `config`, or of one
+    /// platform of it (see [`config_property_group`](Self::config_property_group)).
+    ///
+    /// When the group does not exist it is created the way the IDE lays
+    /// out a project: for a platform, a selector group
+    /// (`('$(Platform)'=='Win64' and '$(Cfg_2)'=='true') or '$(Cfg_2_Win64)'!=''`)
+    /// is added after the configuration's other selector groups, and the
+    /// settings group after the configuration's other settings groups.
+    /// `platform` must be one of the project's [`platforms`](Self::platforms)
+    /// or a [`TargetPlatform`]; otherwise nothing is edited and
+    /// [`DprojError::UnknownPlatform`] is returned.
+    ///
+    /// # Example
+    /// ```
+    /// let mut dproj = dproj_rs::Dproj::from_file("example.dproj")?;
+    /// dproj.set_config_property("Release", Some("Win64"), "DCC_Optimize", "true")?;
+    /// let release = dproj.active_property_group_for("Release", "Win64")?;
+    /// assert_eq!(release.dcc_options.optimize.as_deref(), Some("true"));
+    /// # Ok::<(), dproj_rs::dproj::DprojError>(())
+    /// ```
+    pub fn set_config_property(
+        &mut self,
+        config: &str,
+        platform: Option<&str>,
+        tag: &str,
+        value: &str,
+    ) -> Result<(), DprojError> {
+        if let Some(platform) = platform {
+            let known = self.platforms().iter().any(|(p, _)| p.eq_ignore_ascii_case(platform))
+                || TargetPlatform::ALL.iter().any(|p| p.as_str().eq_ignore_ascii_case(platform));
+            if !known {
+                return Err(crate::platform::UnknownPlatform(platform.to_string()).into());
+            }
+        }
+        let key = self.config_key(config, platform)?;
+        let index = match self.settings_group_index(&key) {
+            Some(index) => index,
+            None => self.insert_config_groups(config, platform, &key)?,
+        };
+        self.set_or_insert_property(index, tag, value)
+    }
+
+    /// The variable `pg` is conditioned on if it is a settings group
+    /// (`Condition="'$(Cfg_2)'!=''"`).
+    fn settings_group_key(&self, pg: &PropertyGroup) -> Option<String> {
+        let condition = pg.condition.as_deref()?;
+        let [var]: [String; 1] = functions::referenced_variables(condition).try_into().ok()?;
+        let wanted = condition::parse_condition(&format!("'$({var})'!=''")).ok()?;
+        self.conditions
+            .parse(condition)
+            .is_ok_and(|expr| *expr == wanted)
+            .then_some(var)
+    }
+
+    fn settings_group_index(&self, key: &str) -> Option<usize> {
+        self.project.property_groups.iter().position(|pg| {
+            self.settings_group_key(pg)
+                .is_some_and(|k| k.eq_ignore_ascii_case(key))
+        })
+    }
+
+    /// Add the settings group for `key` and, for a platform, its selector
+    /// group.  Returns the index of the settings group.
+    fn insert_config_groups(
+        &mut self,
+        config: &str,
+        platform: Option<&str>,
+        key: &str,
+    ) -> Result<usize, DprojError> {
+        let config_key = self.config_key(config, None)?;
+        let belongs = |k: &str| {
+            k.eq_ignore_ascii_case(&config_key)
+                || k.split_at_checked(config_key.len()).is_some_and(|(prefix, rest)| {
+                    prefix.eq_ignore_ascii_case(&config_key) && rest.starts_with('_')
+                })
+        };
+        let groups = &self.project.property_groups;
+        let settings_keys: Vec<Option<String>> =
+            groups.iter().map(|pg| self.settings_group_key(pg)).collect();
+        let Some(last_group) = groups.len().checked_sub(1) else {
+            return Err(DprojError::ElementNotFound {
+                element: "<PropertyGroup>".into(),
+                context: "the project".into(),
+            });
+        };
+        let references = |pg: &PropertyGroup, var: &str| {
+            pg.condition.as_deref().is_some_and(|c| {
+                functions::referenced_variables(c)
+                    .iter()
+                    .any(|v| v.eq_ignore_ascii_case(var))
+            })
+        };
+
+        // Groups are inserted after the group at these indices.
+        let settings_after = settings_keys
+            .iter()
+            .rposition(|k| k.as_deref().is_some_and(belongs))
+            .or_else(|| settings_keys.iter().rposition(Option::is_some))
+            .unwrap_or(last_group);
+        let selector = platform.filter(|_| {
+            !groups
+                .iter()
+                .zip(&settings_keys)
+                .any(|(pg, k)| k.is_none() && references(pg, key))
+        });
+        let selector_after = selector.map(|_| {
+            let first_settings = settings_keys.iter().position(Option::is_some);
+            let selectors = &groups[..first_settings.unwrap_or(groups.len())];
+            selectors
+                .iter()
+                .rposition(|pg| references(pg, &config_key))
+                .or_else(|| first_settings.and_then(|i| i.checked_sub(1)))
+                .unwrap_or(last_group)
+        });
+
+        // The keys of the configuration and its ancestors, all set to
+        // `true` by the selector group.  `<CfgParent>` names the parent.
+        let mut ancestors = Vec::new();
+        let mut current_name = config.to_string();
+        let mut visited = Vec::new();
+        loop {
+            if visited.contains(&current_name) {
+                break; // prevent cycles
+            }
+            visited.push(current_name.clone());
+
+            let Some(bc) = self
+                .project
+                .item_groups
+                .iter()
+                .flat_map(|ig| &ig.build_configurations)
+                .find(|bc| bc.name == current_name)
+            else {
+                break;
+            };
+            ancestors.push(bc.key.clone());
+
+            let Some(parent) = &bc.cfg_parent else {
+                break;
+            };
+            current_name = parent.clone();
+        }
+
+        let doc = roxmltree::Document::parse(&self.source)?;
+        let mut source = self.source.clone();
+        let newline = line_ending(&self.source);
+        // Each new group goes on its own line, indented like the group it
+        // follows.
+        let after = |index: usize| -> Result<(usize, String, &str), DprojError> {
+            let node = self.property_group_node(&doc, index)?;
+            let indent = indentation_at(&self.source, node.range().start);
+            let separator = indent.map(|i| format!("{newline}{i}")).unwrap_or_default();
+            Ok((node.range().end, separator, indent.unwrap_or_default()))
+        };
+
+        let (offset, separator, _) = after(settings_after)?;
+        let mut edits = vec![(
+            offset,
+            format!("{separator}<PropertyGroup Condition=\"'$({key})'!=''\"/>"),
+        )];
+        if let (Some(platform), Some(index)) = (selector, selector_after) {
+            let (offset, separator, indent) = after(index)?;
+            let child = if indent.is_empty() { "    " } else { indent };
+            let platform = escape_xml(platform);
+            let mut group = format!(
+                "{separator}<PropertyGroup Condition=\"('$(Platform)'=='{platform}' and '$({config_key})'=='true') \
+                 or '$({key})'!=''\">{newline}\
+                 {indent}{child}<{key}>true</{key}>{newline}\
+                 {indent}{child}<CfgParent>{config_key}</CfgParent>{newline}"
+            );
+            for ancestor in &ancestors {
+                group.push_str(&format!("{indent}{child}<{ancestor}>true</{ancestor}>{newline}"));
+            }
+            group.push_str(&format!("{indent}</PropertyGroup>"));
+            edits.push((offset, group));
+        }
+
+        // Edit back to front so that earlier offsets stay valid; at equal
+        // offsets the selector group goes first.
+        edits.sort_by_key(|(offset, _)| std::cmp::Reverse(*offset));
+        for (offset, text) in edits {
+            source.insert_str(offset, &text);
+        }
+        self.replace_source(source)?;
+        self.settings_group_index(key).ok_or_else(|| DprojError::ElementNotFound {
+            element: format!("<PropertyGroup Condition=\"'$({key})'!=''\">"),
+            context: "the project".into(),
+        })
     }
 }
 
@@ -1641,7 +4088,7 @@ impl Dproj {
 // ═══════════════════════════════════════════════════════════════════════════════
 
 impl DprojProject {
-    fn parse(root: roxmltree::Node) -> Result<Self, DprojError> {
+    pub(crate) fn parse(root: roxmltree::Node) -> Result<Self, DprojError> {
         let mut project = Self::default();
 
         for child in root.children().filter(|n| n.is_element()) {
@@ -1659,6 +4106,7 @@ impl DprojProject {
                     project.imports.push(Import {
                         project: child.attribute("Project").unwrap_or("").to_string(),
                         condition: child.attribute("Condition").map(String::from),
+                        position: project.property_groups.len(),
                     });
                 }
                 _ => {}
@@ -1680,26 +4128,50 @@ impl PropertyGroup {
 
         for child in node.children().filter(|n| n.is_element()) {
             let tag = child.tag_name().name();
-            let text = child.text().unwrap_or("").to_string();
-
-            if set_project_property(tag, &text, &mut pg.project_properties) { continue; }
-            if set_dcc_option(tag, &text, &mut pg.dcc_options) { continue; }
-            if set_brcc_option(tag, &text, &mut pg.brcc_options) { continue; }
-            if set_build_event(tag, &text, &mut pg.build_events) { continue; }
-            if set_ver_info(tag, &text, &mut pg.ver_info) { continue; }
-            if set_platform_packaging(tag, &text, &mut pg.platform_packaging) { continue; }
-            if set_debugger_option(tag, &text, &mut pg.debugger_options) { continue; }
-
-            // Unrecognised element → stash in `other`.
-            pg.other.insert(tag.to_string(), text);
+            let value = child.text().unwrap_or("");
+            let condition = child.attribute("Condition");
+            if condition.is_none() {
+                pg.set(tag, Some(value));
+            }
+            pg.elements.push(PropertyElement {
+                tag: tag.to_string(),
+                condition: condition.map(String::from),
+                value: value.to_string(),
+            });
+        }
+        if pg.conditional_elements().next().is_none() {
+            pg.elements.clear();
         }
 
         pg
     }
+
+    /// Set (`Some`) or clear (`None`) the value stored for an XML tag,
+    /// dispatching to the matching typed field.  Unrecognised tags go to
+    /// [`other`](Self::other).
+    fn set(&mut self, tag: &str, value: Option<&str>) {
+        if set_project_property(tag, value, &mut self.project_properties) { return; }
+        if set_dcc_option(tag, value, &mut self.dcc_options) { return; }
+        if set_brcc_option(tag, value, &mut self.brcc_options) { return; }
+        if set_build_event(tag, value, &mut self.build_events) { return; }
+        if set_ver_info(tag, value, &mut self.ver_info) { return; }
+        if set_platform_packaging(tag, value, &mut self.platform_packaging) { return; }
+        if set_debugger_option(tag, value, &mut self.debugger_options) { return; }
+
+        // Unrecognised element → stash in `other`.
+        match value {
+            Some(v) => {
+                self.other.insert(tag.to_string(), v.to_string());
+            }
+            None => {
+                self.other.remove(tag);
+            }
+        }
+    }
 }
 
-fn set_project_property(tag: &str, text: &str, p: &mut ProjectProperties) -> bool {
-    let s = || Some(text.to_string());
+fn set_project_property(tag: &str, text: Option<&str>, p: &mut ProjectProperties) -> bool {
+    let s = || text.map(String::from);
     match tag {
         "ProjectGuid"          => p.project_guid = s(),
         "ProjectVersion"       => p.project_version = s(),
@@ -1726,8 +4198,8 @@ fn set_project_property(tag: &str, text: &str, p: &mut ProjectProperties) -> boo
     true
 }
 
-fn set_dcc_option(tag: &str, text: &str, o: &mut DccOptions) -> bool {
-    let s = || Some(text.to_string());
+fn set_dcc_option(tag: &str, text: Option<&str>, o: &mut DccOptions) -> bool {
+    let s = || text.map(String::from);
     match tag {
         // Compiler identity
         "DCC_DCCCompiler"                  => o.dcc_compiler = s(),
@@ -1836,16 +4308,21 @@ fn set_dcc_option(tag: &str, text: &str, o: &mut DccOptions) -> bool {
         "DCC_macOSArmMinimumVersion"       => o.macos_arm_minimum_version = s(),
         "DCC_macOSMinimumVersion"          => o.macos_minimum_version = s(),
         // Any other DCC_ tag → warning / hint directive.
-        _ if tag.starts_with("DCC_") => {
-            o.warning_directives.insert(tag.to_string(), text.to_string());
-        }
+        _ if tag.starts_with("DCC_") => match text {
+            Some(text) => {
+                o.warning_directives.insert(tag.to_string(), text.to_string());
+            }
+            None => {
+                o.warning_directives.remove(tag);
+            }
+        },
         _ => return false,
     }
     true
 }
 
-fn set_brcc_option(tag: &str, text: &str, o: &mut BrccOptions) -> bool {
-    let s = || Some(text.to_string());
+fn set_brcc_option(tag: &str, text: Option<&str>, o: &mut BrccOptions) -> bool {
+    let s = || text.map(String::from);
     match tag {
         "BRCC_UserSuppliedOptions" => o.user_supplied_options = s(),
         "BRCC_CodePage"            => o.code_page = s(),
@@ -1863,8 +4340,8 @@ fn set_brcc_option(tag: &str, text: &str, o: &mut BrccOptions) -> bool {
     true
 }
 
-fn set_build_event(tag: &str, text: &str, e: &mut BuildEvents) -> bool {
-    let s = || Some(text.to_string());
+fn set_build_event(tag: &str, text: Option<&str>, e: &mut BuildEvents) -> bool {
+    let s = || text.map(String::from);
     match tag {
         "PreBuildEvent"                 => e.pre_build_event = s(),
         "PreBuildEventCancelOnError"    => e.pre_build_event_cancel_on_error = s(),
@@ -1881,8 +4358,8 @@ fn set_build_event(tag: &str, text: &str, e: &mut BuildEvents) -> bool {
     true
 }
 
-fn set_ver_info(tag: &str, text: &str, v: &mut VerInfo) -> bool {
-    let s = || Some(text.to_string());
+fn set_ver_info(tag: &str, text: Option<&str>, v: &mut VerInfo) -> bool {
+    let s = || text.map(String::from);
     match tag {
         "VerInfo_IncludeVerInfo"  => v.include_ver_info = s(),
         "VerInfo_MajorVer"       => v.major_ver = s(),
@@ -1902,8 +4379,8 @@ fn set_ver_info(tag: &str, text: &str, v: &mut VerInfo) -> bool {
     true
 }
 
-fn set_platform_packaging(tag: &str, text: &str, p: &mut PlatformPackaging) -> bool {
-    let s = || Some(text.to_string());
+fn set_platform_packaging(tag: &str, text: Option<&str>, p: &mut PlatformPackaging) -> bool {
+    let s = || text.map(String::from);
     match tag {
         "AppDPIAwarenessMode"         => p.app_dpi_awareness_mode = s(),
         "AppEnableRuntimeThemes"      => p.app_enable_runtime_themes = s(),
@@ -1924,13 +4401,15 @@ fn set_platform_packaging(tag: &str, text: &str, p: &mut PlatformPackaging) -> b
     true
 }
 
-fn set_debugger_option(tag: &str, text: &str, d: &mut DebuggerOptions) -> bool {
-    let s = || Some(text.to_string());
+fn set_debugger_option(tag: &str, text: Option<&str>, d: &mut DebuggerOptions) -> bool {
+    let s = || text.map(String::from);
     match tag {
         "Debugger_IncludeSystemVars" => d.include_system_vars = s(),
         "Debugger_EnvVars"           => d.env_vars = s(),
         "Debugger_SymbolSourcePath"  => d.symbol_source_path = s(),
         "Debugger_RunParams"         => d.run_params = s(),
+        "Debugger_HostApplication"   => d.host_application = s(),
+        "Debugger_CWD"               => d.cwd = s(),
         _ => return false,
     }
     true
@@ -2165,123 +4644,803 @@ fn parse_deploy_platforms<T>(
         .collect()
 }
 
-// ─── Helpers ─────────────────────────────────────────────────────────────────
+// ─── Helpers ─────────────────────────────────────────────────────────────────
+
+/// Return the text content of the first child element with the given tag name.
+fn find_child_text(parent: &roxmltree::Node, tag: &str) -> Option<String> {
+    parent
+        .children()
+        .find(|c| c.is_element() && c.tag_name().name() == tag)
+        .and_then(|c| c.text())
+        .map(String::from)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smoke-test: every .dproj in the repo root must parse without error.
+    #[test]
+    fn parse_all_dproj_files() {
+        let files = [
+            "example.dproj",
+        ];
+        for file in &files {
+            let result = Dproj::from_file(file);
+            assert!(result.is_ok(), "Failed to parse {file}: {}", result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn example_dproj_basic_properties() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let pg0 = &dproj.project.property_groups[0];
+        assert_eq!(pg0.project_properties.project_version.as_deref(), Some("20.1"));
+        assert_eq!(pg0.project_properties.framework_type.as_deref(), Some("VCL"));
+        assert!(pg0.project_properties.project_guid.is_some());
+    }
+
+    #[test]
+    fn set_property_value_round_trip() {
+        let source = std::fs::read_to_string("example.dproj").unwrap();
+        let mut dproj = Dproj::parse(source).unwrap();
+
+        let old = dproj.project.property_groups[0]
+            .project_properties
+            .project_version
+            .clone();
+        assert_eq!(old.as_deref(), Some("20.1"));
+
+        dproj.set_property_value(0, "ProjectVersion", "99.9").unwrap();
+        assert_eq!(
+            dproj.project.property_groups[0]
+                .project_properties
+                .project_version
+                .as_deref(),
+            Some("99.9")
+        );
+
+        // The raw source should reflect the change too.
+        assert!(dproj.source().contains("<ProjectVersion>99.9</ProjectVersion>"));
+
+        dproj.set_property_value(0, "ProjectVersion", "1 < 2 & 3").unwrap();
+        assert!(dproj.source().contains("<ProjectVersion>1 &lt; 2 &amp; 3</ProjectVersion>"));
+        let reparsed = Dproj::parse(dproj.source()).unwrap();
+        let version = &reparsed.project.property_groups[0].project_properties.project_version;
+        assert_eq!(version.as_deref(), Some("1 < 2 & 3"));
+    }
+
+    #[test]
+    fn insert_and_remove_property() {
+        let source = std::fs::read_to_string("example.dproj").unwrap();
+        let mut dproj = Dproj::parse(source.clone()).unwrap();
+        let index = dproj
+            .project
+            .property_groups
+            .iter()
+            .position(|pg| pg.condition.as_deref() == Some("'$(Cfg_1_Win32)'!=''"))
+            .unwrap();
+
+        dproj.insert_property(index, "DCC_MapFile", "3").unwrap();
+        let newline = line_ending(&source);
+        assert!(dproj.source().contains(&format!(
+            "<VerInfo_Locale>1033</VerInfo_Locale>{newline}        <DCC_MapFile>3</DCC_MapFile>{newline}    </PropertyGroup>"
+        )));
+        let pg = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        assert_eq!(pg.dcc_options.map_file.as_deref(), Some("3"));
+
+        let err = dproj.insert_property(index, "DCC_MapFile", "0").unwrap_err();
+        assert!(matches!(err, DprojError::ElementExists { .. }), "{err:?}");
+        assert_eq!(err.to_string(), format!("PropertyGroup #{index} already has a <DCC_MapFile> element"));
+
+        dproj.set_or_insert_property(index, "DCC_MapFile", "0").unwrap();
+        assert!(dproj.source().contains("<DCC_MapFile>0</DCC_MapFile>"));
+        dproj.remove_property(index, "DCC_MapFile").unwrap();
+        assert_eq!(dproj.source(), source);
+        let err = dproj.remove_property(index, "DCC_MapFile").unwrap_err();
+        assert!(matches!(err, DprojError::ElementNotFound { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn insert_property_into_empty_group() {
+        let mut dproj = Dproj::parse(
+            "<Project>\n    <PropertyGroup Condition=\"'$(Base)'!=''\"/>\n    <PropertyGroup></PropertyGroup>\n</Project>",
+        )
+        .unwrap();
+        dproj.set_or_insert_property(0, "DCC_Define", "A&B").unwrap();
+        dproj.insert_property(1, "Config", "Debug").unwrap();
+        assert_eq!(
+            dproj.source(),
+            "<Project>\n    <PropertyGroup Condition=\"'$(Base)'!=''\">\n        <DCC_Define>A&amp;B</DCC_Define>\n    </PropertyGroup>\n    \
+             <PropertyGroup>\n        <Config>Debug</Config>\n    </PropertyGroup>\n</Project>"
+        );
+        assert_eq!(dproj.project.property_groups[0].dcc_options.define.as_deref(), Some("A&B"));
+    }
+
+    #[test]
+    fn failed_insert_leaves_the_project_unchanged() {
+        let source = std::fs::read_to_string("example.dproj").unwrap();
+        let mut dproj = Dproj::parse(source.clone()).unwrap();
+        let groups = dproj.project.property_groups.len();
+
+        let err = dproj.insert_property(0, "Bad Tag", "x").unwrap_err();
+        assert!(matches!(err, DprojError::Xml { .. }), "{err:?}");
+        assert_eq!(dproj.source(), source);
+        assert_eq!(dproj.project.property_groups.len(), groups);
+
+        dproj.set_or_insert_property(0, "DCC_MapFile", "3").unwrap();
+        assert!(dproj.source().contains("<DCC_MapFile>3</DCC_MapFile>"));
+    }
+
+    #[test]
+    fn set_config_property_uses_existing_groups() {
+        let source = std::fs::read_to_string("example.dproj").unwrap();
+        let mut dproj = Dproj::parse(source.clone()).unwrap();
+        let index = dproj.config_property_group("Release", Some("Win64")).unwrap().unwrap();
+        assert_eq!(
+            dproj.project.property_groups[index].condition.as_deref(),
+            Some("'$(Cfg_2_Win64)'!=''")
+        );
+        let debug = dproj.config_property_group("Debug", None).unwrap().unwrap();
+        assert_eq!(dproj.project.property_groups[debug].condition.as_deref(), Some("'$(Cfg_1)'!=''"));
+
+        dproj.set_config_property("Release", Some("Win64"), "DCC_Optimize", "false").unwrap();
+        dproj.set_config_property("Debug", None, "DCC_Optimize", "true").unwrap();
+        let groups = dproj.project.property_groups.len();
+        assert_eq!(Dproj::parse(source.clone()).unwrap().project.property_groups.len(), groups);
+        let release = dproj.active_property_group_for("Release", "Win64").unwrap();
+        assert_eq!(release.dcc_options.optimize.as_deref(), Some("false"));
+        let debug = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        assert_eq!(debug.dcc_options.optimize.as_deref(), Some("true"));
+
+        assert!(matches!(
+            dproj.set_config_property("Nope", None, "DCC_Optimize", "true"),
+            Err(DprojError::ConfigurationNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn set_config_property_creates_ide_shaped_groups() {
+        let source = std::fs::read_to_string("example.dproj").unwrap().replace("\r\n", "\n");
+        let mut dproj = Dproj::parse(source).unwrap();
+        assert_eq!(dproj.config_property_group("Release", Some("Linux64")).unwrap(), None);
+
+        dproj.set_config_property("Release", Some("Linux64"), "DCC_Define", "LINUX;$(DCC_Define)").unwrap();
+        assert!(dproj.source().contains(
+            "        <Base>true</Base>\n    </PropertyGroup>\n    \
+             <PropertyGroup Condition=\"('$(Platform)'=='Linux64' and '$(Cfg_2)'=='true') or '$(Cfg_2_Linux64)'!=''\">\n        \
+             <Cfg_2_Linux64>true</Cfg_2_Linux64>\n        \
+             <CfgParent>Cfg_2</CfgParent>\n        \
+             <Cfg_2>true</Cfg_2>\n        \
+             <Base>true</Base>\n    \
+             </PropertyGroup>\n    \
+             <PropertyGroup Condition=\"'$(Base)'!=''\">"
+        ));
+        assert!(dproj.source().contains(
+            "        <AppDPIAwarenessMode>PerMonitorV2</AppDPIAwarenessMode>\n    </PropertyGroup>\n    \
+             <PropertyGroup Condition=\"'$(Cfg_2_Linux64)'!=''\">\n        \
+             <DCC_Define>LINUX;$(DCC_Define)</DCC_Define>\n    \
+             </PropertyGroup>\n    <ItemGroup>"
+        ));
+
+        let pg = dproj.active_property_group_for("Release", "Linux64").unwrap();
+        assert!(pg.dcc_options.define.as_deref().unwrap().starts_with("LINUX;"));
+        let win64 = dproj.active_property_group_for("Release", "Win64").unwrap();
+        assert!(!win64.dcc_options.define.as_deref().unwrap_or_default().contains("LINUX"));
+
+        // A second value reuses the new group.
+        let groups = dproj.project.property_groups.len();
+        dproj.set_config_property("Release", Some("Linux64"), "DCC_Optimize", "true").unwrap();
+        assert_eq!(dproj.project.property_groups.len(), groups);
+    }
+
+    #[test]
+    fn set_config_property_selects_every_ancestor() {
+        let source = std::fs::read_to_string("example.dproj").unwrap().replace("\r\n", "\n").replace(
+            "    </ItemGroup>\n    <ProjectExtensions>",
+            "        <BuildConfiguration Include=\"MyDebug\">\n            \
+             <Key>Cfg_3</Key>\n            \
+             <CfgParent>Debug</CfgParent>\n        \
+             </BuildConfiguration>\n    </ItemGroup>\n    <ProjectExtensions>",
+        );
+        let mut dproj = Dproj::parse(source).unwrap();
+
+        dproj.set_config_property("MyDebug", Some("Win64"), "DCC_Optimize", "false").unwrap();
+        assert!(dproj.source().contains(
+            "<PropertyGroup Condition=\"('$(Platform)'=='Win64' and '$(Cfg_3)'=='true') or '$(Cfg_3_Win64)'!=''\">\n        \
+             <Cfg_3_Win64>true</Cfg_3_Win64>\n        \
+             <CfgParent>Cfg_3</CfgParent>\n        \
+             <Cfg_3>true</Cfg_3>\n        \
+             <Cfg_1>true</Cfg_1>\n        \
+             <Base>true</Base>\n    \
+             </PropertyGroup>"
+        ));
+        let pg = dproj.active_property_group_for("MyDebug", "Win64").unwrap();
+        assert_eq!(pg.dcc_options.optimize.as_deref(), Some("false"));
+    }
+
+    #[test]
+    fn set_config_property_rejects_unknown_platforms() {
+        let source = std::fs::read_to_string("example.dproj").unwrap();
+        let mut dproj = Dproj::parse(source.clone()).unwrap();
+        for platform in ["Win46", "Win64'", " Win64"] {
+            let err = dproj.set_config_property("Release", Some(platform), "DCC_Optimize", "true").unwrap_err();
+            assert!(matches!(err, DprojError::UnknownPlatform(_)), "{err:?}");
+        }
+        assert_eq!(dproj.source(), source);
+        dproj.set_config_property("Release", Some("win64"), "DCC_Optimize", "true").unwrap();
+    }
+
+    // ── Active property group resolution ─────────────────────────────────
+
+    #[test]
+    fn active_pg_all_files() {
+        // Every dproj must resolve an active PG for its default config/platform.
+        let files = [
+            "example.dproj",
+        ];
+        for file in files {
+            let dproj = Dproj::from_file(file).unwrap();
+            let result = dproj.active_property_group();
+            assert!(
+                result.is_ok(),
+                "active_property_group failed for {file}: {}",
+                result.unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn active_pg_example_debug_win32() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        // example.dproj defaults to Debug/Win32.
+        let pg = dproj.active_property_group().unwrap();
+
+        // Core project properties should be populated from the unconditional PG.
+        assert_eq!(pg.project_properties.project_version.as_deref(), Some("20.1"));
+        assert_eq!(pg.project_properties.framework_type.as_deref(), Some("VCL"));
+
+        // Debug-specific DCC options should be merged in.
+        // In example.dproj, Debug has DCC_Optimize = "false" and
+        // DCC_DebugInformation = "2".
+        assert!(
+            pg.dcc_options.optimize.is_some() || pg.dcc_options.debug_information.is_some(),
+            "Expected some DCC options from the Debug config PG"
+        );
+    }
+
+    #[test]
+    fn active_pg_example_release_win32() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let pg = dproj.active_property_group_for("Release", "Win32").unwrap();
+
+        assert_eq!(pg.project_properties.project_version.as_deref(), Some("20.1"));
+        // Release should have its own DCC options (optimization, etc.)
+        assert!(pg.dcc_options.optimize.is_some() || pg.dcc_options.debug_information.is_some());
+    }
+
+    #[test]
+    fn active_pg_nonexistent_config() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let result = dproj.active_property_group_for("DoesNotExist", "Win32");
+        assert!(matches!(
+            result,
+            Err(DprojError::ConfigurationNotFound { name }) if name == "DoesNotExist"
+        ));
+    }
+
+    #[test]
+    fn errors_are_typed() {
+        let err = Dproj::parse("<Project>\n  <PropertyGroup>\n</Project>").unwrap_err();
+        let DprojError::Xml { path: None, line, column, .. } = err else { panic!("{err:?}") };
+        assert_eq!((line, column), (3, 1));
+
+        let err = Dproj::from_file("missing.dproj").unwrap_err();
+        assert!(
+            matches!(&err, DprojError::Io { path: Some(p), source }
+                if p.ends_with("missing.dproj") && source.kind() == std::io::ErrorKind::NotFound),
+            "{err:?}"
+        );
+        assert!(std::error::Error::source(&err).is_some());
+
+        let mut dproj = Dproj::parse(std::fs::read_to_string("example.dproj").unwrap()).unwrap();
+        assert!(matches!(
+            dproj.get_exe_path(),
+            Err(DprojError::NoDirectory { operation: "exe path" })
+        ));
+        let len = dproj.project.property_groups.len();
+        let err = dproj.set_property_value(len, "DCC_Define", "X").unwrap_err();
+        assert!(matches!(err, DprojError::IndexOutOfBounds { index, len: l } if index == len && l == len));
+        let err = dproj.set_property_value(0, "NoSuchElement", "X").unwrap_err();
+        assert!(matches!(err, DprojError::ElementNotFound { .. }), "{err:?}");
+        assert_eq!(err.to_string(), "No <NoSuchElement> element found in PropertyGroup #0");
+    }
+
+    // ── Element-level conditions ─────────────────────────────────────────
+
+    #[test]
+    fn element_conditions_are_recorded() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let pg0 = &dproj.project.property_groups[0];
+        assert_eq!(
+            pg0.elements[4],
+            PropertyElement {
+                tag: "Config".into(),
+                condition: Some("'$(Config)'==''".into()),
+                value: "Debug".into(),
+            }
+        );
+        assert_eq!(pg0.elements.len(), 10);
+        assert_eq!(pg0.elements.iter().filter(|e| e.condition.is_some()).count(), 3);
+        assert_eq!(pg0.elements[1].tag, "ProjectVersion");
+        assert_eq!(pg0.elements[1].condition, None);
+        assert_eq!(pg0.project_properties.config, None);
+        assert_eq!(pg0.project_properties.project_version.as_deref(), Some("20.1"));
+        // Groups without element-level conditions keep no element list.
+        assert!(dproj.project.property_groups[1].elements.is_empty());
+    }
+
+    #[test]
+    fn repeated_conditional_elements_are_kept_apart() {
+        let source = r#"<Project>
+            <PropertyGroup>
+                <Config>Debug</Config>
+                <Foo Condition="'$(Platform)'=='Win32'">a</Foo>
+                <Foo Condition="'$(Platform)'=='Win64'">b</Foo>
+            </PropertyGroup>
+            <ItemGroup>
+                <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+            </ItemGroup>
+        </Project>"#;
+        let dproj = Dproj::parse(source).unwrap();
+        for (platform, expected) in [("Win32", "a"), ("Win64", "b"), ("Linux64", "")] {
+            let pg = dproj.active_property_group_for("Debug", platform).unwrap();
+            assert_eq!(pg.other.get("Foo").map_or("", String::as_str), expected, "{platform}");
+        }
+    }
+
+    #[test]
+    fn conditional_elements_see_earlier_elements_of_their_group() {
+        let source = r#"<Project>
+            <PropertyGroup>
+                <Config>Debug</Config>
+                <Flag>on</Flag>
+                <Foo Condition="'$(Flag)'=='on'">set</Foo>
+                <Bar Condition="'$(Foo)'=='set'">$(Foo)!</Bar>
+            </PropertyGroup>
+            <ItemGroup>
+                <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+            </ItemGroup>
+        </Project>"#;
+        for mode in [EvaluationMode::Immediate, EvaluationMode::Deferred] {
+            let dproj = DprojBuilder::new().evaluation_mode(mode).parse(source).unwrap();
+            let pg = dproj.active_property_group_for("Debug", "Win32").unwrap();
+            assert_eq!(pg.other.get("Foo").map(String::as_str), Some("set"), "{mode:?}");
+            assert_eq!(pg.other.get("Bar").map(String::as_str), Some("set!"), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn group_elements_are_evaluated_in_document_order() {
+        let source = r#"<Project>
+            <PropertyGroup>
+                <Config>Debug</Config>
+                <Foo Condition="'$(Platform)'=='Win32'">cond</Foo>
+                <Foo>plain</Foo>
+                <Bar Condition="'$(Bar)'==''">dflt</Bar>
+                <Baz>$(Bar)-x</Baz>
+            </PropertyGroup>
+            <ItemGroup>
+                <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+            </ItemGroup>
+        </Project>"#;
+        for mode in [EvaluationMode::Immediate, EvaluationMode::Deferred] {
+            let dproj = DprojBuilder::new().evaluation_mode(mode).parse(source).unwrap();
+            let pg = dproj.active_property_group_for("Debug", "Win32").unwrap();
+            assert_eq!(pg.other.get("Foo").map(String::as_str), Some("plain"), "{mode:?}");
+            assert_eq!(pg.other.get("Baz").map(String::as_str), Some("dflt-x"), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn element_condition_keeps_requested_config() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let pg = dproj.active_property_group_for("Release", "Win64").unwrap();
+        // `<Config Condition="'$(Config)'==''">Debug</Config>` must not apply
+        // because Config is already set to Release.
+        assert_ne!(pg.project_properties.config.as_deref(), Some("Debug"));
+        assert_ne!(pg.project_properties.platform.as_deref(), Some("Win32"));
+        // `$(ProjectName)` is not set externally, so the default applies.
+        assert_eq!(pg.project_properties.project_name.as_deref(), Some("Project1"));
+    }
+
+    #[test]
+    fn element_condition_default_unless_set() {
+        let source = r#"<Project>
+            <PropertyGroup>
+                <Config>Debug</Config>
+                <Platform>Win32</Platform>
+                <OutputRoot Condition="'$(OutputRoot)'==''">bin</OutputRoot>
+            </PropertyGroup>
+            <ItemGroup>
+                <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+            </ItemGroup>
+        </Project>"#;
+
+        let dproj = Dproj::parse(source).unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        assert_eq!(pg.other.get("OutputRoot").map(String::as_str), Some("bin"));
+
+        let dproj = DprojBuilder::new()
+            .env_var("OutputRoot", "custom")
+            .parse(source)
+            .unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        assert!(!pg.other.contains_key("OutputRoot"));
+    }
+
+    // ── Partial evaluation ───────────────────────────────────────────────
+
+    #[test]
+    fn possible_property_groups_for_any_win64_configuration() {
+        use crate::condition::Partial;
+
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let index_of = |condition: &str| {
+            dproj
+                .project
+                .property_groups
+                .iter()
+                .position(|pg| pg.condition.as_deref() == Some(condition))
+                .unwrap()
+        };
+        let base_win64 = index_of("'$(Base_Win64)'!=''");
+        let cfg_1 = index_of("'$(Cfg_1)'!=''");
+        let groups = dproj.possible_property_groups(None, Some("Win64")).unwrap();
+        let conditions: Vec<Option<&str>> = groups
+            .iter()
+            .map(|(i, _)| dproj.project.property_groups[*i].condition.as_deref())
+            .collect();
+
+        // Shared by every Win64 configuration.
+        assert_eq!(groups[0], (0, Partial::True));
+        assert!(groups.contains(&(base_win64, Partial::True)), "{conditions:?}");
+        // Depends on the configuration.
+        assert!(conditions.contains(&Some("'$(Cfg_1_Win64)'!=''")));
+        assert!(matches!(
+            groups.iter().find(|(i, _)| *i == cfg_1),
+            Some((_, Partial::Residual(_)))
+        ));
+        // Never applies to Win64.
+        assert!(!conditions.iter().flatten().any(|c| c.contains("Win32")), "{conditions:?}");
+
+        let release = dproj.possible_property_groups(Some("Release"), Some("Win64")).unwrap();
+        assert!(release.iter().all(|(_, outcome)| *outcome == Partial::True));
+        assert!(!release.iter().any(|(i, _)| *i == cfg_1));
+    }
+
+    // ── Condition parsing ────────────────────────────────────────────────
+
+    #[test]
+    fn broken_condition_names_property_group() {
+        let source = EXISTS_DPROJ.replace("Exists('$(BDS)", "Exists('$(BDS");
+        let dproj = Dproj::parse(source).unwrap();
+        let err = dproj.active_property_group().unwrap_err();
+        assert!(
+            matches!(&err, DprojError::Condition { file: None, source } if source.property_group == Some(1)),
+            "{err:?}"
+        );
+        assert!(err.to_string().contains("in PropertyGroup #1 at byte"), "unexpected message: {err}");
+    }
 
-/// Return the text content of the first child element with the given tag name.
-fn find_child_text(parent: &roxmltree::Node, tag: &str) -> Option<String> {
-    parent
-        .children()
-        .find(|c| c.is_element() && c.tag_name().name() == tag)
-        .and_then(|c| c.text())
-        .map(String::from)
-}
+    #[test]
+    fn conditions_are_parsed_once() {
+        let mut dproj = Dproj::from_file("example.dproj").unwrap();
+        let debug = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        let parsed = dproj.conditions.len();
+        assert!(parsed > 0);
+        dproj.active_property_group_for("Release", "Win64").unwrap();
+        dproj.clone().active_property_group_for("Debug", "Win64").unwrap();
+        assert_eq!(dproj.conditions.len(), parsed);
+
+        // Editing a condition takes effect: the cache is keyed by text.
+        let index = dproj
+            .project
+            .property_groups
+            .iter()
+            .position(|pg| pg.condition.as_deref() == Some("'$(Cfg_1)'!=''"))
+            .unwrap();
+        dproj.project.property_groups[index].condition = Some("'$(Cfg_1)'=='never'".into());
+        let edited = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        assert_eq!(dproj.conditions.len(), parsed + 1);
+        assert_ne!(
+            format!("{:?}", edited.dcc_options),
+            format!("{:?}", debug.dcc_options)
+        );
+    }
 
-// ═══════════════════════════════════════════════════════════════════════════════
-//  Tests
-// ═══════════════════════════════════════════════════════════════════════════════
+    // ── Evaluation modes ─────────────────────────────────────────────────
+
+    const DEFERRED_DPROJ: &str = r#"<Project>
+        <PropertyGroup>
+            <Config>Debug</Config>
+            <Platform>Win32</Platform>
+            <OutDir>$(OutRoot)\$(Platform)</OutDir>
+            <DCC_UnitSearchPath>$(LibRoot)\src;$(DCC_UnitSearchPath)</DCC_UnitSearchPath>
+            <SdkLib>$(BDSLIB)</SdkLib>
+        </PropertyGroup>
+        <PropertyGroup Condition="'$(Cfg_1)'!=''">
+            <DCC_UnitSearchPath>$(LibRoot)\ext;$(DCC_UnitSearchPath)</DCC_UnitSearchPath>
+            <DCC_Define>$(DCC_Define.Replace('X', 'Y'));DEBUG</DCC_Define>
+        </PropertyGroup>
+        <PropertyGroup>
+            <OutRoot>C:\out</OutRoot>
+            <LibRoot>C:\lib</LibRoot>
+        </PropertyGroup>
+        <ItemGroup>
+            <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+        </ItemGroup>
+    </Project>"#;
+
+    fn deferred_builder() -> DprojBuilder {
+        DprojBuilder::new()
+            .env_var("BDSLIB", r"$(BDS)\lib")
+            .env_var("BDS", r"C:\Studio")
+            .env_var("DCC_Define", "X")
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn immediate_mode_leaves_forward_references_empty() {
+        let pg = deferred_builder()
+            .parse(DEFERRED_DPROJ)
+            .unwrap()
+            .active_property_group()
+            .unwrap();
+        assert_eq!(pg.other["OutDir"], r"\Win32");
+        assert_eq!(pg.dcc_options.unit_search_path.as_deref(), Some(r"\ext;\src;"));
+        assert_eq!(pg.other["SdkLib"], r"$(BDS)\lib");
+    }
 
-    /// Smoke-test: every .dproj in the repo root must parse without error.
     #[test]
-    fn parse_all_dproj_files() {
-        let files = [
-            "example.dproj",
-        ];
-        for file in &files {
-            let result = Dproj::from_file(file);
-            assert!(result.is_ok(), "Failed to parse {file}: {}", result.unwrap_err());
-        }
+    fn deferred_mode_expands_recursively() {
+        let pg = deferred_builder()
+            .evaluation_mode(EvaluationMode::Deferred)
+            .parse(DEFERRED_DPROJ)
+            .unwrap()
+            .active_property_group()
+            .unwrap();
+        assert_eq!(pg.other["OutDir"], r"C:\out\Win32");
+        assert_eq!(
+            pg.dcc_options.unit_search_path.as_deref(),
+            Some(r"C:\lib\ext;C:\lib\src;")
+        );
+        assert_eq!(pg.other["SdkLib"], r"C:\Studio\lib");
+        assert_eq!(pg.dcc_options.define.as_deref(), Some("Y;DEBUG"));
     }
 
     #[test]
-    fn example_dproj_basic_properties() {
-        let dproj = Dproj::from_file("example.dproj").unwrap();
-        let pg0 = &dproj.project.property_groups[0];
-        assert_eq!(pg0.project_properties.project_version.as_deref(), Some("20.1"));
-        assert_eq!(pg0.project_properties.framework_type.as_deref(), Some("VCL"));
-        assert!(pg0.project_properties.project_guid.is_some());
+    fn deferred_mode_reports_cycles() {
+        let source = DEFERRED_DPROJ.replace(
+            r"<OutRoot>C:\out</OutRoot>",
+            r"<OutRoot>$(OutDir)\..</OutRoot>",
+        );
+        let err = DprojBuilder::new()
+            .evaluation_mode(EvaluationMode::Deferred)
+            .parse(source)
+            .unwrap()
+            .active_property_group()
+            .unwrap_err();
+        assert!(matches!(err, DprojError::ReferenceCycle(_)));
+        assert_eq!(err.to_string(), "Reference cycle: OutDir -> OutRoot -> OutDir");
     }
 
     #[test]
-    fn set_property_value_round_trip() {
-        let source = std::fs::read_to_string("example.dproj").unwrap();
-        let mut dproj = Dproj::parse(source).unwrap();
+    fn deferred_mode_matches_immediate_on_example() {
+        for (config, platform) in [("Debug", "Win32"), ("Release", "Win64")] {
+            let immediate = Dproj::from_file("example.dproj")
+                .unwrap()
+                .active_property_group_for(config, platform)
+                .unwrap();
+            let deferred = DprojBuilder::new()
+                .evaluation_mode(EvaluationMode::Deferred)
+                .from_file("example.dproj")
+                .unwrap()
+                .active_property_group_for(config, platform)
+                .unwrap();
+            assert_eq!(deferred.properties(), immediate.properties());
+        }
+    }
 
-        let old = dproj.project.property_groups[0]
-            .project_properties
-            .project_version
-            .clone();
-        assert_eq!(old.as_deref(), Some("20.1"));
+    // ── Unresolved variables ─────────────────────────────────────────────
 
-        dproj.set_property_value(0, "ProjectVersion", "99.9").unwrap();
+    #[test]
+    fn reports_unresolved_references() {
+        let unresolved = |mode| {
+            deferred_builder()
+                .evaluation_mode(mode)
+                .parse(DEFERRED_DPROJ)
+                .unwrap()
+                .unresolved_references_for("Debug", "Win32")
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.variable, r.property, r.group))
+                .collect::<Vec<_>>()
+        };
+        let s = String::from;
         assert_eq!(
-            dproj.project.property_groups[0]
-                .project_properties
-                .project_version
-                .as_deref(),
-            Some("99.9")
+            unresolved(EvaluationMode::Immediate),
+            [
+                (s("LibRoot"), s("DCC_UnitSearchPath"), 0),
+                (s("OutRoot"), s("OutDir"), 0),
+                (s("LibRoot"), s("DCC_UnitSearchPath"), 1),
+            ]
         );
+        assert!(unresolved(EvaluationMode::Deferred).is_empty());
+    }
 
-        // The raw source should reflect the change too.
-        assert!(dproj.source().contains("<ProjectVersion>99.9</ProjectVersion>"));
+    #[test]
+    fn strict_variables_fail_on_unknown_references() {
+        let source = r#"<Project>
+            <PropertyGroup>
+                <Config>Debug</Config>
+                <Platform>Win32</Platform>
+                <DCC_ExeOutput>$(BDSCOMMONDIR)\Bpl\$(Platform)</DCC_ExeOutput>
+                <DCC_Define>DEBUG;$(DCC_Define)</DCC_Define>
+            </PropertyGroup>
+            <ItemGroup>
+                <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+            </ItemGroup>
+        </Project>"#;
+        let err = DprojBuilder::new()
+            .strict_variables(true)
+            .parse(source)
+            .unwrap()
+            .active_property_group()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unresolved variables: $(BDSCOMMONDIR) in DCC_ExeOutput (PropertyGroup #0)"
+        );
+
+        let pg = DprojBuilder::new()
+            .strict_variables(true)
+            .env_var("BDSCOMMONDIR", r"C:\Common")
+            .parse(source)
+            .unwrap()
+            .active_property_group()
+            .unwrap();
+        assert_eq!(pg.dcc_options.exe_output.as_deref(), Some(r"C:\Common\Bpl\Win32"));
     }
 
-    // ── Active property group resolution ─────────────────────────────────
+    // ── Referenced variables ─────────────────────────────────────────────
 
     #[test]
-    fn active_pg_all_files() {
-        // Every dproj must resolve an active PG for its default config/platform.
-        let files = [
-            "example.dproj",
-        ];
-        for file in files {
-            let dproj = Dproj::from_file(file).unwrap();
-            let result = dproj.active_property_group();
-            assert!(
-                result.is_ok(),
-                "active_property_group failed for {file}: {}",
-                result.unwrap_err()
-            );
-        }
+    fn property_group_referenced_variables() {
+        let dproj = Dproj::parse(
+            r#"<Project>
+                <PropertyGroup Condition="'$(Config)'=='Debug' or '$(Cfg_1)'!=''">
+                    <DCC_Define>DEBUG;$(DCC_Define)</DCC_Define>
+                    <DCC_ExeOutput Condition="'$(OUTDIR)'!=''">$(OutDir)\$(Platform)</DCC_ExeOutput>
+                </PropertyGroup>
+            </Project>"#,
+        )
+        .unwrap();
+        let pg = &dproj.project.property_groups[0];
+        assert_eq!(pg.properties()["dcc_define"], "DEBUG;$(DCC_Define)");
+        assert_eq!(
+            pg.referenced_variables(),
+            ["Config", "Cfg_1", "OUTDIR", "DCC_Define", "Platform"]
+        );
     }
 
+    // ── Exists() evaluation ──────────────────────────────────────────────
+
+    const EXISTS_DPROJ: &str = r#"<Project>
+        <PropertyGroup>
+            <Config>Debug</Config>
+            <Platform>Win32</Platform>
+        </PropertyGroup>
+        <PropertyGroup Condition="Exists('$(BDS)\Bin\CodeGear.Delphi.Targets')">
+            <SdkFound>true</SdkFound>
+        </PropertyGroup>
+        <ItemGroup>
+            <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+        </ItemGroup>
+    </Project>"#;
+
     #[test]
-    fn active_pg_example_debug_win32() {
-        let dproj = Dproj::from_file("example.dproj").unwrap();
-        // example.dproj defaults to Debug/Win32.
-        let pg = dproj.active_property_group().unwrap();
+    fn exists_consults_memory_file_system() {
+        use crate::filesystem::MemoryFileSystem;
 
-        // Core project properties should be populated from the unconditional PG.
-        assert_eq!(pg.project_properties.project_version.as_deref(), Some("20.1"));
-        assert_eq!(pg.project_properties.framework_type.as_deref(), Some("VCL"));
+        let fs = MemoryFileSystem::new().with_file(r"C:\Studio\bin\CodeGear.Delphi.Targets");
+        let dproj = DprojBuilder::new()
+            .env_var("BDS", r"C:\Studio")
+            .file_system(fs.clone())
+            .parse(EXISTS_DPROJ)
+            .unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        assert_eq!(pg.other.get("SdkFound").map(String::as_str), Some("true"));
 
-        // Debug-specific DCC options should be merged in.
-        // In example.dproj, Debug has DCC_Optimize = "false" and
-        // DCC_DebugInformation = "2".
-        assert!(
-            pg.dcc_options.optimize.is_some() || pg.dcc_options.debug_information.is_some(),
-            "Expected some DCC options from the Debug config PG"
-        );
+        let dproj = DprojBuilder::new()
+            .env_var("BDS", r"C:\Elsewhere")
+            .file_system(fs)
+            .parse(EXISTS_DPROJ)
+            .unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        assert!(!pg.other.contains_key("SdkFound"));
     }
 
     #[test]
-    fn active_pg_example_release_win32() {
-        let dproj = Dproj::from_file("example.dproj").unwrap();
-        let pg = dproj.active_property_group_for("Release", "Win32").unwrap();
+    fn exists_checks_the_disk_only_for_loaded_files() {
+        // A parsed source has no directory, so paths are assumed to exist.
+        let dproj = DprojBuilder::new()
+            .env_var("BDS", r"Z:\NoSuchStudio")
+            .parse(EXISTS_DPROJ)
+            .unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        assert_eq!(pg.other.get("SdkFound").map(String::as_str), Some("true"));
+
+        let path = std::env::temp_dir().join(format!("dproj-rs-exists-{}.dproj", std::process::id()));
+        std::fs::write(&path, EXISTS_DPROJ).unwrap();
+        let loaded = DprojBuilder::new().env_var("BDS", r"Z:\NoSuchStudio").from_file(&path);
+        let legacy = DprojBuilder::new()
+            .env_var("BDS", r"Z:\NoSuchStudio")
+            .file_system(crate::filesystem::AssumeExists)
+            .from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let pg = loaded.unwrap().active_property_group().unwrap();
+        assert!(!pg.other.contains_key("SdkFound"));
+        let pg = legacy.unwrap().active_property_group().unwrap();
+        assert_eq!(pg.other.get("SdkFound").map(String::as_str), Some("true"));
 
-        assert_eq!(pg.project_properties.project_version.as_deref(), Some("20.1"));
-        // Release should have its own DCC options (optimization, etc.)
-        assert!(pg.dcc_options.optimize.is_some() || pg.dcc_options.debug_information.is_some());
+        let dproj = DprojBuilder::new()
+            .env_var("BDS", r"Z:\NoSuchStudio")
+            .file_system(crate::filesystem::DiskFileSystem)
+            .parse(EXISTS_DPROJ)
+            .unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        assert!(!pg.other.contains_key("SdkFound"));
     }
 
+    // ── Property functions ───────────────────────────────────────────────
+
     #[test]
-    fn active_pg_nonexistent_config() {
-        let dproj = Dproj::from_file("example.dproj").unwrap();
-        let result = dproj.active_property_group_for("DoesNotExist", "Win32");
-        assert!(result.is_err());
+    fn is_os_platform_follows_the_builder_setting() {
+        let source = r#"<Project>
+            <PropertyGroup>
+                <Config>Debug</Config>
+                <Platform>Win32</Platform>
+                <OnLinux>$([MSBuild]::IsOSPlatform('Linux'))</OnLinux>
+                <OnWindows>$([MSBuild]::IsOSPlatform('Windows'))</OnWindows>
+            </PropertyGroup>
+            <PropertyGroup Condition="$([MSBuild]::IsOSPlatform('Linux'))">
+                <Shell>sh</Shell>
+            </PropertyGroup>
+            <ItemGroup>
+                <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+            </ItemGroup>
+        </Project>"#;
+        let dproj = Dproj::parse(source).unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        assert_eq!(pg.other["OnLinux"], "False");
+        assert_eq!(pg.other["OnWindows"], "True");
+        assert!(!pg.other.contains_key("Shell"));
+        // The OS platform is context, not a property.
+        let vars = dproj.resolve_build_variables("Debug", "Win32").unwrap();
+        assert!(!vars.contains_key("MSBuildOSPlatform"));
+
+        for mode in [EvaluationMode::Immediate, EvaluationMode::Deferred] {
+            let dproj = DprojBuilder::new().os_platform("Linux").evaluation_mode(mode).parse(source).unwrap();
+            let pg = dproj.active_property_group().unwrap();
+            assert_eq!(pg.other["OnLinux"], "True", "{mode:?}");
+            assert_eq!(pg.other["OnWindows"], "False", "{mode:?}");
+            assert_eq!(pg.other.get("Shell").map(String::as_str), Some("sh"), "{mode:?}");
+        }
     }
 
     // ── Listing helpers ──────────────────────────────────────────────────
@@ -2302,6 +5461,28 @@ mod tests {
         // example.dproj should have at least Win32
         let names: Vec<&str> = platforms.iter().map(|(n, _)| *n).collect();
         assert!(names.contains(&"Win32"), "expected Win32 in {names:?}");
+        assert_eq!(dproj.target_platforms(), [TargetPlatform::Win32, TargetPlatform::Win64]);
+    }
+
+    #[test]
+    fn platforms_fall_back_to_targeted_platforms() {
+        let dproj = Dproj::parse(
+            "<Project><PropertyGroup>\
+             <Platform Condition=\"'$(Platform)'==''\">Win32</Platform>\
+             <TargetedPlatforms>36993</TargetedPlatforms>\
+             </PropertyGroup></Project>",
+        )
+        .unwrap();
+        assert_eq!(
+            dproj.platforms(),
+            [("Win32", true), ("Linux64", true), ("OSX64", true), ("Android64", true)]
+        );
+
+        let dproj = Dproj::parse(
+            "<Project><PropertyGroup><Platform>Win64</Platform></PropertyGroup></Project>",
+        )
+        .unwrap();
+        assert_eq!(dproj.platforms(), [("Win64", true)]);
     }
 
     #[test]
@@ -2309,6 +5490,23 @@ mod tests {
         let dproj = Dproj::from_file("example.dproj").unwrap();
         assert_eq!(dproj.active_configuration().unwrap(), "Debug");
         assert_eq!(dproj.active_platform().unwrap(), "Win32");
+        assert_eq!(dproj.active_target_platform().unwrap(), TargetPlatform::Win32);
+    }
+
+    #[test]
+    fn typed_platforms() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let bp = dproj.project.project_extensions.as_ref().unwrap().borland_project.as_ref().unwrap();
+        let win64 = bp.platforms.iter().find(|p| p.value == "Win64").unwrap();
+        assert_eq!(win64.target_platform(), Ok(TargetPlatform::Win64));
+        let unknown = Platform { value: "Win46".into(), active: true };
+        assert_eq!(unknown.target_platform().unwrap_err().0, "Win46");
+
+        let mut dproj = Dproj::parse(dproj.source().to_string()).unwrap();
+        dproj.set_platform("Win46").unwrap();
+        let err = dproj.active_target_platform().unwrap_err();
+        assert!(matches!(err, DprojError::UnknownPlatform(_)), "{err:?}");
+        assert_eq!(err.to_string(), "Unknown platform 'Win46'");
     }
 
     #[test]
@@ -2431,13 +5629,28 @@ mod tests {
 
     #[test]
     fn expand_msbuild_vars_works() {
-        let mut vars = HashMap::new();
-        vars.insert("Config".into(), "Debug".into());
-        vars.insert("Platform".into(), "Win32".into());
+        let mut vars = VarMap::new();
+        vars.insert("Config", "Debug");
+        vars.insert("Platform", "Win32");
         assert_eq!(
-            super::expand_msbuild_vars(".\\$(Platform)\\$(Config)\\out", &vars),
+            super::expand_msbuild_vars(".\\$(Platform)\\$(Config)\\out", &vars, &Default::default()),
             ".\\Win32\\Debug\\out"
         );
+        assert_eq!(
+            super::expand_msbuild_vars("$(platform)-$(CONFIG)", &vars, &Default::default()),
+            "Win32-Debug"
+        );
+    }
+
+    #[test]
+    fn expand_msbuild_vars_evaluates_property_functions() {
+        let mut vars = VarMap::new();
+        vars.insert("ProductVersion", "23.0");
+        vars.insert("Platform", "Win64");
+        assert_eq!(
+            super::expand_msbuild_vars(".\\$(Platform.ToLower())\\v$(ProductVersion.Substring(0, 2))", &vars, &Default::default()),
+            ".\\win64\\v23"
+        );
     }
 
     // ── List-property accumulation ───────────────────────────────────────
@@ -2563,7 +5776,7 @@ mod tests {
 
     #[test]
     fn builder_env_map() {
-        let mut env = HashMap::new();
+        let mut env: HashMap<String, String> = HashMap::new();
         env.insert("BDS".into(), r"D:\Studio".into());
         env.insert("BDSCOMMONDIR".into(), r"D:\Common".into());
         let dproj = DprojBuilder::new()
@@ -2578,6 +5791,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn builder_env_is_case_insensitive() {
+        let dproj = DprojBuilder::new()
+            .env_var("bds", r"C:\MyBDS")
+            .from_file("example.dproj")
+            .unwrap();
+        let pg = dproj.active_property_group().unwrap();
+        let icon = pg.project_properties.icon_main_icon.as_deref().unwrap();
+        assert!(
+            icon.contains(r"C:\MyBDS"),
+            "expected C:\\MyBDS in icon: {icon}"
+        );
+    }
+
     #[test]
     fn builder_parse_string() {
         let source = std::fs::read_to_string("example.dproj").unwrap();
@@ -2593,6 +5820,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reserved_properties_from_project_path() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let dir = dproj.directory().unwrap().to_string_lossy().into_owned();
+        let vars = dproj.resolve_build_variables("Debug", "Win32").unwrap();
+        assert_eq!(vars["MSBuildProjectName"], "Project1");
+        assert_eq!(vars["MSBuildProjectFile"], "example.dproj");
+        assert_eq!(vars["MSBuildProjectExtension"], ".dproj");
+        assert_eq!(vars["MSBuildProjectDirectory"], dir);
+        assert_eq!(vars["MSBuildThisFileName"], "example");
+        assert_eq!(
+            vars["MSBuildThisFileDirectory"],
+            format!("{dir}{}", std::path::MAIN_SEPARATOR)
+        );
+        assert_eq!(
+            vars["MSBuildProjectFullPath"],
+            dproj.path().unwrap().to_string_lossy()
+        );
+        assert_eq!(vars["PROJECTDIR"], dir);
+        assert!(!vars.contains_key("OUTPUTDIR"));
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(vars["MSBuildStartupDirectory"], cwd.to_string_lossy());
+
+        // Without a path only the name is known.
+        let source = std::fs::read_to_string("example.dproj").unwrap();
+        let vars = Dproj::parse(source.clone())
+            .unwrap()
+            .resolve_build_variables("Debug", "Win32")
+            .unwrap();
+        assert_eq!(vars["MSBuildProjectName"], "Project1");
+        assert!(!vars.contains_key("MSBuildProjectDirectory"));
+        assert!(!vars.contains_key("MSBuildStartupDirectory"));
+
+        let vars = DprojBuilder::new()
+            .startup_directory(r"C:\Build")
+            .parse(source)
+            .unwrap()
+            .resolve_build_variables("Debug", "Win32")
+            .unwrap();
+        assert_eq!(vars["MSBuildStartupDirectory"], r"C:\Build");
+    }
+
+    #[test]
+    fn reserved_properties_expand_in_values() {
+        let mut dproj = Dproj::from_file("example.dproj").unwrap();
+        let dir = dproj.directory().unwrap().to_string_lossy().into_owned();
+        dproj.insert_property(0, "OutRoot", r"$(MSBuildProjectDirectory)\out").unwrap();
+        let pg = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        assert_eq!(pg.other["OutRoot"], format!(r"{dir}\out"));
+
+        let mut dproj = DprojBuilder::new()
+            .evaluation_mode(EvaluationMode::Deferred)
+            .from_file("example.dproj")
+            .unwrap();
+        dproj.insert_property(0, "Target", "$(OUTPUTDIR)$(OUTPUTNAME).exe").unwrap();
+        let pg = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        let target = &pg.other["Target"];
+        assert!(target.starts_with(&dir), "{target}");
+        assert!(target.ends_with(r"Win32\Debug\DDD\Project1.exe"), "{target}");
+    }
+
     #[test]
     fn msbuild_project_name_expanded() {
         let dproj = Dproj::from_file("example.dproj").unwrap();
@@ -2610,4 +5898,251 @@ mod tests {
         );
     }
 
+    const IMPORTING_DPROJ: &str = r#"<Project>
+        <PropertyGroup>
+            <Config Condition="'$(Config)'==''">Debug</Config>
+            <Platform Condition="'$(Platform)'==''">Win32</Platform>
+            <Tools>project</Tools>
+        </PropertyGroup>
+        <Import Project="$(BDS)\Bin\Common.Targets"/>
+        <PropertyGroup>
+            <Linker>$(Tools)\link.exe</Linker>
+        </PropertyGroup>
+        <ItemGroup>
+            <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+        </ItemGroup>
+        <Import Project="$(BDS)\Bin\Missing.Targets" Condition="Exists('$(BDS)\Bin\Missing.Targets')"/>
+    </Project>"#;
+
+    const COMMON_TARGETS: &str = r#"<Project>
+        <PropertyGroup>
+            <Tools>$(MSBuildThisFileDirectory)tools</Tools>
+            <Imported>$(MSBuildThisFile)</Imported>
+        </PropertyGroup>
+        <PropertyGroup Condition="'$(Platform)'=='Win64'">
+            <Tools>$(Tools)64</Tools>
+        </PropertyGroup>
+        <Import Project="Platform.props"/>
+        <Import Project="Common.Targets"/>
+    </Project>"#;
+
+    fn importing_builder() -> DprojBuilder {
+        use crate::filesystem::MemoryFileSystem;
+
+        let fs = MemoryFileSystem::new()
+            .with_contents(r"C:\Studio\Bin\Common.Targets", COMMON_TARGETS)
+            .with_contents(
+                r"C:\Studio\Bin\Platform.props",
+                "<Project><PropertyGroup><Props>$(MSBuildThisFileName)</Props></PropertyGroup></Project>",
+            );
+        DprojBuilder::new()
+            .env_var("BDS", r"C:\Studio")
+            .file_system(fs)
+            .resolve_imports(true)
+    }
+
+    #[test]
+    fn imports_are_ignored_by_default() {
+        let pg = Dproj::parse(IMPORTING_DPROJ).unwrap().active_property_group().unwrap();
+        assert_eq!(pg.other["Linker"], r"project\link.exe");
+        assert!(!pg.other.contains_key("Imported"));
+    }
+
+    #[test]
+    fn imported_groups_merge_in_document_order() {
+        let dproj = importing_builder().parse(IMPORTING_DPROJ).unwrap();
+        assert_eq!(dproj.project.imports[0].position, 1);
+        assert_eq!(dproj.project.imports[1].position, 2);
+
+        let pg = dproj.active_property_group().unwrap();
+        assert_eq!(pg.other["Tools"], r"C:\Studio\Bin\tools");
+        assert_eq!(pg.other["Linker"], r"C:\Studio\Bin\tools\link.exe");
+        assert_eq!(pg.other["Imported"], "Common.Targets");
+        // Relative to the importing file; the repeated import is skipped.
+        assert_eq!(pg.other["Props"], "Platform");
+
+        let win64 = dproj.active_property_group_for("Debug", "Win64").unwrap();
+        assert_eq!(win64.other["Linker"], r"C:\Studio\Bin\tools64\link.exe");
+    }
+
+    #[test]
+    fn deferred_mode_follows_imports() {
+        let pg = importing_builder()
+            .evaluation_mode(EvaluationMode::Deferred)
+            .parse(IMPORTING_DPROJ)
+            .unwrap()
+            .active_property_group_for("Debug", "Win64")
+            .unwrap();
+        assert_eq!(pg.other["Linker"], r"C:\Studio\Bin\tools64\link.exe");
+        assert_eq!(pg.other["Props"], "Platform");
+    }
+
+    #[test]
+    fn imported_assignments_record_their_file() {
+        let dproj = importing_builder().parse(IMPORTING_DPROJ).unwrap();
+        let provenance = dproj.property_provenance().unwrap();
+        let tools = provenance.get("Tools").unwrap();
+        assert_eq!(tools.origin().file.as_deref(), Some(std::path::Path::new(r"C:\Studio\Bin\Common.Targets")));
+        assert_eq!(tools.origin().group, 0);
+        assert_eq!(tools.overridden().next().unwrap().file, None);
+    }
+
+    #[test]
+    fn example_project_imports_are_guarded() {
+        let plain = Dproj::from_file("example.dproj").unwrap();
+        let resolving = DprojBuilder::new().resolve_imports(true).from_file("example.dproj").unwrap();
+        assert_eq!(
+            resolving.active_property_group().unwrap().properties(),
+            plain.active_property_group().unwrap().properties()
+        );
+    }
+
+    #[test]
+    fn property_matrix_matches_individual_evaluation() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let matrix = dproj.property_matrix(false);
+        let platforms: Vec<&str> = dproj.platforms().into_iter().map(|(p, _)| p).collect();
+        assert_eq!(matrix.len(), 2 * platforms.len());
+        assert_eq!((matrix[0].configuration.as_str(), matrix[0].platform.as_str()), ("Debug", platforms[0]));
+        for entry in &matrix {
+            let expected = dproj.active_property_group_for(&entry.configuration, &entry.platform).unwrap();
+            let actual = entry.property_group.as_ref().unwrap();
+            assert_eq!(actual.properties(), expected.properties(), "{} {}", entry.configuration, entry.platform);
+        }
+
+        let all = dproj.property_matrix(true);
+        assert_eq!(all.len(), 3 * platforms.len());
+        assert_eq!(all[0].configuration, "Base");
+
+        let strict = DprojBuilder::new().strict_variables(true).from_file("example.dproj").unwrap();
+        let entry = &strict.property_matrix(false)[0];
+        assert!(matches!(entry.property_group, Err(DprojError::UnresolvedVariables(_))));
+    }
+
+    #[test]
+    fn property_matrix_reads_each_import_once() {
+        use crate::filesystem::MemoryFileSystem;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Debug)]
+        struct Counting(MemoryFileSystem, Arc<AtomicUsize>);
+        impl FileSystem for Counting {
+            fn exists(&self, path: &std::path::Path) -> bool {
+                self.0.exists(path)
+            }
+            fn read_to_string(&self, path: &std::path::Path) -> std::io::Result<String> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.read_to_string(path)
+            }
+        }
+
+        let source = IMPORTING_DPROJ.replace(
+            "</ItemGroup>",
+            r#"<BuildConfiguration Include="Release"><Key>Cfg_2</Key></BuildConfiguration>
+            </ItemGroup>
+            <ProjectExtensions><BorlandProject><Platforms>
+                <Platform value="Win32">True</Platform>
+                <Platform value="Win64">True</Platform>
+            </Platforms></BorlandProject></ProjectExtensions>"#,
+        );
+        let reads = Arc::new(AtomicUsize::new(0));
+        let fs = MemoryFileSystem::new()
+            .with_contents(r"C:\Studio\Bin\Common.Targets", COMMON_TARGETS)
+            .with_contents(r"C:\Studio\Bin\Platform.props", "<Project/>");
+        let dproj = DprojBuilder::new()
+            .env_var("BDS", r"C:\Studio")
+            .file_system(Counting(fs, Arc::clone(&reads)))
+            .resolve_imports(true)
+            .parse(source)
+            .unwrap();
+
+        let matrix = dproj.property_matrix(false);
+        assert_eq!(matrix.len(), 4);
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+        let win64 = matrix.iter().find(|e| e.configuration == "Release" && e.platform == "Win64").unwrap();
+        assert_eq!(win64.property_group.as_ref().unwrap().other["Linker"], r"C:\Studio\Bin\tools64\link.exe");
+    }
+
+    #[test]
+    fn missing_import_is_an_error() {
+        let source = IMPORTING_DPROJ.replace("Common.Targets", "Gone.Targets");
+        let err = importing_builder()
+            .parse(source)
+            .unwrap()
+            .active_property_group()
+            .unwrap_err();
+        let DprojError::Import { project, source } = &err else { panic!("{err:?}") };
+        assert_eq!(project, r"$(BDS)\Bin\Gone.Targets");
+        assert!(matches!(**source, DprojError::Io { path: Some(_), .. }), "{source:?}");
+        assert!(err.to_string().starts_with(r#"Import of "$(BDS)\Bin\Gone.Targets""#), "{err}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn project_model_round_trips_through_json() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let json = serde_json::to_value(&dproj.project).unwrap();
+
+        let pg0 = &json["PropertyGroup"][0];
+        assert_eq!(pg0["ProjectVersion"], "20.1");
+        assert_eq!(pg0["FrameworkType"], "VCL");
+        assert!(pg0.get("DCC_Define").is_none(), "unset values are omitted");
+        let debug = json["PropertyGroup"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|pg| pg["Condition"] == "'$(Cfg_1)'!=''")
+            .unwrap();
+        assert_eq!(debug["DCC_Define"], "DEBUG;$(DCC_Define)");
+        assert_eq!(json["ItemGroup"][0]["DCCReference"][0]["Include"], "Unit1.pas");
+        assert_eq!(json["Import"][0]["Project"], r"$(BDS)\Bin\CodeGear.Delphi.Targets");
+        let base = json["PropertyGroup"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|pg| pg["Condition"] == "'$(Base)'!=''")
+            .unwrap();
+        assert!(base.get("Other").is_none() && base.get("WarningDirectives").is_none());
+        assert_eq!(base["DCC_UNSAFE_TYPE"], "error");
+
+        let text = serde_json::to_string(&dproj.project).unwrap();
+        let back: DprojProject = serde_json::from_str(&text).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), text);
+        assert_eq!(back.property_groups.len(), dproj.project.property_groups.len());
+        let warnings = back
+            .property_groups
+            .iter()
+            .find(|pg| !pg.dcc_options.warning_directives.is_empty())
+            .unwrap();
+        assert_eq!(warnings.dcc_options.warning_directives["DCC_UNSAFE_TYPE"], "error");
+        for (pg, original) in back.property_groups.iter().zip(&dproj.project.property_groups) {
+            assert_eq!(pg.other, original.other);
+            assert_eq!(pg.dcc_options.warning_directives, original.dcc_options.warning_directives);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn effective_property_group_serializes_by_tag() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let pg = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        let json = serde_json::to_value(&pg).unwrap();
+        assert!(json["DCC_Define"].as_str().unwrap().starts_with("DEBUG;"));
+        assert_eq!(json["MainSource"], "Project1.dpr");
+
+        let back: PropertyGroup = serde_json::from_value(json).unwrap();
+        assert_eq!(back.properties(), pg.properties());
+
+        let dproj = Dproj::parse(
+            "<Project><PropertyGroup><Custom>1</Custom><DCC_Define>A</DCC_Define>\
+             <DCC_UNSAFE_CAST>false</DCC_UNSAFE_CAST></PropertyGroup></Project>",
+        )
+        .unwrap();
+        let json = serde_json::to_value(&dproj.project.property_groups[0]).unwrap();
+        assert_eq!(json, serde_json::json!({"Custom": "1", "DCC_Define": "A", "DCC_UNSAFE_CAST": "false"}));
+        let back: PropertyGroup = serde_json::from_value(json).unwrap();
+        assert_eq!(back.other.keys().collect::<Vec<_>>(), ["Custom"]);
+        assert_eq!(back.dcc_options.warning_directives.keys().collect::<Vec<_>>(), ["DCC_UNSAFE_CAST"]);
+        assert_eq!(back.dcc_options.define.as_deref(), Some("A"));
+    }
 }
@@ -0,0 +1,237 @@
+//! Pluggable filesystem access for MSBuild `Exists(…)` checks and
+//! `<Import>`ed files.
+//!
+//! Project evaluation never touches the disk directly; it asks a
+//! [`FileSystem`] implementation instead.  Three implementations ship with
+//! the crate:
+//!
+//! - [`DiskFileSystem`] — queries the real filesystem.
+//! - [`MemoryFileSystem`] — an in-memory tree of files, for tests and for
+//!   simulating an SDK layout that is not installed locally.
+//! - [`AssumeExists`] — every path exists (the behaviour of `dproj-rs`
+//!   0.2 and earlier).
+//!
+//! Paths in `.dproj` files are Windows paths.  On other platforms
+//! [`DiskFileSystem`] translates `\` separators to `/` before querying the
+//! disk, and [`MemoryFileSystem`] compares paths case-insensitively with
+//! either separator.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Filesystem queries needed while evaluating a project.
+pub trait FileSystem: std::fmt::Debug + Send + Sync {
+    /// Whether a file or directory exists at `path`.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Read the file at `path`, e.g. an `<Import>`ed `.targets` file.
+    ///
+    /// The default implementation reports every file as unreadable.
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("cannot read {}", path.display()),
+        ))
+    }
+}
+
+/// Queries the real filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskFileSystem;
+
+impl FileSystem for DiskFileSystem {
+    fn exists(&self, path: &Path) -> bool {
+        native(path).exists()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(native(path))
+    }
+}
+
+/// `path` with `\` separators translated to `/` on non-Windows platforms.
+fn native(path: &Path) -> std::borrow::Cow<'_, Path> {
+    if cfg!(windows) {
+        path.into()
+    } else {
+        PathBuf::from(path.to_string_lossy().replace('\\', "/")).into()
+    }
+}
+
+/// Treats every path as existing.
+///
+/// This reproduces the legacy behaviour where `Exists(…)` always evaluated
+/// to `true`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AssumeExists;
+
+impl FileSystem for AssumeExists {
+    fn exists(&self, _path: &Path) -> bool {
+        true
+    }
+}
+
+/// An in-memory tree of files.
+///
+/// A path exists if it was added explicitly or if it is a parent directory
+/// of an added path.  Comparison ignores ASCII case and treats `\` and `/`
+/// as equivalent separators, matching Windows semantics.  Files added with
+/// [`with_file`](Self::with_file) are empty; use
+/// [`with_contents`](Self::with_contents) for files that are read, such as
+/// imported `.targets` files.
+///
+/// # Example
+/// ```
+/// use dproj_rs::filesystem::{FileSystem, MemoryFileSystem};
+/// use std::path::Path;
+///
+/// let fs = MemoryFileSystem::new().with_file(r"C:\Studio\Bin\CodeGear.Delphi.Targets");
+/// assert!(fs.exists(Path::new("c:/studio/bin/codegear.delphi.targets")));
+/// assert!(fs.exists(Path::new(r"C:\Studio\Bin")));
+/// assert!(!fs.exists(Path::new(r"C:\Studio\lib")));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFileSystem {
+    /// Normalised path → contents.
+    files: HashMap<String, String>,
+}
+
+impl MemoryFileSystem {
+    /// Create an empty in-memory filesystem.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file path and return `self` (builder style).
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.add_file(path);
+        self
+    }
+
+    /// Add a file path.
+    pub fn add_file(&mut self, path: impl AsRef<Path>) {
+        self.add_contents(path, "");
+    }
+
+    /// Add a file with the given contents and return `self` (builder style).
+    pub fn with_contents(mut self, path: impl AsRef<Path>, contents: impl Into<String>) -> Self {
+        self.add_contents(path, contents);
+        self
+    }
+
+    /// Add a file with the given contents, replacing any earlier contents.
+    pub fn add_contents(&mut self, path: impl AsRef<Path>, contents: impl Into<String>) {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn exists(&self, path: &Path) -> bool {
+        let wanted = normalize(path);
+        if wanted.is_empty() {
+            return false;
+        }
+        self.files.keys().any(|f| {
+            f == &wanted
+                || (f.len() > wanted.len()
+                    && f.starts_with(wanted.as_str())
+                    && f.as_bytes()[wanted.len()] == b'/')
+        })
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.files.get(&normalize(path)).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{}: no such file", path.display()))
+        })
+    }
+}
+
+/// Normalise a path for case-insensitive, separator-agnostic comparison.
+pub(crate) fn normalize(path: &Path) -> String {
+    let mut out = String::new();
+    for c in path.to_string_lossy().chars() {
+        let c = if c == '\\' { '/' } else { c.to_ascii_lowercase() };
+        // Collapse repeated separators.
+        if c == '/' && out.ends_with('/') {
+            continue;
+        }
+        out.push(c);
+    }
+    while out.len() > 1 && out.ends_with('/') {
+        out.pop();
+    }
+    out
+}
+
+/// Whether `path` is absolute in either Windows (`C:\…`, `\\server`, `\…`)
+/// or Unix (`/…`) notation.
+pub(crate) fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || path.starts_with('\\')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_fs_ignores_case_and_separators() {
+        let fs = MemoryFileSystem::new().with_file(r"C:\Studio\23.0\Bin\CodeGear.Delphi.Targets");
+        assert!(fs.exists(Path::new(r"c:\studio\23.0\bin\codegear.delphi.targets")));
+        assert!(fs.exists(Path::new("C:/Studio/23.0/Bin/CodeGear.Delphi.Targets")));
+        assert!(fs.exists(Path::new(r"C:\Studio\\23.0\Bin\")));
+        assert!(!fs.exists(Path::new(r"C:\Studio\23.0\Bin\CodeGear.Cpp.Targets")));
+    }
+
+    #[test]
+    fn memory_fs_parent_directories_exist() {
+        let fs = MemoryFileSystem::new().with_file("/sdk/bin/tool");
+        assert!(fs.exists(Path::new("/sdk")));
+        assert!(fs.exists(Path::new("/sdk/bin")));
+        // A sibling that merely shares a prefix is not a parent.
+        assert!(!fs.exists(Path::new("/sdk/bi")));
+        assert!(!fs.exists(Path::new("")));
+    }
+
+    #[test]
+    fn memory_fs_reads_contents() {
+        let fs = MemoryFileSystem::new()
+            .with_contents(r"C:\Studio\Bin\CodeGear.Common.Targets", "<Project/>")
+            .with_file(r"C:\Studio\Bin\empty.txt");
+        let read = fs.read_to_string(Path::new("c:/studio/bin/codegear.common.targets"));
+        assert_eq!(read.unwrap(), "<Project/>");
+        assert_eq!(fs.read_to_string(Path::new(r"C:\Studio\Bin\empty.txt")).unwrap(), "");
+        let missing = fs.read_to_string(Path::new(r"C:\Studio\Bin")).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+        assert!(AssumeExists.read_to_string(Path::new("x.targets")).is_err());
+    }
+
+    #[test]
+    fn assume_exists_is_always_true() {
+        assert!(AssumeExists.exists(Path::new(r"Z:\does\not\exist")));
+    }
+
+    #[test]
+    fn disk_fs_checks_real_files() {
+        assert!(DiskFileSystem.exists(Path::new("Cargo.toml")));
+        assert!(DiskFileSystem.exists(Path::new(r"src\lib.rs")));
+        assert!(!DiskFileSystem.exists(Path::new("definitely-not-here.targets")));
+        assert!(DiskFileSystem.read_to_string(Path::new(r"src\lib.rs")).unwrap().contains("pub mod"));
+    }
+
+    #[test]
+    fn absolute_path_detection() {
+        assert!(is_absolute(r"C:\Studio"));
+        assert!(is_absolute(r"\\server\share"));
+        assert!(is_absolute("/usr/lib"));
+        assert!(!is_absolute(r"..\shared\Debug.optset"));
+        assert!(!is_absolute("Project1.deployproj"));
+    }
+}
@@ -0,0 +1,1154 @@
+//! MSBuild property functions.
+//!
+//! Evaluates the text between `$(` and `)` in property values and
+//! conditions.  Besides plain variable references (`$(Config)`), MSBuild
+//! allows method calls on a property's string value and on a fixed set of
+//! static classes:
+//!
+//! - `$(ProductVersion.Substring(0, 2))`
+//! - `$(DCC_Define.Replace(';', ' '))`
+//! - `$([System.IO.Path]::Combine($(BDS), 'bin'))`
+//! - `$([MSBuild]::VersionGreaterThanOrEquals($(ProductVersion), '22.0'))`
+//! - `$([System.Environment]::GetEnvironmentVariable('APPDATA'))`
+//!
+//! Calls can be chained (`$(Foo.Trim().ToUpper())`) and arguments may be
+//! quoted with `'`, `"` or `` ` ``, or be unquoted numbers, booleans and
+//! `$(…)` references.  Type and member names are matched case-insensitively.
+//!
+//! ## Supported members
+//!
+//! | Receiver                | Members |
+//! |-------------------------|---------|
+//! | string value            | `Length`, `ToUpper`, `ToLower`, `ToUpperInvariant`, `ToLowerInvariant`, `Trim`, `TrimStart`, `TrimEnd`, `Replace`, `Substring`, `Remove`, `Insert`, `PadLeft`, `PadRight`, `StartsWith`, `EndsWith`, `Contains`, `Equals`, `IndexOf`, `LastIndexOf` |
+//! | `[System.IO.Path]`      | `Combine`, `GetFileName`, `GetFileNameWithoutExtension`, `GetDirectoryName`, `GetExtension`, `ChangeExtension`, `HasExtension`, `IsPathRooted`, `GetPathRoot`, `DirectorySeparatorChar`, `AltDirectorySeparatorChar`, `PathSeparator` |
+//! | `[MSBuild]`             | `Add`, `Subtract`, `Multiply`, `Divide`, `Modulo`, `BitwiseOr`, `BitwiseAnd`, `BitwiseXor`, `BitwiseNot`, `VersionEquals`, `VersionNotEquals`, `VersionGreaterThan`, `VersionGreaterThanOrEquals`, `VersionLessThan`, `VersionLessThanOrEquals`, `EnsureTrailingSlash`, `NormalizePath`, `NormalizeDirectory`, `MakeRelative`, `ValueOrDefault`, `Escape`, `Unescape`, `IsOSPlatform`, `IsOsPlatform` |
+//! | `[System.Environment]`  | `GetEnvironmentVariable`, `NewLine`, `Is64BitOperatingSystem` |
+//! | `[System.String]`       | `IsNullOrEmpty`, `IsNullOrWhiteSpace`, `Concat`, `Copy`, `Join`, `Empty` |
+//!
+//! Paths are treated as Windows paths: both `\` and `/` are separators and
+//! newly inserted separators are `\`.  Booleans are rendered as `True` /
+//! `False`, like .NET does.
+//!
+//! Evaluation never consults the host: `GetEnvironmentVariable` reads
+//! `vars`, and `IsOSPlatform` and `Is64BitOperatingSystem` report the
+//! [`Context`], by default a 64-bit Windows machine.
+
+use crate::vars::VarMap;
+
+/// Everything besides the variable map that property functions may
+/// consult: a description of the machine the build is evaluated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context<'a> {
+    /// The operating system `[MSBuild]::IsOSPlatform` compares its argument
+    /// with, e.g. `Windows`, `Linux` or `OSX`.  Set by
+    /// [`DprojBuilder::os_platform`](crate::dproj::DprojBuilder::os_platform).
+    pub os_platform: &'a str,
+    /// What `[System.Environment]::Is64BitOperatingSystem` reports.
+    pub is_64bit: bool,
+}
+
+impl Default for Context<'_> {
+    /// A 64-bit Windows machine.
+    fn default() -> Self {
+        Self { os_platform: "Windows", is_64bit: true }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Error
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Failure to evaluate a property function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionError {
+    /// The text between `$(` and `)`.
+    pub expression: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for FunctionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot evaluate '$({})': {}", self.expression, self.message)
+    }
+}
+
+impl std::error::Error for FunctionError {}
+
+/// Variables whose values reference each other in a cycle, so that
+/// recursive expansion would never finish.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceCycle {
+    /// The variables on the cycle in reference order, starting and ending
+    /// with the same name, e.g. `["OutDir", "OutRoot", "OutDir"]`.
+    pub path: Vec<String>,
+}
+
+impl std::fmt::Display for ReferenceCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Reference cycle: {}", self.path.join(" -> "))
+    }
+}
+
+impl std::error::Error for ReferenceCycle {}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Nesting scanner
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Tracks `$( … )` nesting character by character.
+///
+/// Inside a property reference, parentheses nest and `'`, `"` and `` ` ``
+/// delimit string arguments; inside such a string argument a further
+/// `$(` opens a nested reference.  Outside any reference only `$(` opens
+/// one, so literal parentheses and quotes are left alone.
+#[derive(Debug, Default)]
+pub(crate) struct Nesting {
+    stack: Vec<char>,
+    dollar: bool,
+}
+
+impl Nesting {
+    /// Feed the next character.
+    pub(crate) fn feed(&mut self, c: char) {
+        let after_dollar = std::mem::replace(&mut self.dollar, c == '$');
+        match self.stack.last() {
+            None => {
+                if after_dollar && c == '(' {
+                    self.stack.push('(');
+                }
+            }
+            Some('(') => match c {
+                '(' => self.stack.push('('),
+                ')' => {
+                    self.stack.pop();
+                }
+                '\'' | '"' | '`' => self.stack.push(c),
+                _ => {}
+            },
+            Some(&quote) => {
+                if c == quote {
+                    self.stack.pop();
+                } else if after_dollar && c == '(' {
+                    self.stack.push('(');
+                }
+            }
+        }
+    }
+
+    /// Whether a property reference is currently open.
+    pub(crate) fn is_open(&self) -> bool {
+        !self.stack.is_empty()
+    }
+}
+
+/// Given `s` starting with `$(`, return the byte length of the reference
+/// including the closing `)`, or `None` if it is unterminated.
+pub(crate) fn property_len(s: &str) -> Option<usize> {
+    if !s.starts_with("$(") {
+        return None;
+    }
+    let mut nesting = Nesting::default();
+    for (i, c) in s.char_indices() {
+        nesting.feed(c);
+        if i > 0 && !nesting.is_open() {
+            return Some(i + c.len_utf8());
+        }
+    }
+    None
+}
+
+/// Whether `body` is a plain property name rather than a function call.
+pub(crate) fn is_property_name(body: &str) -> bool {
+    let mut chars = body.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Names of the variables referenced by `$(…)` expressions in `value`,
+/// including those used inside property function calls, in order of first
+/// appearance.  Names differing only in case are reported once.
+///
+/// # Example
+/// ```
+/// let names = dproj_rs::functions::referenced_variables(
+///     r"$(BDS)\lib\$(Platform)\$(Config.ToLower());$(bds)",
+/// );
+/// assert_eq!(names, ["BDS", "Platform", "Config"]);
+/// ```
+pub fn referenced_variables(value: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("$(") {
+        let Some(len) = property_len(&rest[start..]) else {
+            break;
+        };
+        for name in referenced_properties(&rest[start + 2..start + len - 1]) {
+            push_unique(&mut names, name);
+        }
+        rest = &rest[start + len..];
+    }
+    names
+}
+
+/// Append `name` unless a case-insensitively equal name is already present.
+pub(crate) fn push_unique(names: &mut Vec<String>, name: String) {
+    if !names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+        names.push(name);
+    }
+}
+
+/// Names of the properties read by a `$(…)` body: the receiver of instance
+/// member calls (`Foo` in `Foo.Trim()`) and every reference nested in the
+/// arguments.  Static receivers such as `[MSBuild]` read no property.
+pub(crate) fn referenced_properties(body: &str) -> Vec<String> {
+    let body = body.trim();
+    let mut names = Vec::new();
+    if !body.starts_with('[') {
+        let end = body
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(body.len());
+        if end > 0 {
+            names.push(body[..end].to_string());
+        }
+    }
+
+    let mut rest = body;
+    while let Some(start) = rest.find("$(") {
+        let Some(len) = property_len(&rest[start..]) else {
+            break;
+        };
+        names.extend(referenced_properties(&rest[start + 2..start + len - 1]));
+        rest = &rest[start + len..];
+    }
+    names
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Expansion
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Expand every `$(…)` reference in `s`.
+///
+/// Plain references are looked up in `vars`; property functions are
+/// evaluated with the default [`Context`].  Unknown variables and failing
+/// functions expand to the empty string.  An unterminated `$(` is kept
+/// verbatim.
+pub fn expand(s: &str, vars: &VarMap) -> String {
+    expand_with(s, vars, &Context::default())
+}
+
+/// Like [`expand`], evaluating property functions in `ctx`.
+pub fn expand_with(s: &str, vars: &VarMap, ctx: &Context) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(start) = rest.find("$(") {
+        result.push_str(&rest[..start]);
+        let Some(len) = property_len(&rest[start..]) else {
+            rest = &rest[start..];
+            break;
+        };
+        let body = &rest[start + 2..start + len - 1];
+        result.push_str(&evaluate_with(body, vars, ctx).unwrap_or_default());
+        rest = &rest[start + len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Expand every `$(…)` reference in `s`, expanding references inside the
+/// values of the referenced variables as well, however deeply nested.
+///
+/// Fails if the variables involved reference each other in a cycle.
+///
+/// # Example
+/// ```
+/// use dproj_rs::VarMap;
+///
+/// let vars: VarMap = [("OutRoot", r"$(BDS)\out"), ("BDS", r"C:\Studio")].into_iter().collect();
+/// let out = dproj_rs::functions::expand_recursive(r"$(OutRoot)\Win32", &vars).unwrap();
+/// assert_eq!(out, r"C:\Studio\out\Win32");
+///
+/// let vars: VarMap = [("A", "$(B)"), ("B", "$(A)")].into_iter().collect();
+/// let err = dproj_rs::functions::expand_recursive("$(A)", &vars).unwrap_err();
+/// assert_eq!(err.path, ["A", "B", "A"]);
+/// ```
+pub fn expand_recursive(s: &str, vars: &VarMap) -> Result<String, ReferenceCycle> {
+    expand_recursive_with(s, vars, &Context::default())
+}
+
+/// Like [`expand_recursive`], evaluating property functions in `ctx`.
+pub fn expand_recursive_with(s: &str, vars: &VarMap, ctx: &Context) -> Result<String, ReferenceCycle> {
+    let mut resolver = Resolver::msbuild(vars, *ctx);
+    for name in referenced_variables(s) {
+        resolver.resolve(&name)?;
+    }
+    Ok(expand_with(s, &resolver.resolved, ctx))
+}
+
+/// Replace the plain references to `name` in `value` (`$(Name)`, in any
+/// case) by `replacement`.  References inside property function calls are
+/// left alone.
+pub(crate) fn substitute_reference(value: &str, name: &str, replacement: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("$(") {
+        let Some(len) = property_len(&rest[start..]) else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let body = rest[start + 2..start + len - 1].trim();
+        if body.eq_ignore_ascii_case(name) {
+            result.push_str(replacement);
+        } else {
+            result.push_str(&rest[start..start + len]);
+        }
+        rest = &rest[start + len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Expands the references in a value, given the variables resolved so far.
+type Expand<'a> = Box<dyn Fn(&str, &VarMap) -> String + 'a>;
+
+/// Recursive variable resolution with cycle detection, shared by `$(…)`
+/// and `%…%` expansion.
+///
+/// Variables are resolved depth-first: before a value is expanded, every
+/// variable it references is resolved.  Variables missing from `raw` are
+/// left to the expansion function (which normally expands them to the empty
+/// string).
+pub(crate) struct Resolver<'a> {
+    raw: &'a VarMap,
+    references: fn(&str) -> Vec<String>,
+    expand: Expand<'a>,
+    /// Fully expanded values of the variables resolved so far.
+    pub(crate) resolved: VarMap,
+    /// Variables currently being resolved, outermost first.
+    stack: Vec<String>,
+}
+
+impl<'a> Resolver<'a> {
+    pub(crate) fn new(
+        raw: &'a VarMap,
+        references: fn(&str) -> Vec<String>,
+        expand: impl Fn(&str, &VarMap) -> String + 'a,
+    ) -> Self {
+        Self { raw, references, expand: Box::new(expand), resolved: VarMap::new(), stack: Vec::new() }
+    }
+
+    /// Resolver for `$(…)` references, evaluating property functions in
+    /// `ctx`.
+    pub(crate) fn msbuild(raw: &'a VarMap, ctx: Context<'a>) -> Self {
+        Self::new(raw, referenced_variables, move |s, vars| expand_with(s, vars, &ctx))
+    }
+
+    /// Treat `vars` as already expanded.
+    pub(crate) fn seed(mut self, vars: VarMap) -> Self {
+        self.resolved = vars;
+        self
+    }
+
+    /// Resolve `name` and everything it references into
+    /// [`resolved`](Self::resolved).
+    pub(crate) fn resolve(&mut self, name: &str) -> Result<(), ReferenceCycle> {
+        if let Some(pos) = self.stack.iter().position(|n| n.eq_ignore_ascii_case(name)) {
+            let mut path = self.stack[pos..].to_vec();
+            path.push(self.stack[pos].clone());
+            return Err(ReferenceCycle { path });
+        }
+        if self.resolved.contains_key(name) {
+            return Ok(());
+        }
+        let Some((name, value)) = self.raw.get_key_value(name) else {
+            return Ok(());
+        };
+
+        self.stack.push(name.to_string());
+        for dep in (self.references)(value) {
+            self.resolve(&dep)?;
+        }
+        self.stack.pop();
+
+        let expanded = (self.expand)(value, &self.resolved);
+        self.resolved.insert(name, expanded);
+        Ok(())
+    }
+}
+
+/// Evaluate the body of a `$(…)` reference (the text between the
+/// parentheses) with the default [`Context`].
+///
+/// # Example
+/// ```
+/// use dproj_rs::VarMap;
+///
+/// let vars: VarMap = [("ProductVersion", "23.0")].into_iter().collect();
+/// let major = dproj_rs::functions::evaluate("ProductVersion.Substring(0, 2)", &vars);
+/// assert_eq!(major.unwrap(), "23");
+/// ```
+pub fn evaluate(body: &str, vars: &VarMap) -> Result<String, FunctionError> {
+    evaluate_with(body, vars, &Context::default())
+}
+
+/// Like [`evaluate`], in `ctx`.
+pub fn evaluate_with(body: &str, vars: &VarMap, ctx: &Context) -> Result<String, FunctionError> {
+    let body = body.trim();
+    if is_property_name(body) {
+        return Ok(vars.get(body).cloned().unwrap_or_default());
+    }
+
+    let err = |message: String| FunctionError { expression: body.to_string(), message };
+    let mut cursor = Cursor { s: body, pos: 0 };
+
+    let mut value = if cursor.eat("[") {
+        let ty = cursor.take_until(']').ok_or_else(|| err("unterminated type name".into()))?;
+        cursor.eat("]");
+        if !cursor.eat("::") {
+            return Err(err(format!("expected '::' after [{ty}]")));
+        }
+        let member = cursor.ident().ok_or_else(|| err("expected member name".into()))?;
+        let args = cursor.args(vars, ctx).map_err(err)?;
+        call_static(ty.trim(), member, args.as_deref(), vars, ctx).map_err(err)?
+    } else {
+        let name = cursor.ident().ok_or_else(|| err("expected property name".into()))?;
+        vars.get(name).cloned().unwrap_or_default()
+    };
+
+    while cursor.eat(".") {
+        let member = cursor.ident().ok_or_else(|| err("expected member name".into()))?;
+        let args = cursor.args(vars, ctx).map_err(err)?;
+        value = call_instance(&value, member, args.as_deref()).map_err(err)?;
+    }
+
+    cursor.skip_ws();
+    if cursor.pos < body.len() {
+        return Err(err(format!("unexpected '{}'", &body[cursor.pos..])));
+    }
+    Ok(value)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Body parsing
+// ═══════════════════════════════════════════════════════════════════════════════
+
+struct Cursor<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.s.len() - trimmed.len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_until(&mut self, end: char) -> Option<&'a str> {
+        let idx = self.rest().find(end)?;
+        let taken = &self.rest()[..idx];
+        self.pos += idx;
+        Some(taken)
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return None;
+        }
+        let ident = &self.rest()[..len];
+        self.pos += len;
+        Some(ident)
+    }
+
+    /// Parse an optional `( arg, … )` list, evaluating each argument.
+    /// Returns `None` for a property access without parentheses.
+    fn args(&mut self, vars: &VarMap, ctx: &Context) -> Result<Option<Vec<String>>, String> {
+        self.skip_ws();
+        if !self.rest().starts_with('(') {
+            return Ok(None);
+        }
+
+        let mut nesting = Nesting::default();
+        nesting.stack.push('(');
+        let mut raw_args = Vec::new();
+        let mut arg_start = self.pos + 1;
+        let mut end = None;
+
+        for (i, c) in self.rest().char_indices().skip(1) {
+            if c == ',' && nesting.stack.len() == 1 && nesting.stack[0] == '(' {
+                raw_args.push(&self.s[arg_start..self.pos + i]);
+                arg_start = self.pos + i + 1;
+                continue;
+            }
+            nesting.feed(c);
+            if !nesting.is_open() {
+                end = Some(self.pos + i);
+                break;
+            }
+        }
+
+        let end = end.ok_or("unterminated argument list")?;
+        let last = &self.s[arg_start..end];
+        if !(raw_args.is_empty() && last.trim().is_empty()) {
+            raw_args.push(last);
+        }
+        self.pos = end + 1;
+
+        Ok(Some(raw_args.into_iter().map(|a| eval_arg(a, vars, ctx)).collect()))
+    }
+}
+
+/// Evaluate a single raw argument: strip matching quotes and expand any
+/// nested `$(…)` references.
+fn eval_arg(raw: &str, vars: &VarMap, ctx: &Context) -> String {
+    let raw = raw.trim();
+    for quote in ['\'', '"', '`'] {
+        if raw.len() >= 2 && raw.starts_with(quote) && raw.ends_with(quote) {
+            return expand_with(&raw[1..raw.len() - 1], vars, ctx);
+        }
+    }
+    expand_with(raw, vars, ctx)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Member dispatch
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn call_static(
+    ty: &str,
+    member: &str,
+    args: Option<&[String]>,
+    vars: &VarMap,
+    ctx: &Context,
+) -> Result<String, String> {
+    let ty_lower = ty.to_ascii_lowercase();
+    match ty_lower.as_str() {
+        "system.io.path" => call_path(member, args),
+        "msbuild" => call_msbuild(member, args, ctx),
+        "system.environment" => call_environment(member, args, vars, ctx),
+        "system.string" => call_string_static(member, args),
+        _ => Err(format!("unsupported type [{ty}]")),
+    }
+}
+
+/// Fetch exactly `N` arguments.
+fn exact<'a, const N: usize>(member: &str, args: Option<&'a [String]>) -> Result<[&'a str; N], String> {
+    let args = args.unwrap_or_default();
+    if args.len() != N {
+        return Err(format!("{member} expects {N} argument(s), got {}", args.len()));
+    }
+    Ok(std::array::from_fn(|i| args[i].as_str()))
+}
+
+fn bool_str(b: bool) -> String {
+    if b { "True" } else { "False" }.to_string()
+}
+
+/// Largest width `PadLeft`/`PadRight` pad to, so that a project cannot make
+/// evaluation allocate an unbounded string.
+const MAX_PADDED_WIDTH: usize = 1 << 16;
+
+fn call_instance(value: &str, member: &str, args: Option<&[String]>) -> Result<String, String> {
+    let m = member.to_ascii_lowercase();
+    let chars: Vec<char> = value.chars().collect();
+    let index = |s: &str| -> Result<usize, String> {
+        s.trim().parse::<usize>().map_err(|_| format!("invalid index '{s}'"))
+    };
+    let slice = |start: usize, len: usize| -> Result<String, String> {
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= chars.len())
+            .ok_or_else(|| format!("index {start}+{len} out of range for '{value}'"))?;
+        Ok(chars[start..end].iter().collect())
+    };
+
+    Ok(match m.as_str() {
+        "length" => chars.len().to_string(),
+        "toupper" | "toupperinvariant" => value.to_uppercase(),
+        "tolower" | "tolowerinvariant" => value.to_lowercase(),
+        "trim" | "trimstart" | "trimend" => {
+            let set: Option<Vec<char>> = args
+                .filter(|a| !a.is_empty())
+                .map(|a| a.iter().flat_map(|s| s.chars()).collect());
+            let pred = |c: char| match &set {
+                Some(set) => set.contains(&c),
+                None => c.is_whitespace(),
+            };
+            match m.as_str() {
+                "trim" => value.trim_matches(pred),
+                "trimstart" => value.trim_start_matches(pred),
+                _ => value.trim_end_matches(pred),
+            }
+            .to_string()
+        }
+        "replace" => {
+            let [from, to] = exact::<2>(member, args)?;
+            if from.is_empty() {
+                return Err("Replace: old value must not be empty".into());
+            }
+            value.replace(from, to)
+        }
+        "substring" => match args.unwrap_or_default() {
+            [start] => {
+                let start = index(start)?;
+                slice(start, chars.len().saturating_sub(start))?
+            }
+            [start, len] => slice(index(start)?, index(len)?)?,
+            _ => return Err("Substring expects 1 or 2 arguments".into()),
+        },
+        "remove" => match args.unwrap_or_default() {
+            [start] => slice(0, index(start)?.min(chars.len()))?,
+            [start, count] => {
+                let (start, count) = (index(start)?, index(count)?);
+                let end = start
+                    .checked_add(count)
+                    .filter(|&end| end <= chars.len())
+                    .ok_or("Remove: range out of bounds")?;
+                chars[..start].iter().chain(&chars[end..]).collect()
+            }
+            _ => return Err("Remove expects 1 or 2 arguments".into()),
+        },
+        "insert" => {
+            let [at, text] = exact::<2>(member, args)?;
+            let at = index(at)?;
+            if at > chars.len() {
+                return Err("Insert: index out of range".into());
+            }
+            let mut out: String = chars[..at].iter().collect();
+            out.push_str(text);
+            out.extend(&chars[at..]);
+            out
+        }
+        "padleft" | "padright" => {
+            let args = args.unwrap_or_default();
+            let (width, pad) = match args {
+                [w] => (index(w)?, ' '),
+                [w, p] => (index(w)?, p.chars().next().unwrap_or(' ')),
+                _ => return Err(format!("{member} expects 1 or 2 arguments")),
+            };
+            if width > chars.len().max(MAX_PADDED_WIDTH) {
+                return Err(format!("{member}: width {width} too large"));
+            }
+            let fill: String = std::iter::repeat_n(pad, width.saturating_sub(chars.len())).collect();
+            if m == "padleft" { fill + value } else { value.to_string() + &fill }
+        }
+        "startswith" => bool_str(value.starts_with(exact::<1>(member, args)?[0])),
+        "endswith" => bool_str(value.ends_with(exact::<1>(member, args)?[0])),
+        "contains" => bool_str(value.contains(exact::<1>(member, args)?[0])),
+        "equals" => bool_str(value == exact::<1>(member, args)?[0]),
+        "indexof" | "lastindexof" => {
+            let [needle] = exact::<1>(member, args)?;
+            let found = if m == "indexof" { value.find(needle) } else { value.rfind(needle) };
+            match found {
+                Some(byte) => value[..byte].chars().count().to_string(),
+                None => "-1".to_string(),
+            }
+        }
+        _ => return Err(format!("unsupported string member '{member}'")),
+    })
+}
+
+// ─── [System.IO.Path] ────────────────────────────────────────────────────────
+
+fn is_sep(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+/// Index just past the directory part of `path` (after the last separator
+/// or drive colon), i.e. where the file name starts.
+fn file_name_start(path: &str) -> usize {
+    path.rfind(|c: char| is_sep(c) || c == ':').map_or(0, |i| i + 1)
+}
+
+fn extension_start(path: &str) -> Option<usize> {
+    let name_start = file_name_start(path);
+    path[name_start..]
+        .rfind('.')
+        .map(|i| name_start + i)
+        .filter(|&i| i + 1 < path.len())
+}
+
+fn path_root(path: &str) -> &str {
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        if bytes.len() >= 3 && is_sep(bytes[2] as char) { &path[..3] } else { &path[..2] }
+    } else if path.starts_with(is_sep) {
+        &path[..1]
+    } else {
+        ""
+    }
+}
+
+fn combine<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut result = String::new();
+    for part in parts {
+        if part.is_empty() {
+            continue;
+        }
+        if !path_root(part).is_empty() {
+            result = part.to_string();
+        } else {
+            if !result.is_empty() && !result.ends_with(is_sep) && !result.ends_with(':') {
+                result.push('\\');
+            }
+            result.push_str(part);
+        }
+    }
+    result
+}
+
+fn call_path(member: &str, args: Option<&[String]>) -> Result<String, String> {
+    let m = member.to_ascii_lowercase();
+    Ok(match m.as_str() {
+        "directoryseparatorchar" => "\\".into(),
+        "altdirectoryseparatorchar" => "/".into(),
+        "pathseparator" => ";".into(),
+        "combine" => combine(args.unwrap_or_default().iter().map(String::as_str)),
+        "getfilename" => {
+            let [p] = exact::<1>(member, args)?;
+            p[file_name_start(p)..].to_string()
+        }
+        "getfilenamewithoutextension" => {
+            let [p] = exact::<1>(member, args)?;
+            let end = extension_start(p).unwrap_or(p.len());
+            p[file_name_start(p)..end].to_string()
+        }
+        "getdirectoryname" => {
+            let [p] = exact::<1>(member, args)?;
+            let root = path_root(p);
+            if p.len() <= root.len() {
+                String::new()
+            } else {
+                let dir = &p[..file_name_start(p)];
+                let trimmed = dir.trim_end_matches(is_sep);
+                if trimmed.len() < root.len() { root.to_string() } else { trimmed.to_string() }
+            }
+        }
+        "getextension" => {
+            let [p] = exact::<1>(member, args)?;
+            extension_start(p).map(|i| p[i..].to_string()).unwrap_or_default()
+        }
+        "changeextension" => {
+            let [p, ext] = exact::<2>(member, args)?;
+            let base = &p[..extension_start(p).unwrap_or(p.len())];
+            let base = base.strip_suffix('.').unwrap_or(base);
+            if ext.starts_with('.') { format!("{base}{ext}") } else { format!("{base}.{ext}") }
+        }
+        "hasextension" => bool_str(extension_start(exact::<1>(member, args)?[0]).is_some()),
+        "ispathrooted" => bool_str(!path_root(exact::<1>(member, args)?[0]).is_empty()),
+        "getpathroot" => path_root(exact::<1>(member, args)?[0]).to_string(),
+        _ => return Err(format!("unsupported member [System.IO.Path]::{member}")),
+    })
+}
+
+// ─── [MSBuild] ───────────────────────────────────────────────────────────────
+
+fn integer(s: &str) -> Option<i64> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{n}")
+    }
+}
+
+fn arithmetic(member: &str, args: Option<&[String]>, op: char) -> Result<String, String> {
+    let [a, b] = exact::<2>(member, args)?;
+    if let (Some(x), Some(y)) = (integer(a), integer(b)) {
+        let r = match op {
+            '+' => x.checked_add(y),
+            '-' => x.checked_sub(y),
+            '*' => x.checked_mul(y),
+            '/' => x.checked_div(y),
+            _ => x.checked_rem(y),
+        };
+        return r.map(|r| r.to_string()).ok_or_else(|| format!("{member}: arithmetic error"));
+    }
+    let x = parse_number(a).ok_or_else(|| format!("'{a}' is not a number"))?;
+    let y = parse_number(b).ok_or_else(|| format!("'{b}' is not a number"))?;
+    Ok(format_number(match op {
+        '+' => x + y,
+        '-' => x - y,
+        '*' => x * y,
+        '/' => x / y,
+        _ => x % y,
+    }))
+}
+
+fn version_compare(member: &str, args: Option<&[String]>) -> Result<std::cmp::Ordering, String> {
+    let [a, b] = exact::<2>(member, args)?;
+    let a = parse_version(a).ok_or_else(|| format!("'{a}' is not a version"))?;
+    let b = parse_version(b).ok_or_else(|| format!("'{b}' is not a version"))?;
+    Ok(a.cmp(&b))
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '%' | '*' | '?' | '@' | '$' | '(' | ')' | ';' | '\'') {
+            out.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let Some(hex) = s.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn make_relative(base: &str, path: &str) -> String {
+    let split = |p: &str| -> Vec<String> {
+        p.split(is_sep).filter(|s| !s.is_empty()).map(str::to_string).collect()
+    };
+    let base_parts = split(base);
+    let path_parts = split(path);
+    let common = base_parts
+        .iter()
+        .zip(&path_parts)
+        .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+        .count();
+    if common == 0 && !path_root(path).is_empty() {
+        return path.to_string();
+    }
+    let mut parts: Vec<&str> = vec![".."; base_parts.len() - common];
+    parts.extend(path_parts[common..].iter().map(String::as_str));
+    let mut out = parts.join("\\");
+    if path.ends_with(is_sep) && !out.is_empty() {
+        out.push('\\');
+    }
+    out
+}
+
+fn call_msbuild(member: &str, args: Option<&[String]>, ctx: &Context) -> Result<String, String> {
+    use std::cmp::Ordering;
+
+    let m = member.to_ascii_lowercase();
+    Ok(match m.as_str() {
+        "add" => arithmetic(member, args, '+')?,
+        "subtract" => arithmetic(member, args, '-')?,
+        "multiply" => arithmetic(member, args, '*')?,
+        "divide" => arithmetic(member, args, '/')?,
+        "modulo" => arithmetic(member, args, '%')?,
+        "bitwiseor" | "bitwiseand" | "bitwisexor" => {
+            let [a, b] = exact::<2>(member, args)?;
+            let (a, b) = (
+                integer(a).ok_or_else(|| format!("'{a}' is not an integer"))?,
+                integer(b).ok_or_else(|| format!("'{b}' is not an integer"))?,
+            );
+            match m.as_str() {
+                "bitwiseor" => a | b,
+                "bitwiseand" => a & b,
+                _ => a ^ b,
+            }
+            .to_string()
+        }
+        "bitwisenot" => {
+            let [a] = exact::<1>(member, args)?;
+            (!integer(a).ok_or_else(|| format!("'{a}' is not an integer"))?).to_string()
+        }
+        "versionequals" => bool_str(version_compare(member, args)? == Ordering::Equal),
+        "versionnotequals" => bool_str(version_compare(member, args)? != Ordering::Equal),
+        "versiongreaterthan" => bool_str(version_compare(member, args)?.is_gt()),
+        "versiongreaterthanorequals" => bool_str(version_compare(member, args)?.is_ge()),
+        "versionlessthan" => bool_str(version_compare(member, args)?.is_lt()),
+        "versionlessthanorequals" => bool_str(version_compare(member, args)?.is_le()),
+        "ensuretrailingslash" => {
+            let [p] = exact::<1>(member, args)?;
+            if p.is_empty() || p.ends_with(is_sep) { p.to_string() } else { format!("{p}\\") }
+        }
+        "normalizepath" => combine(args.unwrap_or_default().iter().map(String::as_str)),
+        "normalizedirectory" => {
+            let p = combine(args.unwrap_or_default().iter().map(String::as_str));
+            if p.is_empty() || p.ends_with(is_sep) { p } else { p + "\\" }
+        }
+        "makerelative" => {
+            let [base, path] = exact::<2>(member, args)?;
+            make_relative(base, path)
+        }
+        "valueordefault" => {
+            let [value, default] = exact::<2>(member, args)?;
+            if value.is_empty() { default } else { value }.to_string()
+        }
+        "escape" => escape(exact::<1>(member, args)?[0]),
+        "unescape" => unescape(exact::<1>(member, args)?[0]),
+        "isosplatform" => {
+            let [os] = exact::<1>(member, args)?;
+            bool_str(os.trim().eq_ignore_ascii_case(ctx.os_platform.trim()))
+        }
+        _ => return Err(format!("unsupported member [MSBuild]::{member}")),
+    })
+}
+
+// ─── [System.Environment] / [System.String] ─────────────────────────────────
+
+fn call_environment(
+    member: &str,
+    args: Option<&[String]>,
+    vars: &VarMap,
+    ctx: &Context,
+) -> Result<String, String> {
+    Ok(match member.to_ascii_lowercase().as_str() {
+        "getenvironmentvariable" => {
+            let [name] = exact::<1>(member, args)?;
+            vars.get(name).cloned().unwrap_or_default()
+        }
+        "newline" => "\r\n".into(),
+        "is64bitoperatingsystem" => bool_str(ctx.is_64bit),
+        _ => return Err(format!("unsupported member [System.Environment]::{member}")),
+    })
+}
+
+fn call_string_static(member: &str, args: Option<&[String]>) -> Result<String, String> {
+    Ok(match member.to_ascii_lowercase().as_str() {
+        "empty" => String::new(),
+        "isnullorempty" => bool_str(exact::<1>(member, args)?[0].is_empty()),
+        "isnullorwhitespace" => bool_str(exact::<1>(member, args)?[0].trim().is_empty()),
+        "concat" => args.unwrap_or_default().concat(),
+        "copy" => exact::<1>(member, args)?[0].to_string(),
+        "join" => match args.unwrap_or_default() {
+            [sep, rest @ ..] => rest.join(sep),
+            [] => return Err("Join expects at least 1 argument".into()),
+        },
+        _ => return Err(format!("unsupported member [System.String]::{member}")),
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Shared value parsing
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Parse a decimal (`-1.5`) or hexadecimal (`0x1F`) number.
+pub(crate) fn parse_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return i64::from_str_radix(hex, 16).ok().map(|n| n as f64);
+    }
+    let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if digits.is_empty()
+        || !digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        || digits.matches('.').count() > 1
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Parse a dotted version with one to four numeric components, padding
+/// missing components with zero.
+pub(crate) fn parse_version(s: &str) -> Option<[u64; 4]> {
+    let mut version = [0u64; 4];
+    let mut count = 0;
+    for (i, part) in s.trim().split('.').enumerate() {
+        if i >= 4 || part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        version[i] = part.parse().ok()?;
+        count += 1;
+    }
+    (count > 0).then_some(version)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_vars(pairs: &[(&str, &str)]) -> VarMap {
+        pairs.iter().copied().collect()
+    }
+
+    fn eval(body: &str, vars: &VarMap) -> String {
+        evaluate(body, vars).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // ── Scanning ─────────────────────────────────────────────────────────
+
+    #[test]
+    fn property_len_handles_nesting_and_quotes() {
+        assert_eq!(property_len("$(Config)"), Some(9));
+        assert_eq!(property_len("$(Config)\\rest"), Some(9));
+        assert_eq!(property_len("$(A.Replace(')', '('))x"), Some(22));
+        assert_eq!(property_len("$([MSBuild]::Add($(A), 1))"), Some(26));
+        assert_eq!(property_len("$(A.Replace('$(B.Trim(')'))', 'x'))"), Some(35));
+        assert_eq!(property_len("$(Unclosed"), None);
+        assert_eq!(property_len("Config"), None);
+    }
+
+    #[test]
+    fn referenced_properties_of_bodies() {
+        assert_eq!(referenced_properties("Config"), vec!["Config"]);
+        assert_eq!(referenced_properties("DCC_Define.Replace(';', '$(Sep)')"), vec!["DCC_Define", "Sep"]);
+        assert_eq!(
+            referenced_properties("[System.IO.Path]::Combine($(BDS), '$(Platform.ToLower())')"),
+            vec!["BDS", "Platform"]
+        );
+        assert!(referenced_properties("[MSBuild]::Add(1, 2)").is_empty());
+    }
+
+    #[test]
+    fn expand_mixes_literals_and_functions() {
+        let vars = make_vars(&[("BDS", r"C:\Studio"), ("Config", "Debug")]);
+        assert_eq!(
+            expand(r"$([System.IO.Path]::Combine($(BDS), 'bin'))\$(Config.ToLower())", &vars),
+            r"C:\Studio\bin\debug"
+        );
+        assert_eq!(expand("$(Missing)x$(Unclosed", &vars), "x$(Unclosed");
+        assert_eq!(expand("$(Config.NoSuchMethod())!", &vars), "!");
+    }
+
+    // ── String members ───────────────────────────────────────────────────
+
+    #[test]
+    fn string_members() {
+        let vars = make_vars(&[("V", "23.0"), ("D", "DEBUG;TRACE"), ("P", "  pad  ")]);
+        assert_eq!(eval("V.Substring(0,2)", &vars), "23");
+        assert_eq!(eval("V.Substring(3)", &vars), "0");
+        assert_eq!(eval("D.Replace(';', ' ')", &vars), "DEBUG TRACE");
+        assert_eq!(eval("D.Length", &vars), "11");
+        assert_eq!(eval("D.ToLower()", &vars), "debug;trace");
+        assert_eq!(eval("P.Trim().ToUpper()", &vars), "PAD");
+        assert_eq!(eval("P.TrimStart()", &vars), "pad  ");
+        assert_eq!(eval("D.TrimEnd('E', 'C')", &vars), "DEBUG;TRA");
+        assert_eq!(eval("D.StartsWith('DEBUG')", &vars), "True");
+        assert_eq!(eval("D.Contains('X')", &vars), "False");
+        assert_eq!(eval("D.IndexOf(';')", &vars), "5");
+        assert_eq!(eval("D.LastIndexOf('Z')", &vars), "-1");
+        assert_eq!(eval("V.PadLeft(6, '0')", &vars), "0023.0");
+        assert_eq!(eval("V.Remove(2)", &vars), "23");
+        assert_eq!(eval("V.Insert(2, '!')", &vars), "23!.0");
+        assert_eq!(eval("Missing.Length", &vars), "0");
+    }
+
+    #[test]
+    fn string_member_errors() {
+        let vars = make_vars(&[("V", "23")]);
+        assert!(evaluate("V.Substring(5)", &vars).is_err());
+        assert!(evaluate("V.Replace('a')", &vars).is_err());
+        assert!(evaluate("V.Frobnicate()", &vars).is_err());
+        assert!(evaluate("V.Trim(", &vars).is_err());
+    }
+
+    #[test]
+    fn huge_string_member_arguments_are_errors() {
+        let vars = make_vars(&[("X", "abc")]);
+        let max = usize::MAX;
+        assert!(evaluate(&format!("X.Substring({max}, 2)"), &vars).is_err());
+        assert!(evaluate(&format!("X.Remove({max}, 2)"), &vars).is_err());
+        assert!(evaluate(&format!("X.Remove(1, {max})"), &vars).is_err());
+        assert!(evaluate(&format!("X.PadLeft({max})"), &vars).is_err());
+        assert!(evaluate(&format!("X.PadRight({max}, '0')"), &vars).is_err());
+        assert_eq!(expand(&format!("$(X.Substring({max}, 2))"), &vars), "");
+        let cond = format!("'$(X.Substring({max}, 2))'==''");
+        let expr = crate::condition::parse_condition(&cond).unwrap();
+        assert!(crate::condition::evaluate(&expr, &vars));
+    }
+
+    // ── Static members ───────────────────────────────────────────────────
+
+    #[test]
+    fn path_members() {
+        let vars = make_vars(&[("F", r"C:\src\app\Project1.dproj")]);
+        assert_eq!(eval("[System.IO.Path]::GetFileName($(F))", &vars), "Project1.dproj");
+        assert_eq!(eval("[System.IO.Path]::GetFileNameWithoutExtension('$(F)')", &vars), "Project1");
+        assert_eq!(eval("[System.IO.Path]::GetDirectoryName($(F))", &vars), r"C:\src\app");
+        assert_eq!(eval("[System.IO.Path]::GetExtension($(F))", &vars), ".dproj");
+        assert_eq!(eval("[System.IO.Path]::ChangeExtension($(F), 'exe')", &vars), r"C:\src\app\Project1.exe");
+        assert_eq!(eval("[System.IO.Path]::Combine('a', 'b', 'c.pas')", &vars), r"a\b\c.pas");
+        assert_eq!(eval(r"[System.IO.Path]::Combine('a', 'D:\x')", &vars), r"D:\x");
+        assert_eq!(eval("[system.io.path]::IsPathRooted('rel')", &vars), "False");
+        assert_eq!(eval("[System.IO.Path]::GetPathRoot($(F))", &vars), r"C:\");
+        assert_eq!(eval("[System.IO.Path]::DirectorySeparatorChar", &vars), r"\");
+    }
+
+    #[test]
+    fn msbuild_members() {
+        let vars = make_vars(&[("ProductVersion", "23.0"), ("Empty", "")]);
+        assert_eq!(eval("[MSBuild]::Add(40, 2)", &vars), "42");
+        assert_eq!(eval("[MSBuild]::Subtract(1.5, 0.5)", &vars), "1");
+        assert_eq!(eval("[MSBuild]::Divide(7, 2)", &vars), "3");
+        assert_eq!(eval("[MSBuild]::Modulo(7, 2)", &vars), "1");
+        assert_eq!(eval("[MSBuild]::BitwiseOr(1, 2)", &vars), "3");
+        assert_eq!(
+            eval("[MSBuild]::VersionGreaterThanOrEquals($(ProductVersion), '22.0')", &vars),
+            "True"
+        );
+        assert_eq!(eval("[MSBuild]::VersionLessThan('10.0.2', '10.0.10')", &vars), "True");
+        assert_eq!(eval("[MSBuild]::EnsureTrailingSlash('out')", &vars), r"out\");
+        assert_eq!(eval("[MSBuild]::ValueOrDefault('$(Empty)', 'fallback')", &vars), "fallback");
+        assert_eq!(eval(r"[MSBuild]::MakeRelative('C:\a\b', 'C:\a\c\d.pas')", &vars), r"..\c\d.pas");
+        assert_eq!(eval("[MSBuild]::Escape('a;b')", &vars), "a%3Bb");
+        assert_eq!(eval("[MSBuild]::Unescape('a%3Bb')", &vars), "a;b");
+        assert_eq!(eval("[MSBuild]::IsOSPlatform('windows')", &vars), "True");
+        assert_eq!(eval("[MSBuild]::IsOsPlatform('Linux')", &vars), "False");
+        let linux = Context { os_platform: "Linux", ..Context::default() };
+        let is_linux = |os| evaluate_with(&format!("[MSBuild]::IsOSPlatform('{os}')"), &vars, &linux).unwrap();
+        assert_eq!(is_linux("LINUX"), "True");
+        assert_eq!(is_linux("Windows"), "False");
+        assert_eq!(expand_with("$([MSBuild]::IsOSPlatform('Linux'))", &vars, &linux), "True");
+        assert!(evaluate("[MSBuild]::Add('x', 1)", &vars).is_err());
+        assert!(evaluate("[MSBuild]::NoSuchThing()", &vars).is_err());
+    }
+
+    #[test]
+    fn environment_and_string_members() {
+        let vars = make_vars(&[("BDS", r"C:\Studio"), ("Empty", "")]);
+        assert_eq!(eval("[System.Environment]::GetEnvironmentVariable('BDS')", &vars), r"C:\Studio");
+        assert_eq!(eval("[System.Environment]::GetEnvironmentVariable('PATH')", &vars), "");
+        assert_eq!(eval("[System.Environment]::Is64BitOperatingSystem", &vars), "True");
+        let x86 = Context { is_64bit: false, ..Context::default() };
+        let is_64bit = evaluate_with("[System.Environment]::Is64BitOperatingSystem", &vars, &x86);
+        assert_eq!(is_64bit.unwrap(), "False");
+        assert_eq!(eval("[System.String]::IsNullOrEmpty($(Empty))", &vars), "True");
+        assert_eq!(eval("[System.String]::Concat('a', $(BDS))", &vars), r"aC:\Studio");
+        assert_eq!(eval("[System.String]::Join(';', 'a', 'b')", &vars), "a;b");
+        assert!(evaluate("[System.Console]::ReadLine()", &vars).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! Dependencies between the properties of a project.
+//!
+//! [`PropertyGraph`] records, for every property assignment in every
+//! `<PropertyGroup>`, which variables the assigned value reads and which
+//! variables decide whether the assignment happens at all (the group's and
+//! the element's `Condition`).  It answers questions such as:
+//!
+//! - Which properties form a reference cycle?
+//! - Which variables must come from outside the project (`rsvars.bat`, the
+//!   environment)?
+//! - What does `DCC_ExeOutput` depend on?
+//!
+//! The graph is static: it covers every group regardless of whether its
+//! condition holds for a particular configuration.
+
+use std::collections::HashMap;
+
+use crate::dproj::Dproj;
+use crate::functions::{self, push_unique};
+
+/// One assignment of a property inside a `<PropertyGroup>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyDefinition {
+    /// Property (element) name, e.g. `"DCC_ExeOutput"`.
+    pub name: String,
+    /// Index of the `<PropertyGroup>` in
+    /// [`DprojProject::property_groups`](crate::dproj::DprojProject::property_groups).
+    pub group: usize,
+    /// Variables read by the assigned value.  A reference to the property
+    /// itself (`DEBUG;$(DCC_Define)`) extends the previous value rather than
+    /// creating a dependency, and is left out.
+    pub value_references: Vec<String>,
+    /// Variables read by the group's and the element's `Condition`.
+    pub condition_references: Vec<String>,
+}
+
+/// Property dependency graph of a project, built by
+/// [`Dproj::property_graph`].
+///
+/// Property names are compared case-insensitively.
+#[derive(Debug, Clone, Default)]
+pub struct PropertyGraph {
+    /// Every property assignment, group by group in document order.
+    /// Within a group, the unconditional assignments come first, sorted by
+    /// name, followed by the elements with their own `Condition` in
+    /// document order.
+    pub definitions: Vec<PropertyDefinition>,
+    /// Variables `Dproj` provides itself (`Config`, `Platform`, build
+    /// configuration keys, …).
+    supplied: Vec<String>,
+    /// Variables read by `<Import>` elements.
+    import_references: Vec<String>,
+}
+
+impl PropertyGraph {
+    pub(crate) fn build(dproj: &Dproj, supplied: Vec<String>) -> Self {
+        let mut definitions = Vec::new();
+        for (group, pg) in dproj.project.property_groups.iter().enumerate() {
+            let group_refs = pg
+                .condition
+                .as_deref()
+                .map(functions::referenced_variables)
+                .unwrap_or_default();
+
+            let properties = pg.properties();
+            let mut assignments: Vec<(&str, &str, Option<&str>)> =
+                properties.iter().map(|(name, value)| (name, value, None)).collect();
+            assignments.sort_unstable_by_key(|&(name, _, _)| name.to_ascii_lowercase());
+            assignments.extend(pg.elements.iter().filter_map(|e| {
+                e.condition.as_deref().map(|cond| (e.tag.as_str(), e.value.as_str(), Some(cond)))
+            }));
+
+            for (name, value, element_condition) in assignments {
+                let mut condition_references = group_refs.clone();
+                if let Some(cond) = element_condition {
+                    for var in functions::referenced_variables(cond) {
+                        push_unique(&mut condition_references, var);
+                    }
+                }
+                let value_references = functions::referenced_variables(value)
+                    .into_iter()
+                    .filter(|var| !var.eq_ignore_ascii_case(name))
+                    .collect();
+                definitions.push(PropertyDefinition {
+                    name: name.to_string(),
+                    group,
+                    value_references,
+                    condition_references,
+                });
+            }
+        }
+
+        let mut import_references = Vec::new();
+        for import in &dproj.project.imports {
+            let texts = std::iter::once(import.project.as_str()).chain(import.condition.as_deref());
+            for text in texts {
+                for var in functions::referenced_variables(text) {
+                    push_unique(&mut import_references, var);
+                }
+            }
+        }
+
+        Self { definitions, supplied, import_references }
+    }
+
+    /// Whether the project assigns `name` in any `<PropertyGroup>`.
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.definitions.iter().any(|d| d.name.eq_ignore_ascii_case(name))
+    }
+
+    /// All assignments of `name`, in the order of
+    /// [`definitions`](Self::definitions).
+    pub fn definitions_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a PropertyDefinition> {
+        self.definitions.iter().filter(move |d| d.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Variables that the values assigned to `name` read, over all of its
+    /// definitions.
+    pub fn dependencies(&self, name: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        for def in self.definitions_of(name) {
+            for var in &def.value_references {
+                push_unique(&mut names, var.clone());
+            }
+        }
+        names
+    }
+
+    /// Variables the project reads but neither assigns nor receives from
+    /// `Dproj` itself — typically `rsvars.bat` or environment variables such
+    /// as `BDS` and `BDSCOMMONDIR`.  Includes references in conditions and
+    /// `<Import>` elements.
+    pub fn external_variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let referenced = self
+            .definitions
+            .iter()
+            .flat_map(|d| d.condition_references.iter().chain(&d.value_references))
+            .chain(&self.import_references);
+        for var in referenced {
+            let supplied = self.supplied.iter().any(|s| s.eq_ignore_ascii_case(var));
+            if !supplied && !self.is_defined(var) {
+                push_unique(&mut names, var.clone());
+            }
+        }
+        names
+    }
+
+    /// Groups of properties whose values reference each other in a cycle
+    /// (`A` → `B` → … → `A`).  Each cycle lists its members sorted by name;
+    /// cycles are sorted by their first member.  Only value references are
+    /// considered, since conditions do not make a value depend on another.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        // Nodes: distinct property names (first spelling wins).
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut names: Vec<&str> = Vec::new();
+        for def in &self.definitions {
+            index.entry(def.name.to_ascii_lowercase()).or_insert_with(|| {
+                names.push(&def.name);
+                names.len() - 1
+            });
+        }
+
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+        for def in &self.definitions {
+            let from = index[&def.name.to_ascii_lowercase()];
+            for var in &def.value_references {
+                if let Some(&to) = index.get(&var.to_ascii_lowercase())
+                    && !edges[from].contains(&to)
+                {
+                    edges[from].push(to);
+                }
+            }
+        }
+
+        let mut cycles: Vec<Vec<String>> = strongly_connected(&edges)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| {
+                let mut members: Vec<String> =
+                    component.into_iter().map(|i| names[i].to_string()).collect();
+                members.sort_by_key(|n| n.to_ascii_lowercase());
+                members
+            })
+            .collect();
+        cycles.sort_by_key(|c| c[0].to_ascii_lowercase());
+        cycles
+    }
+}
+
+/// Tarjan's algorithm: the strongly connected components of a graph given
+/// as adjacency lists.
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        edges: &'a [Vec<usize>],
+        next: usize,
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(s: &mut State, v: usize) {
+        s.index[v] = Some(s.next);
+        s.low[v] = s.next;
+        s.next += 1;
+        s.stack.push(v);
+        s.on_stack[v] = true;
+
+        for &w in &s.edges[v] {
+            match s.index[w] {
+                None => {
+                    visit(s, w);
+                    s.low[v] = s.low[v].min(s.low[w]);
+                }
+                Some(wi) if s.on_stack[w] => s.low[v] = s.low[v].min(wi),
+                Some(_) => {}
+            }
+        }
+
+        if Some(s.low[v]) == s.index[v] {
+            let mut component = Vec::new();
+            while let Some(w) = s.stack.pop() {
+                s.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            s.components.push(component);
+        }
+    }
+
+    let n = edges.len();
+    let mut state = State {
+        edges,
+        next: 0,
+        index: vec![None; n],
+        low: vec![0; n],
+        stack: Vec::new(),
+        on_stack: vec![false; n],
+        components: Vec::new(),
+    };
+    for v in 0..n {
+        if state.index[v].is_none() {
+            visit(&mut state, v);
+        }
+    }
+    state.components
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CYCLE_DPROJ: &str = r#"<Project>
+        <PropertyGroup>
+            <Config>Debug</Config>
+            <Platform>Win32</Platform>
+            <OutRoot>$(OutDir)\..</OutRoot>
+            <OutDir>$(OutRoot)\$(Platform)</OutDir>
+            <DCC_Define>APP;$(DCC_Define)</DCC_Define>
+        </PropertyGroup>
+        <PropertyGroup Condition="'$(Cfg_1)'!=''">
+            <DCC_ExeOutput>$(OutDir)\$(Config)</DCC_ExeOutput>
+            <DCC_UnitSearchPath Condition="'$(UseLib)'=='true'">$(LIBROOT)\src</DCC_UnitSearchPath>
+        </PropertyGroup>
+        <ItemGroup>
+            <BuildConfiguration Include="Debug"><Key>Cfg_1</Key></BuildConfiguration>
+        </ItemGroup>
+        <Import Project="$(BDS)\Bin\CodeGear.Delphi.Targets" Condition="Exists('$(BDS)\Bin\CodeGear.Delphi.Targets')"/>
+    </Project>"#;
+
+    #[test]
+    fn definitions_record_value_and_condition_references() {
+        let graph = Dproj::parse(CYCLE_DPROJ).unwrap().property_graph();
+        let exe: Vec<_> = graph.definitions_of("dcc_exeoutput").collect();
+        assert_eq!(exe.len(), 1);
+        assert_eq!(exe[0].group, 1);
+        assert_eq!(exe[0].value_references, ["OutDir", "Config"]);
+        assert_eq!(exe[0].condition_references, ["Cfg_1"]);
+
+        let search: Vec<_> = graph.definitions_of("DCC_UnitSearchPath").collect();
+        assert_eq!(search[0].condition_references, ["Cfg_1", "UseLib"]);
+
+        // Self-references accumulate and are not dependencies.
+        assert!(graph.dependencies("DCC_Define").is_empty());
+        assert_eq!(graph.dependencies("OutDir"), ["OutRoot", "Platform"]);
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let graph = Dproj::parse(CYCLE_DPROJ).unwrap().property_graph();
+        assert_eq!(graph.cycles(), vec![vec!["OutDir".to_string(), "OutRoot".to_string()]]);
+    }
+
+    #[test]
+    fn external_variables_exclude_defined_and_supplied() {
+        let graph = Dproj::parse(CYCLE_DPROJ).unwrap().property_graph();
+        assert_eq!(graph.external_variables(), ["UseLib", "LIBROOT", "BDS"]);
+    }
+
+    #[test]
+    fn example_project_graph() {
+        let graph = Dproj::from_file("example.dproj").unwrap().property_graph();
+        assert!(graph.cycles().is_empty(), "{:?}", graph.cycles());
+        let external = graph.external_variables();
+        assert!(external.iter().any(|v| v == "BDS"), "{external:?}");
+        assert!(!external.iter().any(|v| v == "Base" || v == "Config"), "{external:?}");
+    }
+}
@@ -1,7 +1,21 @@
 pub mod condition;
+pub mod dcc;
 pub mod dproj;
+pub mod filesystem;
+pub mod functions;
+pub mod graph;
+pub mod list;
+pub mod local;
+pub mod optset;
+pub mod platform;
+pub mod provenance;
 pub mod rsvars;
+pub mod vars;
+pub mod warnings;
 
 pub use dproj::Dproj;
 pub use dproj::DprojBuilder;
-pub use rsvars::{parse_rsvars, parse_rsvars_file};
\ No newline at end of file
+pub use dproj::EvaluationMode;
+pub use platform::TargetPlatform;
+pub use rsvars::{parse_rsvars, parse_rsvars_file};
+pub use vars::VarMap;
\ No newline at end of file
@@ -0,0 +1,390 @@
+//! Semicolon-separated list properties.
+//!
+//! Defines, search paths, namespaces and package lists are stored as a
+//! single `;`-joined string.  A configuration usually adds to the value it
+//! inherits by referencing the property itself:
+//!
+//! ```xml
+//! <DCC_Define>DEBUG;LOGGING;$(DCC_Define)</DCC_Define>
+//! ```
+//!
+//! [`PropertyList`] splits such a value into its own entries (`DEBUG`,
+//! `LOGGING`) and the inherited reference (`$(DCC_Define)`), and edits it
+//! without disturbing the rest of the text.  [`Dproj::add_list_entry`],
+//! [`remove_list_entry`](crate::Dproj::remove_list_entry),
+//! [`move_list_entry`](crate::Dproj::move_list_entry) and
+//! [`dedup_list`](crate::Dproj::dedup_list) apply such edits to a
+//! `<PropertyGroup>` of the project file.
+//!
+//! Entries are compared ignoring ASCII case and surrounding whitespace, like
+//! Delphi compares defines, unit names and Windows paths.
+//!
+//! [`Dproj::add_list_entry`]: crate::Dproj::add_list_entry
+//! [`Dproj`]: crate::Dproj
+
+use crate::dproj::{BrccOptions, DccOptions};
+use crate::functions;
+
+/// The value of a `;`-separated list property.
+///
+/// The list keeps the items exactly as written, so that
+/// `PropertyList::parse(name, text).to_string() == text`.
+///
+/// # Example
+/// ```
+/// use dproj_rs::list::PropertyList;
+///
+/// let mut defines = PropertyList::parse("DCC_Define", "DEBUG;$(DCC_Define)");
+/// assert!(defines.inherits());
+/// assert!(defines.add("DEBUGLOG"));
+/// assert!(!defines.add("debuglog"));
+/// assert_eq!(defines.to_string(), "DEBUG;DEBUGLOG;$(DCC_Define)");
+/// assert_eq!(defines.entries().collect::<Vec<_>>(), ["DEBUG", "DEBUGLOG"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyList {
+    property: String,
+    /// The `;`-separated items as written, including empty ones and the
+    /// inherited reference.
+    items: Vec<String>,
+}
+
+impl PropertyList {
+    /// Split `text`, the value of `property`.  A `;` inside a `$(…)`
+    /// reference does not separate entries.
+    pub fn parse(property: impl Into<String>, text: &str) -> Self {
+        let items = split_items(text).into_iter().map(str::to_string).collect();
+        Self { property: property.into(), items }
+    }
+
+    /// An empty list for `property`.
+    pub fn new(property: impl Into<String>) -> Self {
+        Self::parse(property, "")
+    }
+
+    /// The property the list belongs to, e.g. `"DCC_Define"`.
+    pub fn property(&self) -> &str {
+        &self.property
+    }
+
+    /// The list's own entries, trimmed, in order; without empty items and
+    /// the inherited reference.
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.items
+            .iter()
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty() && !self.is_inherited(item))
+    }
+
+    /// Whether the list includes the value it inherits (`$(DCC_Define)` in
+    /// `DCC_Define`).
+    pub fn inherits(&self) -> bool {
+        self.items.iter().any(|item| self.is_inherited(item))
+    }
+
+    /// Whether the list has no own entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries().next().is_none()
+    }
+
+    pub fn contains(&self, entry: &str) -> bool {
+        self.position(entry).is_some()
+    }
+
+    /// Append `entries`, one entry or several separated by `;`, to the own
+    /// entries, before the inherited reference.  Entries already in the
+    /// list are skipped.  Returns whether anything was added.
+    pub fn add(&mut self, entries: &str) -> bool {
+        let mut added = false;
+        for entry in split(entries) {
+            added |= self.add_one(entry);
+        }
+        added
+    }
+
+    fn add_one(&mut self, entry: &str) -> bool {
+        if self.contains(entry) {
+            return false;
+        }
+        let at = match self.items.iter().position(|item| self.is_inherited(item)) {
+            Some(inherited) => inherited,
+            None => self.items.iter().rposition(|item| !item.trim().is_empty()).map_or(0, |i| i + 1),
+        };
+        if self.items.len() == 1 && self.items[0].trim().is_empty() {
+            self.items[0] = entry.to_string();
+        } else {
+            self.items.insert(at, entry.to_string());
+        }
+        true
+    }
+
+    /// Remove every occurrence of `entries`, one entry or several separated
+    /// by `;`.  Returns whether any of them was present.
+    pub fn remove(&mut self, entries: &str) -> bool {
+        let entries = split(entries);
+        let before = self.items.len();
+        self.items
+            .retain(|item| !entries.iter().any(|entry| same_entry(item, entry)));
+        if self.items.is_empty() {
+            self.items.push(String::new());
+        }
+        self.items.len() != before
+    }
+
+    /// Move `entry` to `index` among the own entries (clamped to the last
+    /// position).  The inherited reference stays where it is.  Returns
+    /// `false` if `entry` is not in the list.
+    pub fn move_entry(&mut self, entry: &str, index: usize) -> bool {
+        let Some(from) = self.position(entry) else {
+            return false;
+        };
+        let item = self.items.remove(from);
+        let own: Vec<usize> = (0..self.items.len()).filter(|&i| self.is_own(i)).collect();
+        let to = match own.get(index) {
+            Some(&i) => i,
+            None => own.last().map_or(from.min(self.items.len()), |&i| i + 1),
+        };
+        self.items.insert(to, item);
+        true
+    }
+
+    /// Remove repeated entries, keeping the first occurrence of each.
+    /// Returns whether anything was removed.
+    pub fn dedup(&mut self) -> bool {
+        let mut seen: Vec<String> = Vec::new();
+        let before = self.items.len();
+        self.items.retain(|item| {
+            let item = item.trim();
+            if item.is_empty() {
+                return true;
+            }
+            let duplicate = seen.iter().any(|s| s.eq_ignore_ascii_case(item));
+            if !duplicate {
+                seen.push(item.to_string());
+            }
+            !duplicate
+        });
+        self.items.len() != before
+    }
+
+    /// Index in `items` of the first item equal to `entry`.
+    fn position(&self, entry: &str) -> Option<usize> {
+        self.items.iter().position(|item| same_entry(item, entry))
+    }
+
+    /// Whether `items[index]` is an own entry.
+    fn is_own(&self, index: usize) -> bool {
+        let item = &self.items[index];
+        !item.trim().is_empty() && !self.is_inherited(item)
+    }
+
+    fn is_inherited(&self, item: &str) -> bool {
+        item.trim()
+            .strip_prefix("$(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .is_some_and(|name| name.trim().eq_ignore_ascii_case(&self.property))
+    }
+}
+
+impl std::fmt::Display for PropertyList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.items.join(";"))
+    }
+}
+
+/// The `;`-separated items of `text`, including empty ones.  A `;` inside
+/// a `$(…)` reference does not separate items.
+fn split_items(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let bytes = text.as_bytes();
+    let (mut start, mut i) = (0, 0);
+    while i < bytes.len() {
+        if bytes[i] == b'$'
+            && let Some(len) = functions::property_len(&text[i..])
+        {
+            i += len;
+            continue;
+        }
+        if bytes[i] == b';' {
+            items.push(&text[start..i]);
+            start = i + 1;
+        }
+        i += 1;
+    }
+    items.push(&text[start..]);
+    items
+}
+
+/// The non-empty items of `text`.
+fn split(text: &str) -> Vec<&str> {
+    let mut entries = split_items(text);
+    entries.retain(|entry| !entry.trim().is_empty());
+    entries
+}
+
+/// Whether the list item `item` is `entry`.
+fn same_entry(item: &str, entry: &str) -> bool {
+    let item = item.trim();
+    !item.is_empty() && item.eq_ignore_ascii_case(entry.trim())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Accessors
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Generate a `PropertyList` getter for each `method: field "Tag"` line.
+macro_rules! list_properties {
+    ($ty:ty { $($method:ident: $field:ident $tag:literal;)+ }) => {
+        impl $ty {
+            $(
+                #[doc = concat!("`", $tag, "` as a [`PropertyList`]; empty when not set.")]
+                pub fn $method(&self) -> PropertyList {
+                    PropertyList::parse($tag, self.$field.as_deref().unwrap_or(""))
+                }
+            )+
+        }
+    };
+}
+
+list_properties!(DccOptions {
+    define_list: define "DCC_Define";
+    namespace_list: namespace "DCC_Namespace";
+    unit_alias_list: unit_alias "DCC_UnitAlias";
+    use_package_list: use_package "DCC_UsePackage";
+    unit_search_path_list: unit_search_path "DCC_UnitSearchPath";
+    resource_path_list: resource_path "DCC_ResourcePath";
+    include_path_list: include_path "DCC_IncludePath";
+    obj_path_list: obj_path "DCC_ObjPath";
+    framework_path_list: framework_path "DCC_FrameworkPath";
+});
+
+list_properties!(BrccOptions {
+    defines_list: defines "BRCC_Defines";
+    include_path_list: include_path "BRCC_IncludePath";
+});
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dproj;
+
+    fn entries(list: &PropertyList) -> Vec<&str> {
+        list.entries().collect()
+    }
+
+    #[test]
+    fn parse_keeps_text_and_splits_entries() {
+        for text in ["", "A", "A;B;", " A ; B;$(DCC_Define)", ";;A;;", "$(X.Replace(';', ','));B"] {
+            assert_eq!(PropertyList::parse("DCC_Define", text).to_string(), text);
+        }
+        let list = PropertyList::parse("DCC_Define", " A ; B;;$(dcc_define)");
+        assert_eq!(entries(&list), ["A", "B"]);
+        assert!(list.inherits());
+
+        let paths = PropertyList::parse("DCC_UnitSearchPath", "$(BDS)\\lib;$(X.Replace(';', ','))");
+        assert_eq!(entries(&paths), ["$(BDS)\\lib", "$(X.Replace(';', ','))"]);
+        assert!(!paths.inherits());
+        assert!(PropertyList::new("DCC_Define").is_empty());
+    }
+
+    #[test]
+    fn add_remove_and_move() {
+        let mut list = PropertyList::parse("DCC_Define", "A;B;$(DCC_Define)");
+        assert!(list.add("C"));
+        assert_eq!(list.to_string(), "A;B;C;$(DCC_Define)");
+        assert!(list.move_entry("c", 0));
+        assert_eq!(list.to_string(), "C;A;B;$(DCC_Define)");
+        assert!(list.move_entry("C", 99));
+        assert_eq!(list.to_string(), "A;B;C;$(DCC_Define)");
+        assert!(list.remove(" b "));
+        assert!(!list.remove("B"));
+        assert_eq!(list.to_string(), "A;C;$(DCC_Define)");
+        assert!(!list.move_entry("Z", 0));
+
+        let mut trailing = PropertyList::parse("DCC_Namespace", "System;Vcl;");
+        trailing.add("Data");
+        assert_eq!(trailing.to_string(), "System;Vcl;Data;");
+
+        let mut empty = PropertyList::new("DCC_Define");
+        empty.add("DEBUG");
+        assert_eq!(empty.to_string(), "DEBUG");
+        empty.remove("DEBUG");
+        assert_eq!(empty.to_string(), "");
+    }
+
+    #[test]
+    fn several_entries_at_once() {
+        let mut list = PropertyList::parse("DCC_Define", "A;$(DCC_Define)");
+        assert!(list.add("B; a ;C;"));
+        assert_eq!(list.to_string(), "A;B;C;$(DCC_Define)");
+        assert!(!list.add("b;c"));
+        assert!(list.add("$(X.Replace(';', ','))"));
+        assert_eq!(entries(&list), ["A", "B", "C", "$(X.Replace(';', ','))"]);
+        assert!(list.remove("a;c;Z"));
+        assert_eq!(list.to_string(), "B;$(X.Replace(';', ','));$(DCC_Define)");
+        assert!(!list.remove(";"));
+    }
+
+    #[test]
+    fn dedup_keeps_first_occurrence() {
+        let mut list = PropertyList::parse("DCC_Define", "A;b;B;a;$(DCC_Define);C");
+        assert!(list.dedup());
+        assert_eq!(list.to_string(), "A;b;$(DCC_Define);C");
+        assert!(!list.dedup());
+    }
+
+    #[test]
+    fn options_accessors() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let pg = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        let defines = pg.dcc_options.define_list();
+        assert!(defines.contains("DEBUG"));
+        assert!(!defines.inherits(), "the effective value is expanded");
+        assert_eq!(DccOptions::default().define_list().entries().count(), 0);
+    }
+
+    #[test]
+    fn dproj_edits_preserve_the_rest_of_the_file() {
+        let source = std::fs::read_to_string("example.dproj").unwrap();
+        let mut dproj = Dproj::parse(source.clone()).unwrap();
+        let index = dproj
+            .project
+            .property_groups
+            .iter()
+            .position(|pg| pg.condition.as_deref() == Some("'$(Cfg_1)'!=''"))
+            .unwrap();
+
+        assert!(dproj.add_list_entry(index, "DCC_Define", "DEBUGLOG").unwrap());
+        assert!(!dproj.add_list_entry(index, "DCC_Define", "debuglog").unwrap());
+        let expected = source.replace(
+            "<DCC_Define>DEBUG;$(DCC_Define)</DCC_Define>",
+            "<DCC_Define>DEBUG;DEBUGLOG;$(DCC_Define)</DCC_Define>",
+        );
+        assert_eq!(dproj.source(), expected);
+        let list = dproj.property_list(index, "DCC_Define").unwrap();
+        assert_eq!(entries(&list), ["DEBUG", "DEBUGLOG"]);
+        let debug = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        assert!(debug.dcc_options.define_list().contains("DEBUGLOG"));
+
+        assert!(dproj.move_list_entry(index, "DCC_Define", "DEBUGLOG", 0).unwrap());
+        assert!(dproj.source().contains("<DCC_Define>DEBUGLOG;DEBUG;$(DCC_Define)</DCC_Define>"));
+        assert!(dproj.remove_list_entry(index, "DCC_Define", "DEBUGLOG").unwrap());
+        assert_eq!(dproj.source(), source);
+
+        assert!(!dproj.remove_list_entry(index, "DCC_Namespace", "Vcl").unwrap());
+        assert!(dproj.add_list_entry(index, "DCC_Namespace", "Vcl").unwrap());
+        assert!(dproj.source().contains("<DCC_Namespace>Vcl</DCC_Namespace>"));
+        assert!(dproj.add_list_entry(index, "DCC_Namespace", "System;vcl;Vcl.Imaging").unwrap());
+        assert!(!dproj.dedup_list(index, "DCC_Namespace").unwrap());
+        dproj.set_property_value(index, "DCC_Namespace", "Vcl;System;vcl").unwrap();
+        assert!(dproj.dedup_list(index, "DCC_Namespace").unwrap());
+        assert!(dproj.source().contains("<DCC_Namespace>Vcl;System</DCC_Namespace>"));
+
+        let err = dproj.add_list_entry(999, "DCC_Define", "X").unwrap_err();
+        assert!(matches!(err, crate::dproj::DprojError::IndexOutOfBounds { .. }));
+    }
+}
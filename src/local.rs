@@ -0,0 +1,200 @@
+//! The per-user `.dproj.local` file.
+//!
+//! The IDE keeps user-specific settings next to the project in
+//! `Project1.dproj.local`: debugger settings such as `Debugger_RunParams` and
+//! `Debugger_HostApplication` in conditional `<PropertyGroup>`s, and the
+//! history of files added to, removed from or renamed in the project as
+//! `<Transaction>` entries.  The CodeGear targets import it after the
+//! project's own groups, so its values take precedence.
+//!
+//! Older IDE versions write a bare `<BorlandProject>` root holding only the
+//! transactions; newer ones an MSBuild `<Project>`:
+//!
+//! ```xml
+//! <Project xmlns="http://schemas.microsoft.com/developer/msbuild/2003">
+//!     <PropertyGroup Condition="'$(Cfg_1_Win32)'!=''">
+//!         <Debugger_RunParams>--verbose</Debugger_RunParams>
+//!     </PropertyGroup>
+//!     <ProjectExtensions>
+//!         <BorlandProject>
+//!             <Transactions>
+//!                 <Transaction>2024/05/02 09:14:51.000.417,=C:\src\Unit2.pas</Transaction>
+//!             </Transactions>
+//!         </BorlandProject>
+//!     </ProjectExtensions>
+//! </Project>
+//! ```
+//!
+//! Load it with [`DprojBuilder::local_file`](crate::DprojBuilder::local_file).
+
+use crate::dproj::{DprojError, DprojProject, PropertyGroup};
+
+/// The contents of a `.dproj.local` file.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct DprojLocal {
+    #[cfg_attr(feature = "serde", serde(rename = "PropertyGroup"))]
+    pub property_groups: Vec<PropertyGroup>,
+    /// File history, in document order.
+    #[cfg_attr(feature = "serde", serde(rename = "Transaction"))]
+    pub transactions: Vec<Transaction>,
+}
+
+impl DprojLocal {
+    /// Parse a `.dproj.local` file from its XML source.
+    pub fn parse(source: &str) -> Result<Self, DprojError> {
+        let doc = roxmltree::Document::parse(source)?;
+        let root = doc.root_element();
+        let property_groups = if root.tag_name().name() == "Project" {
+            DprojProject::parse(root)?.property_groups
+        } else {
+            Vec::new()
+        };
+        let transactions = root
+            .descendants()
+            .filter(|n| n.is_element() && n.tag_name().name() == "Transaction")
+            .filter_map(|n| Transaction::parse(n.text().unwrap_or("")))
+            .collect();
+        Ok(Self { property_groups, transactions })
+    }
+
+    /// Load a `.dproj.local` file from disk.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, DprojError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| DprojError::from(e).in_file(path))?;
+        Self::parse(&source).map_err(|e| e.in_file(path))
+    }
+}
+
+/// A `<Transaction>` entry: `timestamp,old=new`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "PascalCase"))]
+pub struct Transaction {
+    /// When the change was made, as written (`"2024/05/02 09:14:51.000.417"`).
+    pub timestamp: String,
+    /// The file before the change; `None` when it was added.
+    pub old_path: Option<String>,
+    /// The file after the change; `None` when it was removed.
+    pub new_path: Option<String>,
+}
+
+/// What a [`Transaction`] did to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransactionKind {
+    Added,
+    Removed,
+    Renamed,
+}
+
+impl Transaction {
+    /// Parse the text of a `<Transaction>` element.  `None` if it is not of
+    /// the form `timestamp,old=new` with at least one path.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (timestamp, change) = text.trim().split_once(',')?;
+        let (old, new) = change.split_once('=')?;
+        let path = |p: &str| Some(p.trim()).filter(|p| !p.is_empty()).map(String::from);
+        let (old_path, new_path) = (path(old), path(new));
+        if old_path.is_none() && new_path.is_none() {
+            return None;
+        }
+        Some(Self { timestamp: timestamp.trim().to_string(), old_path, new_path })
+    }
+
+    pub fn kind(&self) -> TransactionKind {
+        match (&self.old_path, &self.new_path) {
+            (None, _) => TransactionKind::Added,
+            (_, None) => TransactionKind::Removed,
+            _ => TransactionKind::Renamed,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DprojBuilder;
+    use crate::filesystem::MemoryFileSystem;
+
+    const LOCAL: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Project xmlns="http://schemas.microsoft.com/developer/msbuild/2003">
+    <PropertyGroup Condition="'$(Cfg_1_Win32)'!=''">
+        <Debugger_RunParams>--verbose $(Config)</Debugger_RunParams>
+        <Debugger_HostApplication>C:\Tools\Host.exe</Debugger_HostApplication>
+    </PropertyGroup>
+    <ProjectExtensions>
+        <BorlandProject>
+            <Transactions>
+                <Transaction>2024/05/02 09:14:51.000.417,=C:\src\Unit2.pas</Transaction>
+                <Transaction>2024/05/02 09:15:02.000.118,C:\src\Unit2.pas=C:\src\Main.pas</Transaction>
+                <Transaction>2024/05/03 16:40:10.000.905,C:\src\Old.pas=</Transaction>
+            </Transactions>
+        </BorlandProject>
+    </ProjectExtensions>
+</Project>"#;
+
+    #[test]
+    fn parses_groups_and_transactions() {
+        let local = DprojLocal::parse(LOCAL).unwrap();
+        assert_eq!(local.property_groups.len(), 1);
+        let kinds: Vec<_> = local.transactions.iter().map(Transaction::kind).collect();
+        assert_eq!(
+            kinds,
+            [TransactionKind::Added, TransactionKind::Renamed, TransactionKind::Removed]
+        );
+        assert_eq!(local.transactions[1].timestamp, "2024/05/02 09:15:02.000.118");
+        assert_eq!(local.transactions[1].new_path.as_deref(), Some(r"C:\src\Main.pas"));
+    }
+
+    #[test]
+    fn parses_legacy_borland_project_root() {
+        let local = DprojLocal::parse(
+            "<BorlandProject><Transactions>\
+             <Transaction>1899/12/30 00:00:00.000.000,=Unit1.pas</Transaction>\
+             <Transaction>malformed</Transaction>\
+             </Transactions></BorlandProject>",
+        )
+        .unwrap();
+        assert!(local.property_groups.is_empty());
+        assert_eq!(local.transactions.len(), 1);
+        assert_eq!(local.transactions[0].new_path.as_deref(), Some("Unit1.pas"));
+    }
+
+    #[test]
+    fn local_file_overrides_project_settings() {
+        let path = std::path::Path::new("example.dproj").canonicalize().unwrap();
+        let local_path = format!("{}.local", path.display());
+        let fs = MemoryFileSystem::new().with_contents(&local_path, LOCAL);
+        let dproj = DprojBuilder::new()
+            .file_system(fs.clone())
+            .local_file(true)
+            .from_file(&path)
+            .unwrap();
+        assert_eq!(dproj.local().unwrap().transactions.len(), 3);
+
+        let debug = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        assert_eq!(debug.debugger_options.run_params.as_deref(), Some("--verbose Debug"));
+        assert_eq!(debug.debugger_options.host_application.as_deref(), Some(r"C:\Tools\Host.exe"));
+        let provenance = dproj.property_provenance_for("Debug", "Win32").unwrap();
+        let origin = provenance.get("Debugger_RunParams").unwrap().origin();
+        assert_eq!(origin.file.as_deref(), Some(std::path::Path::new(&local_path)));
+
+        let release = dproj.active_property_group_for("Release", "Win32").unwrap();
+        assert_eq!(release.debugger_options.run_params, None);
+
+        // Not loaded unless asked for, and optional when asked for.
+        let plain = DprojBuilder::new().file_system(fs).from_file(&path).unwrap();
+        assert!(plain.local().is_none());
+        let missing = DprojBuilder::new()
+            .file_system(MemoryFileSystem::new())
+            .local_file(true)
+            .from_file(&path)
+            .unwrap();
+        assert!(missing.local().is_none());
+    }
+}
@@ -0,0 +1,256 @@
+//! RAD Studio option sets (`.optset` files).
+//!
+//! An option set is an MSBuild file holding a `<PropertyGroup>` of compiler
+//! and linker settings that many projects share.  The IDE attaches it to a
+//! build configuration with two edits to the `.dproj`: an `<Import>` placed
+//! just before the configuration's `<PropertyGroup>`, and a
+//! `<CfgDependentOn>` element inside that group:
+//!
+//! ```xml
+//! <Import Project="..\shared\Release.optset" Condition="'$(Cfg_2)'!='' And Exists('..\shared\Release.optset')"/>
+//! <PropertyGroup Condition="'$(Cfg_2)'!=''">
+//!     <CfgDependentOn>..\shared\Release.optset</CfgDependentOn>
+//!     ...
+//! ```
+//!
+//! Since the import comes first, values set by the configuration itself
+//! override the option set, which in turn overrides the configurations it
+//! inherits from (`Base`).  [`Dproj::option_sets`](crate::Dproj::option_sets),
+//! [`attach_option_set`](crate::Dproj::attach_option_set) and
+//! [`detach_option_set`](crate::Dproj::detach_option_set) list and edit
+//! these attachments.
+
+use crate::dproj::{DprojError, DprojProject, PropertyGroup};
+
+/// The contents of an `.optset` file.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct OptionSet {
+    #[cfg_attr(feature = "serde", serde(rename = "PropertyGroup"))]
+    pub property_groups: Vec<PropertyGroup>,
+}
+
+impl OptionSet {
+    /// Parse an option set from its XML source.
+    pub fn parse(source: &str) -> Result<Self, DprojError> {
+        let doc = roxmltree::Document::parse(source)?;
+        let project = DprojProject::parse(doc.root_element())?;
+        Ok(Self { property_groups: project.property_groups })
+    }
+
+    /// Load an option set from disk.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, DprojError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| DprojError::from(e).in_file(path))?;
+        Self::parse(&source).map_err(|e| e.in_file(path))
+    }
+
+    /// The settings of the option set: its unconditional `<PropertyGroup>`s
+    /// merged in document order, values as written.
+    pub fn property_group(&self) -> PropertyGroup {
+        let mut merged = PropertyGroup::default();
+        for pg in self.property_groups.iter().filter(|pg| pg.condition.is_none()) {
+            merged.merge_from(pg);
+        }
+        merged
+    }
+}
+
+/// An option set attached to a build configuration, as listed by
+/// [`Dproj::option_sets`](crate::Dproj::option_sets).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "PascalCase"))]
+pub struct AttachedOptionSet {
+    /// Name of the build configuration, e.g. `"Release"`.
+    pub configuration: String,
+    /// The platform, for an option set attached to a single platform of the
+    /// configuration.
+    pub platform: Option<String>,
+    /// The variable the import is conditioned on, e.g. `"Cfg_2"` or
+    /// `"Cfg_2_Win64"`.
+    pub key: String,
+    /// The `Project` path of the `<Import>`, as written.
+    pub path: String,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use crate::{Dproj, DprojBuilder};
+
+    const SOURCE: &str = r#"<Project>
+    <PropertyGroup>
+        <Config Condition="'$(Config)'==''">Debug</Config>
+        <Platform Condition="'$(Platform)'==''">Win32</Platform>
+    </PropertyGroup>
+    <PropertyGroup Condition="'$(Base)'!=''">
+        <DCC_Optimize>true</DCC_Optimize>
+        <DCC_Define>BASE;$(DCC_Define)</DCC_Define>
+    </PropertyGroup>
+    <PropertyGroup Condition="'$(Cfg_1)'!=''">
+        <DCC_Define>DEBUG;$(DCC_Define)</DCC_Define>
+    </PropertyGroup>
+    <PropertyGroup Condition="'$(Cfg_2)'!=''">
+        <DCC_RangeChecking>false</DCC_RangeChecking>
+        <DCC_Define>RELEASE;$(DCC_Define)</DCC_Define>
+    </PropertyGroup>
+    <PropertyGroup Condition="'$(Cfg_2_Win64)'!=''">
+        <DCC_ImageBase>00400000</DCC_ImageBase>
+    </PropertyGroup>
+    <ItemGroup>
+        <BuildConfiguration Include="Base"><Key>Base</Key></BuildConfiguration>
+        <BuildConfiguration Include="Debug"><Key>Cfg_1</Key><CfgParent>Base</CfgParent></BuildConfiguration>
+        <BuildConfiguration Include="Release"><Key>Cfg_2</Key><CfgParent>Base</CfgParent></BuildConfiguration>
+    </ItemGroup>
+</Project>"#;
+
+    const RELEASE_OPTSET: &str = r#"<Project>
+        <PropertyGroup>
+            <DCC_Optimize>false</DCC_Optimize>
+            <DCC_RangeChecking>true</DCC_RangeChecking>
+            <DCC_Define>SHIPPING;$(DCC_Define)</DCC_Define>
+        </PropertyGroup>
+    </Project>"#;
+
+    fn parse(source: &str) -> Dproj {
+        let fs = MemoryFileSystem::new().with_contents(r"shared\Release.optset", RELEASE_OPTSET);
+        DprojBuilder::new().file_system(fs).parse(source).unwrap()
+    }
+
+    #[test]
+    fn attach_inserts_import_and_dependency() {
+        let mut dproj = parse(SOURCE);
+        dproj.attach_option_set("Release", None, r"shared\Release.optset").unwrap();
+        let expected = SOURCE.replace(
+            "    <PropertyGroup Condition=\"'$(Cfg_2)'!=''\">\n",
+            "    <Import Project=\"shared\\Release.optset\" Condition=\"'$(Cfg_2)'!='' And Exists('shared\\Release.optset')\"/>\n    \
+             <PropertyGroup Condition=\"'$(Cfg_2)'!=''\">\n        \
+             <CfgDependentOn>shared\\Release.optset</CfgDependentOn>\n",
+        );
+        assert_eq!(dproj.source(), expected);
+        assert_eq!(
+            dproj.option_sets(),
+            [AttachedOptionSet {
+                configuration: "Release".into(),
+                platform: None,
+                key: "Cfg_2".into(),
+                path: r"shared\Release.optset".into(),
+            }]
+        );
+        assert!(dproj.option_sets_for("Debug").is_empty());
+
+        let err = dproj.attach_option_set("Release", None, r"SHARED\release.optset").unwrap_err();
+        assert!(matches!(err, DprojError::OptionSetAttached { .. }), "{err}");
+        for path in [r"O'Brien\Release.optset", "a.optset;b.optset"] {
+            let err = dproj.attach_option_set("Release", None, path).unwrap_err();
+            assert!(matches!(err, DprojError::InvalidValue(_)), "{err}");
+        }
+        assert_eq!(dproj.option_sets().len(), 1);
+        let err = dproj.attach_option_set("Staging", None, "x.optset").unwrap_err();
+        assert!(matches!(&err, DprojError::ConfigurationNotFound { name } if name == "Staging"));
+        assert_eq!(err.to_string(), "Build configuration 'Staging' not found");
+    }
+
+    #[test]
+    fn option_set_values_take_precedence_between_base_and_configuration() {
+        let mut dproj = parse(SOURCE);
+        dproj.attach_option_set("Release", None, r"shared\Release.optset").unwrap();
+
+        let release = dproj.active_property_group_for("Release", "Win32").unwrap();
+        // Overrides Base…
+        assert_eq!(release.dcc_options.optimize.as_deref(), Some("false"));
+        // …but not the configuration itself.
+        assert_eq!(release.dcc_options.range_checking.as_deref(), Some("false"));
+        assert_eq!(release.dcc_options.define.as_deref(), Some("RELEASE;SHIPPING;BASE;"));
+
+        let debug = dproj.active_property_group_for("Debug", "Win32").unwrap();
+        assert_eq!(debug.dcc_options.optimize.as_deref(), Some("true"));
+
+        let attached = &dproj.option_sets()[0];
+        let option_set = dproj.load_option_set(attached).unwrap();
+        assert_eq!(option_set.property_group().dcc_options.optimize.as_deref(), Some("false"));
+    }
+
+    #[test]
+    fn unreadable_option_sets_are_skipped() {
+        let mut dproj = parse(SOURCE);
+        dproj.attach_option_set("Release", None, r"..\shared\Release.optset").unwrap();
+
+        // `Dproj::parse` assumes the file exists but cannot read it.
+        let parsed = Dproj::parse(dproj.source()).unwrap();
+        let release = parsed.active_property_group_for("Release", "Win32").unwrap();
+        assert_eq!(release.dcc_options.optimize.as_deref(), Some("true"));
+        assert!(parsed.active_property_group().is_ok());
+
+        // A missing file is skipped even without an `Exists` guard.
+        let unguarded = dproj
+            .source()
+            .replace(r" And Exists('..\shared\Release.optset')", "");
+        let missing = DprojBuilder::new()
+            .file_system(MemoryFileSystem::new())
+            .parse(unguarded)
+            .unwrap();
+        let release = missing.active_property_group_for("Release", "Win32").unwrap();
+        assert_eq!(release.dcc_options.optimize.as_deref(), Some("true"));
+    }
+
+    #[test]
+    fn detach_restores_the_source() {
+        let mut dproj = parse(SOURCE);
+        dproj.attach_option_set("Release", Some("Win64"), r"shared\Release.optset").unwrap();
+        let attached = dproj.option_sets_for("Release");
+        assert_eq!(attached[0].platform.as_deref(), Some("Win64"));
+        assert_eq!(attached[0].key, "Cfg_2_Win64");
+        let win64 = dproj.active_property_group_for("Release", "Win64").unwrap();
+        assert_eq!(win64.dcc_options.optimize.as_deref(), Some("false"));
+
+        dproj.detach_option_set("Release", Some("Win64"), r"shared\Release.optset").unwrap();
+        assert_eq!(dproj.source(), SOURCE);
+        assert!(dproj.option_sets().is_empty());
+
+        let err = dproj.detach_option_set("Release", None, r"shared\Release.optset").unwrap_err();
+        assert!(matches!(err, DprojError::OptionSetNotAttached { .. }), "{err}");
+    }
+
+    #[test]
+    fn detach_keeps_other_dependencies() {
+        let mut dproj = parse(SOURCE);
+        dproj.attach_option_set("Release", None, "a.optset").unwrap();
+        dproj.attach_option_set("Release", None, "b.optset").unwrap();
+        assert!(dproj.source().contains("<CfgDependentOn>a.optset;b.optset</CfgDependentOn>"));
+        assert_eq!(dproj.option_sets().len(), 2);
+
+        dproj.detach_option_set("Release", None, "a.optset").unwrap();
+        assert!(dproj.source().contains("<CfgDependentOn>b.optset</CfgDependentOn>"));
+        assert!(!dproj.source().contains("a.optset"));
+        assert_eq!(dproj.option_sets()[0].path, "b.optset");
+    }
+
+    #[test]
+    fn parses_option_set_file() {
+        let source = r#"<Project xmlns="http://schemas.microsoft.com/developer/msbuild/2003">
+            <PropertyGroup>
+                <DCC_Optimize>true</DCC_Optimize>
+                <DCC_Define>SHIPPING;$(DCC_Define)</DCC_Define>
+            </PropertyGroup>
+            <PropertyGroup Condition="'$(Platform)'=='Win64'">
+                <DCC_Optimize>false</DCC_Optimize>
+            </PropertyGroup>
+            <ProjectExtensions>
+                <Borland.Personality>Delphi.Personality.12</Borland.Personality>
+                <Borland.ProjectType>OptionSet</Borland.ProjectType>
+            </ProjectExtensions>
+        </Project>"#;
+        let option_set = OptionSet::parse(source).unwrap();
+        assert_eq!(option_set.property_groups.len(), 2);
+        let pg = option_set.property_group();
+        assert_eq!(pg.dcc_options.optimize.as_deref(), Some("true"));
+        assert_eq!(pg.dcc_options.define.as_deref(), Some("SHIPPING;$(DCC_Define)"));
+    }
+}
@@ -0,0 +1,247 @@
+//! RAD Studio target platforms.
+//!
+//! A project names its platforms as strings (`<Platform value="Win64">`,
+//! `'$(Platform)'=='Win64'`) and summarises the enabled ones in the
+//! `<TargetedPlatforms>` bitmask of its first `<PropertyGroup>`:
+//!
+//! ```xml
+//! <TargetedPlatforms>3</TargetedPlatforms>   <!-- Win32 | Win64 -->
+//! ```
+//!
+//! [`TargetPlatform`] lists the platforms supported by current RAD Studio
+//! versions with their operating system and architecture, and converts
+//! between platform names, bits and bitmasks.  The bits are the RTL's
+//! `pid*` platform identifiers (`pidWin32 = $0001`, `pidWin64 = $0002`, …).
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A platform a Delphi project can be compiled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetPlatform {
+    Win32,
+    Win64,
+    /// 64-bit Windows with the Clang-based "modern" toolchain.
+    Win64x,
+    Linux64,
+    OSX64,
+    OSXARM64,
+    #[cfg_attr(feature = "serde", serde(rename = "iOSDevice64"))]
+    IOSDevice64,
+    #[cfg_attr(feature = "serde", serde(rename = "iOSSimARM64"))]
+    IOSSimARM64,
+    /// 32-bit ARM Android.
+    Android,
+    Android64,
+}
+
+/// Operating system of a [`TargetPlatform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetOs {
+    Windows,
+    Linux,
+    MacOs,
+    Ios,
+    Android,
+}
+
+/// CPU architecture of a [`TargetPlatform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Architecture {
+    X86,
+    X64,
+    Arm32,
+    Arm64,
+}
+
+impl TargetPlatform {
+    /// Every platform, in bit order.
+    pub const ALL: [TargetPlatform; 10] = [
+        Self::Win32,
+        Self::Win64,
+        Self::Android,
+        Self::Linux64,
+        Self::IOSDevice64,
+        Self::OSX64,
+        Self::Android64,
+        Self::OSXARM64,
+        Self::IOSSimARM64,
+        Self::Win64x,
+    ];
+
+    /// The name used in `.dproj` files, e.g. `"iOSDevice64"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Win32 => "Win32",
+            Self::Win64 => "Win64",
+            Self::Win64x => "Win64x",
+            Self::Linux64 => "Linux64",
+            Self::OSX64 => "OSX64",
+            Self::OSXARM64 => "OSXARM64",
+            Self::IOSDevice64 => "iOSDevice64",
+            Self::IOSSimARM64 => "iOSSimARM64",
+            Self::Android => "Android",
+            Self::Android64 => "Android64",
+        }
+    }
+
+    /// The platform's bit in `<TargetedPlatforms>`.
+    pub fn bit(self) -> u32 {
+        match self {
+            Self::Win32 => 0x0000_0001,
+            Self::Win64 => 0x0000_0002,
+            Self::Android => 0x0000_0010,
+            Self::Linux64 => 0x0000_0080,
+            Self::IOSDevice64 => 0x0000_0400,
+            Self::OSX64 => 0x0000_1000,
+            Self::Android64 => 0x0000_8000,
+            Self::OSXARM64 => 0x0002_0000,
+            Self::IOSSimARM64 => 0x0008_0000,
+            Self::Win64x => 0x0010_0000,
+        }
+    }
+
+    pub fn os(self) -> TargetOs {
+        match self {
+            Self::Win32 | Self::Win64 | Self::Win64x => TargetOs::Windows,
+            Self::Linux64 => TargetOs::Linux,
+            Self::OSX64 | Self::OSXARM64 => TargetOs::MacOs,
+            Self::IOSDevice64 | Self::IOSSimARM64 => TargetOs::Ios,
+            Self::Android | Self::Android64 => TargetOs::Android,
+        }
+    }
+
+    pub fn architecture(self) -> Architecture {
+        match self {
+            Self::Win32 => Architecture::X86,
+            Self::Win64 | Self::Win64x | Self::Linux64 | Self::OSX64 => Architecture::X64,
+            Self::Android => Architecture::Arm32,
+            Self::OSXARM64 | Self::IOSDevice64 | Self::IOSSimARM64 | Self::Android64 => {
+                Architecture::Arm64
+            }
+        }
+    }
+
+    /// Whether the platform is a simulator rather than a device.
+    pub fn is_simulator(self) -> bool {
+        self == Self::IOSSimARM64
+    }
+
+    /// Decode a `<TargetedPlatforms>` bitmask, in bit order.  Bits of
+    /// platforms no longer supported (`OSX32`, `iOSDevice32`, …) are
+    /// ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use dproj_rs::platform::TargetPlatform;
+    ///
+    /// assert_eq!(
+    ///     TargetPlatform::from_bitmask(3),
+    ///     [TargetPlatform::Win32, TargetPlatform::Win64]
+    /// );
+    /// ```
+    pub fn from_bitmask(mask: u32) -> Vec<TargetPlatform> {
+        Self::ALL.into_iter().filter(|p| mask & p.bit() != 0).collect()
+    }
+
+    /// Encode platforms as a `<TargetedPlatforms>` bitmask.
+    pub fn to_bitmask(platforms: impl IntoIterator<Item = TargetPlatform>) -> u32 {
+        platforms.into_iter().fold(0, |mask, p| mask | p.bit())
+    }
+}
+
+impl fmt::Display for TargetPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A platform name that is not a [`TargetPlatform`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPlatform(pub String);
+
+impl fmt::Display for UnknownPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown platform '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPlatform {}
+
+impl FromStr for TargetPlatform {
+    type Err = UnknownPlatform;
+
+    /// Parse a platform name, ignoring ASCII case like MSBuild does.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| UnknownPlatform(s.to_string()))
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for platform in TargetPlatform::ALL {
+            assert_eq!(platform.to_string().parse::<TargetPlatform>(), Ok(platform));
+        }
+        assert_eq!("iosdevice64".parse(), Ok(TargetPlatform::IOSDevice64));
+        assert_eq!(
+            "OSX32".parse::<TargetPlatform>().unwrap_err().to_string(),
+            "Unknown platform 'OSX32'"
+        );
+    }
+
+    #[test]
+    fn bitmask_round_trip() {
+        // A Delphi 11 multi-device project: Win32, Win64, iOS simulator
+        // (32-bit, dropped), Android, iOSDevice64, OSX64 and Android64.
+        let platforms = TargetPlatform::from_bitmask(37915);
+        assert_eq!(
+            platforms,
+            [
+                TargetPlatform::Win32,
+                TargetPlatform::Win64,
+                TargetPlatform::Android,
+                TargetPlatform::IOSDevice64,
+                TargetPlatform::OSX64,
+                TargetPlatform::Android64,
+            ]
+        );
+        assert_eq!(TargetPlatform::to_bitmask(platforms), 37915 & !0x8);
+        assert_eq!(TargetPlatform::to_bitmask(TargetPlatform::ALL).count_ones(), 10);
+    }
+
+    #[test]
+    fn os_and_architecture() {
+        assert_eq!(TargetPlatform::Win32.architecture(), Architecture::X86);
+        assert_eq!(TargetPlatform::Win64x.os(), TargetOs::Windows);
+        assert_eq!(TargetPlatform::OSXARM64.os(), TargetOs::MacOs);
+        assert_eq!(TargetPlatform::OSXARM64.architecture(), Architecture::Arm64);
+        assert_eq!(TargetPlatform::Android.architecture(), Architecture::Arm32);
+        assert!(TargetPlatform::IOSSimARM64.is_simulator());
+        assert!(!TargetPlatform::IOSDevice64.is_simulator());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_uses_project_names() {
+        let json = serde_json::to_string(&TargetPlatform::ALL).unwrap();
+        let names: Vec<String> = TargetPlatform::ALL.iter().map(|p| format!("\"{p}\"")).collect();
+        assert_eq!(json, format!("[{}]", names.join(",")));
+        let back: Vec<TargetPlatform> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, TargetPlatform::ALL);
+        assert_eq!(serde_json::to_string(&TargetOs::MacOs).unwrap(), "\"MacOs\"");
+    }
+}
@@ -0,0 +1,223 @@
+//! Where effective property values come from.
+//!
+//! [`Dproj::property_provenance_for`](crate::Dproj::property_provenance_for)
+//! evaluates the project like
+//! [`active_property_group_for`](crate::Dproj::active_property_group_for),
+//! but also records every assignment that contributed to each property: the
+//! `<PropertyGroup>` it sits in, the conditions that let it through and the
+//! value as written.  This answers questions such as "where does `RELEASE`
+//! in the defines of Release Win64 come from?":
+//!
+//! ```
+//! # use dproj_rs::Dproj;
+//! let dproj = Dproj::from_file("example.dproj").unwrap();
+//! let provenance = dproj.property_provenance_for("Release", "Win64").unwrap();
+//! let define = provenance.get("DCC_Define").unwrap();
+//! assert_eq!(
+//!     define.to_string(),
+//!     "DCC_Define = RELEASE;AAA;
+//!   set in PropertyGroup #16 (Condition=\"'$(Cfg_2)'!=''\"): RELEASE;$(DCC_Define)
+//!   overrides PropertyGroup #10 (Condition=\"'$(Base)'!=''\"): AAA;$(DCC_Define)"
+//! );
+//! ```
+
+use crate::dproj::PropertyGroup;
+
+/// One assignment of a property that took part in the evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyAssignment {
+    /// Index of the `<PropertyGroup>` in
+    /// [`DprojProject::property_groups`](crate::dproj::DprojProject::property_groups),
+    /// or among the groups of [`file`](Self::file).
+    pub group: usize,
+    /// The `<Import>`ed file containing the group; `None` for the project
+    /// itself.
+    pub file: Option<std::path::PathBuf>,
+    /// The group's `Condition`, if any.
+    pub condition: Option<String>,
+    /// The element's own `Condition`, if any.
+    pub element_condition: Option<String>,
+    /// The value as written in the file, before `$(…)` expansion.
+    pub raw: String,
+}
+
+/// The effective value of a property and the assignments that led to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyProvenance {
+    /// Property (element) name, e.g. `"DCC_RangeChecking"`.
+    pub name: String,
+    /// The effective, expanded value.
+    pub value: String,
+    /// Every assignment in evaluation order.  The last one produced
+    /// [`value`](Self::value); the earlier ones were overridden, or extended
+    /// when the value references the property itself
+    /// (`DEBUG;$(DCC_Define)`).
+    pub assignments: Vec<PropertyAssignment>,
+}
+
+impl PropertyProvenance {
+    /// The assignment that produced the effective value.
+    pub fn origin(&self) -> &PropertyAssignment {
+        self.assignments.last().expect("a property has at least one assignment")
+    }
+
+    /// The assignments preceding [`origin`](Self::origin), most recent first.
+    pub fn overridden(&self) -> impl Iterator<Item = &PropertyAssignment> {
+        self.assignments.iter().rev().skip(1)
+    }
+}
+
+impl std::fmt::Display for PropertyProvenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.name, self.value)?;
+        let lines = std::iter::once(("set in", self.origin()))
+            .chain(self.overridden().map(|a| ("overrides", a)));
+        for (verb, assignment) in lines {
+            write!(f, "\n  {verb} PropertyGroup #{}", assignment.group)?;
+            if let Some(file) = &assignment.file {
+                write!(f, " of {}", file.display())?;
+            }
+            let conditions = assignment.condition.iter().chain(&assignment.element_condition);
+            for condition in conditions {
+                write!(f, " (Condition=\"{condition}\")")?;
+            }
+            write!(f, ": {}", assignment.raw)?;
+        }
+        Ok(())
+    }
+}
+
+/// The effective property group of a configuration and platform together
+/// with the provenance of each of its properties.
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    /// The effective property group, as returned by
+    /// [`active_property_group_for`](crate::Dproj::active_property_group_for).
+    pub property_group: PropertyGroup,
+    /// Sorted by name, ignoring case.
+    properties: Vec<PropertyProvenance>,
+}
+
+impl Provenance {
+    /// Combine the effective group with the assignments recorded while
+    /// evaluating it, in evaluation order.
+    pub(crate) fn new(
+        property_group: PropertyGroup,
+        recorded: Vec<(String, PropertyAssignment)>,
+    ) -> Self {
+        let mut properties: Vec<PropertyProvenance> = property_group
+            .properties()
+            .into_iter()
+            .map(|(name, value)| PropertyProvenance { name, value, assignments: Vec::new() })
+            .collect();
+        properties.sort_unstable_by_key(|p| p.name.to_ascii_lowercase());
+
+        for (name, assignment) in recorded {
+            let key = name.to_ascii_lowercase();
+            if let Ok(i) = properties.binary_search_by_key(&key, |p| p.name.to_ascii_lowercase()) {
+                properties[i].assignments.push(assignment);
+            }
+        }
+        properties.retain(|p| !p.assignments.is_empty());
+
+        Self { property_group, properties }
+    }
+
+    /// Provenance of the property `name`, ignoring ASCII case.  `None` if
+    /// no group that applies assigns it.
+    pub fn get(&self, name: &str) -> Option<&PropertyProvenance> {
+        self.properties
+            .binary_search_by_key(&name.to_ascii_lowercase(), |p| p.name.to_ascii_lowercase())
+            .ok()
+            .map(|i| &self.properties[i])
+    }
+
+    /// Provenance of every effective property, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &PropertyProvenance> {
+        self.properties.iter()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use crate::Dproj;
+
+    const SOURCE: &str = r#"<Project>
+        <PropertyGroup>
+            <Config Condition="'$(Config)'==''">Debug</Config>
+            <Platform Condition="'$(Platform)'==''">Win32</Platform>
+        </PropertyGroup>
+        <PropertyGroup Condition="'$(Base)'!=''">
+            <DCC_RangeChecking>true</DCC_RangeChecking>
+            <DCC_Define>BASE;$(DCC_Define)</DCC_Define>
+            <UseDebugLib Condition="'$(Platform)'=='Win32'">true</UseDebugLib>
+        </PropertyGroup>
+        <PropertyGroup Condition="'$(Cfg_2)'!=''">
+            <DCC_RangeChecking>false</DCC_RangeChecking>
+            <DCC_Define>RELEASE;$(DCC_Define)</DCC_Define>
+        </PropertyGroup>
+        <ItemGroup>
+            <BuildConfiguration Include="Base"><Key>Base</Key></BuildConfiguration>
+            <BuildConfiguration Include="Debug"><Key>Cfg_1</Key><CfgParent>Base</CfgParent></BuildConfiguration>
+            <BuildConfiguration Include="Release"><Key>Cfg_2</Key><CfgParent>Base</CfgParent></BuildConfiguration>
+        </ItemGroup>
+    </Project>"#;
+
+    #[test]
+    fn records_override_chain() {
+        let dproj = Dproj::parse(SOURCE).unwrap();
+        let provenance = dproj.property_provenance_for("Release", "Win64").unwrap();
+
+        let range = provenance.get("dcc_rangechecking").unwrap();
+        assert_eq!(range.value, "false");
+        assert_eq!(range.origin().group, 2);
+        assert_eq!(range.origin().condition.as_deref(), Some("'$(Cfg_2)'!=''"));
+        let overridden: Vec<usize> = range.overridden().map(|a| a.group).collect();
+        assert_eq!(overridden, [1]);
+
+        let define = provenance.get("DCC_Define").unwrap();
+        assert_eq!(define.value, "RELEASE;BASE;");
+        assert_eq!(define.origin().raw, "RELEASE;$(DCC_Define)");
+
+        assert_eq!(
+            range.to_string(),
+            "DCC_RangeChecking = false\n  \
+             set in PropertyGroup #2 (Condition=\"'$(Cfg_2)'!=''\"): false\n  \
+             overrides PropertyGroup #1 (Condition=\"'$(Base)'!=''\"): true"
+        );
+    }
+
+    #[test]
+    fn skips_groups_and_elements_that_do_not_apply() {
+        let dproj = Dproj::parse(SOURCE).unwrap();
+        let provenance = dproj.property_provenance_for("Debug", "Win32").unwrap();
+
+        let range = provenance.get("DCC_RangeChecking").unwrap();
+        assert_eq!(range.value, "true");
+        assert_eq!(range.assignments.len(), 1);
+        // Config and platform were requested explicitly, so their defaults
+        // do not apply.
+        assert!(provenance.get("Config").is_none());
+        assert!(provenance.get("Platform").is_none());
+        let debug_lib = provenance.get("UseDebugLib").unwrap();
+        assert_eq!(debug_lib.origin().element_condition.as_deref(), Some("'$(Platform)'=='Win32'"));
+
+        let win64 = dproj.property_provenance_for("Debug", "Win64").unwrap();
+        assert!(win64.get("UseDebugLib").is_none());
+    }
+
+    #[test]
+    fn matches_active_property_group() {
+        let dproj = Dproj::from_file("example.dproj").unwrap();
+        let provenance = dproj.property_provenance_for("Release", "Win64").unwrap();
+        let active = dproj.active_property_group_for("Release", "Win64").unwrap();
+        assert_eq!(provenance.property_group.properties(), active.properties());
+        for property in provenance.iter() {
+            assert_eq!(active.properties()[&property.name], property.value);
+        }
+    }
+}
@@ -7,18 +7,19 @@
 //! These variables appear as `$(BDS)` / `$(BDSCOMMONDIR)` references inside
 //! `.dproj` files and need to be expanded for correct path resolution.
 
-use std::collections::HashMap;
+use crate::functions::{ReferenceCycle, Resolver};
+use crate::vars::VarMap;
 
 /// Expand `%VAR%` references in a value using the already-accumulated map.
 /// Unknown variables expand to the empty string.
-fn expand_percent_vars(s: &str, vars: &HashMap<String, String>) -> String {
+fn expand_percent_vars(s: &str, vars: &VarMap) -> String {
     let mut result = String::with_capacity(s.len());
     let mut chars = s.chars().peekable();
 
     while let Some(c) = chars.next() {
         if c == '%' {
             let var_name: String = chars.by_ref().take_while(|&ch| ch != '%').collect();
-            if let Some(val) = vars.get(&var_name.to_ascii_uppercase()) {
+            if let Some(val) = vars.get(&var_name) {
                 result.push_str(val);
             }
             // Unknown variables expand to the empty string.
@@ -30,6 +31,69 @@ fn expand_percent_vars(s: &str, vars: &HashMap<String, String>) -> String {
     result
 }
 
+/// Names of the variables referenced by `%VAR%` in a value, in order of
+/// first appearance.
+fn percent_references(s: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut parts = s.split('%').skip(1);
+    while let Some(name) = parts.next() {
+        // An unpaired trailing `%` does not start a reference.
+        if parts.next().is_none() {
+            break;
+        }
+        if !name.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Replace every `%name%` reference (in any case) in `value` with
+/// `replacement`.
+fn substitute_percent(value: &str, name: &str, replacement: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let reference = &rest[start..start + len + 2];
+        result.push_str(&rest[..start]);
+        if reference[1..reference.len() - 1].eq_ignore_ascii_case(name) {
+            result.push_str(replacement);
+        } else {
+            result.push_str(reference);
+        }
+        rest = &rest[start + len + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The `KEY=VALUE` assignments of `@SET` / `SET` lines, in document order,
+/// with values as written.
+fn set_lines(content: &str) -> impl Iterator<Item = (&str, &str)> {
+    content.lines().filter_map(|line| {
+        let trimmed = line.trim();
+
+        // Strip optional leading '@'.
+        let rest = trimmed.strip_prefix('@').unwrap_or(trimmed);
+
+        // Must start with SET (case-insensitive), then whitespace or '='.
+        if rest.len() < 3 || !rest[..3].eq_ignore_ascii_case("set") {
+            return None;
+        }
+
+        // Skip optional whitespace between SET and KEY.
+        let rest = rest[3..].trim_start();
+
+        // Find '=' separator.
+        let (key, value) = rest.split_once('=')?;
+        let key = key.trim();
+        (!key.is_empty()).then_some((key, value))
+    })
+}
+
 /// Parse the **contents** of an `rsvars.bat` file into a variable map.
 ///
 /// The returned map is seeded with **all current process environment variables**
@@ -44,6 +108,9 @@ fn expand_percent_vars(s: &str, vars: &HashMap<String, String>) -> String {
 ///
 /// Lines that don't match the `@SET` / `SET` pattern are silently skipped.
 ///
+/// Variable names are case-insensitive, as on Windows: `%bds%` expands
+/// `BDS`, and `vars["Bds"]` finds it.
+///
 /// # Example
 /// ```
 /// let content = r#"
@@ -54,48 +121,20 @@ fn expand_percent_vars(s: &str, vars: &HashMap<String, String>) -> String {
 /// assert_eq!(vars["BDS"], r"C:\Delphi");
 /// assert_eq!(vars["BDSBIN"], r"C:\Delphi\bin");
 /// ```
-pub fn parse_rsvars(content: &str) -> HashMap<String, String> {
+pub fn parse_rsvars(content: &str) -> VarMap {
     // Seed with the full process environment so that %VAR% references in the
     // file can expand against any already-set variable.  File entries are
     // inserted afterwards and therefore override duplicates.
-    // All keys are stored uppercased to match Windows' case-insensitive
-    // environment variable semantics (e.g. `Path` and `PATH` are the same).
-    let mut vars: HashMap<String, String> =
-        std::env::vars().map(|(k, v)| (k.to_ascii_uppercase(), v)).collect();
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-
-        // Strip optional leading '@'.
-        let rest = trimmed.strip_prefix('@').unwrap_or(trimmed);
-
-        // Must start with SET (case-insensitive), then whitespace or '='.
-        let rest = if rest.len() >= 3 && rest[..3].eq_ignore_ascii_case("set") {
-            &rest[3..]
-        } else {
-            continue;
-        };
-
-        // Skip optional whitespace between SET and KEY.
-        let rest = rest.trim_start();
-
-        // Find '=' separator.
-        let Some(eq_pos) = rest.find('=') else {
-            continue;
-        };
-
-        let key = rest[..eq_pos].trim().to_ascii_uppercase();
-        if key.is_empty() {
-            continue;
-        }
-
-        let raw_value = rest[eq_pos + 1..].to_string();
+    // Keys are case-insensitive, matching Windows' environment variable
+    // semantics (e.g. `Path` and `PATH` are the same).
+    let mut vars: VarMap = std::env::vars().collect();
 
+    for (key, raw_value) in set_lines(content) {
         // Expand %VAR% references using variables collected so far.
         let value = if raw_value.contains('%') {
-            expand_percent_vars(&raw_value, &vars)
+            expand_percent_vars(raw_value, &vars)
         } else {
-            raw_value
+            raw_value.to_string()
         };
 
         vars.insert(key, value);
@@ -104,13 +143,83 @@ pub fn parse_rsvars(content: &str) -> HashMap<String, String> {
     vars
 }
 
+/// Like [`parse_rsvars`], but `%VAR%` references are expanded after the
+/// whole file has been read, so a value may reference a variable that is
+/// set further down, and references inside referenced values are expanded
+/// as well.
+///
+/// A reference of a variable to itself (`@SET PATH=%BDS%\bin;%PATH%`)
+/// still means its previous value.  Variables referencing each other in a
+/// cycle are reported as a [`ReferenceCycle`] instead of silently
+/// expanding to the empty string.
+///
+/// # Example
+/// ```
+/// let content = r#"
+/// @SET BDSBIN=%BDS%\bin
+/// @SET BDS=C:\Delphi
+/// "#;
+/// let vars = dproj_rs::rsvars::parse_rsvars_deferred(content).unwrap();
+/// assert_eq!(vars["BDSBIN"], r"C:\Delphi\bin");
+/// ```
+pub fn parse_rsvars_deferred(content: &str) -> Result<VarMap, ReferenceCycle> {
+    let mut env: VarMap = std::env::vars().collect();
+
+    // File assignments, with self-references replaced by the previous value.
+    let mut raw = VarMap::new();
+    for (key, value) in set_lines(content) {
+        let previous = raw.get(key).or_else(|| env.get(key)).map_or("", String::as_str);
+        let value = substitute_percent(value, key, previous);
+        raw.insert(key, value);
+    }
+
+    // The process environment is taken as already expanded.
+    for key in raw.keys() {
+        env.remove(key);
+    }
+    let mut resolver = Resolver::new(&raw, percent_references, expand_percent_vars).seed(env);
+    for key in raw.keys() {
+        resolver.resolve(key)?;
+    }
+    Ok(resolver.resolved)
+}
+
+/// Find the `%VAR%` references in an `rsvars.bat` file that name a
+/// variable which is neither set earlier in the file nor present in the
+/// process environment, and which [`parse_rsvars`] therefore expands to the
+/// empty string.
+///
+/// Returns `(assigned variable, referenced variable)` pairs in document
+/// order.  A variable referencing its own undefined previous value
+/// (`@SET PATH=%PATH%;…`) is not reported.
+///
+/// # Example
+/// ```
+/// let content = "@SET BDSLIB=%BDS_TOTALLY_UNSET_12345%\\lib\n";
+/// let unresolved = dproj_rs::rsvars::unresolved_references(content);
+/// assert_eq!(unresolved, [("BDSLIB".to_string(), "BDS_TOTALLY_UNSET_12345".to_string())]);
+/// ```
+pub fn unresolved_references(content: &str) -> Vec<(String, String)> {
+    let mut known: VarMap = std::env::vars().collect();
+    let mut unresolved = Vec::new();
+    for (key, value) in set_lines(content) {
+        for name in percent_references(value) {
+            if !name.eq_ignore_ascii_case(key) && !known.contains_key(&name) {
+                unresolved.push((key.to_string(), name));
+            }
+        }
+        known.insert(key, "");
+    }
+    unresolved
+}
+
 /// Parse an `rsvars.bat` file from disk into a variable map.
 ///
 /// This is a convenience wrapper around [`parse_rsvars`] that reads the file
 /// first.
 pub fn parse_rsvars_file(
     path: impl AsRef<std::path::Path>,
-) -> Result<HashMap<String, String>, std::io::Error> {
+) -> Result<VarMap, std::io::Error> {
     let content = std::fs::read_to_string(path)?;
     Ok(parse_rsvars(&content))
 }
@@ -208,6 +317,64 @@ REM This is a comment
         );
     }
 
+    #[test]
+    fn keys_are_case_insensitive() {
+        let content = "@SET BDS=C:\\Delphi\n@SET BdsLib=%bds%\\lib\n@SET bdslib=%BDSLIB%\\win32\n";
+        let vars = parse_rsvars(content);
+        assert_eq!(vars["bds"], "C:\\Delphi");
+        assert_eq!(vars["BDSLIB"], "C:\\Delphi\\lib\\win32");
+    }
+
+    #[test]
+    fn deferred_expands_forward_and_nested_references() {
+        let content = "\
+@SET BDSLIB=%BDSBIN%\\..\\lib
+@SET BDSBIN=%BDS%\\bin
+@SET BDS=C:\\Delphi
+@SET PATH=%BDSBIN%;%PATH%
+";
+        let vars = parse_rsvars_deferred(content).unwrap();
+        assert_eq!(vars["BDSLIB"], "C:\\Delphi\\bin\\..\\lib");
+        let real_path = std::env::var("PATH").unwrap_or_default();
+        assert_eq!(vars["PATH"], format!("C:\\Delphi\\bin;{real_path}"));
+
+        // The single-pass parser leaves forward references empty.
+        assert_eq!(parse_rsvars(content)["BDSLIB"], "\\..\\lib");
+    }
+
+    #[test]
+    fn deferred_reports_cycles() {
+        let content = "@SET A=%B%\\x\n@SET B=%C%\n@SET C=%a%\n";
+        let err = parse_rsvars_deferred(content).unwrap_err();
+        assert_eq!(err.path.len(), 4);
+        assert_eq!(err.path.first(), err.path.last());
+    }
+
+    #[test]
+    fn reports_unresolved_references() {
+        let content = "\
+@SET BDS=C:\\Delphi
+@SET BDSBIN=%BDS%\\bin;%NO_SUCH_VAR_12345%
+@SET TOTALLY_NEW_VAR_12345=%TOTALLY_NEW_VAR_12345%;x
+@SET LATER=%DEFINED_BELOW_12345%
+@SET DEFINED_BELOW_12345=1
+";
+        assert_eq!(
+            unresolved_references(content),
+            [
+                ("BDSBIN".to_string(), "NO_SUCH_VAR_12345".to_string()),
+                ("LATER".to_string(), "DEFINED_BELOW_12345".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn percent_reference_scanning() {
+        assert_eq!(percent_references("%A%\\%b%;%A%"), ["A", "b"]);
+        assert_eq!(percent_references("100% done"), Vec::<String>::new());
+        assert_eq!(substitute_percent("x;%path%;%P%", "PATH", "old"), "x;old;%P%");
+    }
+
     #[test]
     fn path_expands_framework_dir() {
         let content = "\
@@ -0,0 +1,198 @@
+//! Case-insensitive variable map.
+//!
+//! MSBuild property names and Windows environment variables are
+//! case-insensitive: `$(BDS)`, `$(Bds)` and `%bds%` all refer to the same
+//! variable.  [`VarMap`] stores variables under a case-folded key while
+//! remembering the spelling they were inserted with, so lookups behave like
+//! MSBuild and iteration still shows the names as written.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::hash_map;
+
+/// A map from variable names to values with ASCII case-insensitive keys.
+///
+/// # Example
+/// ```
+/// use dproj_rs::VarMap;
+///
+/// let mut vars = VarMap::new();
+/// vars.insert("BDSCOMMONDIR", r"C:\Users\Public\Documents\Embarcadero\Studio\23.0");
+/// assert_eq!(vars["bdscommondir"], vars["BDSCOMMONDIR"]);
+/// assert_eq!(vars.iter().next().unwrap().0, "BDSCOMMONDIR");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VarMap {
+    /// Case-folded name → (name as last inserted, value).
+    entries: HashMap<String, (String, String)>,
+}
+
+/// Fold `key` to the form used for hashing, borrowing when it is already
+/// lowercase.
+fn fold(key: &str) -> Cow<'_, str> {
+    if key.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(key.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+impl VarMap {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up a variable, ignoring ASCII case.
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.entries.get(fold(key).as_ref()).map(|(_, v)| v)
+    }
+
+    /// Look up a variable, ignoring ASCII case, and return its name as
+    /// stored together with its value.
+    pub fn get_key_value(&self, key: &str) -> Option<(&str, &str)> {
+        self.entries
+            .get(fold(key).as_ref())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Whether a variable is defined, ignoring ASCII case.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(fold(key).as_ref())
+    }
+
+    /// Set a variable, returning the previous value if it was defined under
+    /// any spelling.  The new spelling of the name replaces the old one.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        let key = key.into();
+        self.entries
+            .insert(fold(&key).into_owned(), (key, value.into()))
+            .map(|(_, v)| v)
+    }
+
+    /// Remove a variable, ignoring ASCII case.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.entries.remove(fold(key).as_ref()).map(|(_, v)| v)
+    }
+
+    /// Number of variables.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over `(name, value)` pairs in arbitrary order.  Names keep
+    /// the spelling they were inserted with.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.values().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Iterate over variable names in arbitrary order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.values().map(|(k, _)| k.as_str())
+    }
+}
+
+impl std::ops::Index<&str> for VarMap {
+    type Output = String;
+
+    fn index(&self, key: &str) -> &String {
+        self.get(key)
+            .unwrap_or_else(|| panic!("variable '{key}' not found"))
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for VarMap {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for VarMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut vars = VarMap::new();
+        vars.extend(iter);
+        vars
+    }
+}
+
+impl From<HashMap<String, String>> for VarMap {
+    fn from(map: HashMap<String, String>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl IntoIterator for VarMap {
+    type Item = (String, String);
+    type IntoIter = hash_map::IntoValues<String, (String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_values()
+    }
+}
+
+impl<'a> IntoIterator for &'a VarMap {
+    type Item = (&'a String, &'a String);
+    type IntoIter = std::iter::Map<
+        hash_map::Values<'a, String, (String, String)>,
+        fn(&'a (String, String)) -> (&'a String, &'a String),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        fn split(entry: &(String, String)) -> (&String, &String) {
+            (&entry.0, &entry.1)
+        }
+        self.entries.values().map(split as fn(&'a (String, String)) -> _)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_ignore_case() {
+        let mut vars = VarMap::new();
+        vars.insert("BDS", r"C:\Studio");
+        assert_eq!(vars.get("bds").map(String::as_str), Some(r"C:\Studio"));
+        assert_eq!(vars["Bds"], r"C:\Studio");
+        assert!(vars.contains_key("bDS"));
+        assert!(!vars.contains_key("BDSBIN"));
+        assert_eq!(vars.get_key_value("bds"), Some(("BDS", r"C:\Studio")));
+    }
+
+    #[test]
+    fn insert_replaces_any_spelling() {
+        let mut vars = VarMap::new();
+        vars.insert("Config", "Debug");
+        assert_eq!(vars.insert("CONFIG", "Release").as_deref(), Some("Debug"));
+        assert_eq!(vars.len(), 1);
+        assert_eq!(vars.iter().collect::<Vec<_>>(), vec![("CONFIG", "Release")]);
+        assert_eq!(vars.remove("config").as_deref(), Some("Release"));
+        assert!(vars.is_empty());
+    }
+
+    #[test]
+    fn conversions() {
+        let map = HashMap::from([("Platform".to_string(), "Win64".to_string())]);
+        let vars = VarMap::from(map);
+        assert_eq!(vars["platform"], "Win64");
+
+        let vars: VarMap = [("A", "1"), ("a", "2")].into_iter().collect();
+        assert_eq!(vars.len(), 1);
+        assert_eq!(vars["A"], "2");
+
+        let pairs: Vec<(String, String)> = vars.into_iter().collect();
+        assert_eq!(pairs, vec![("a".to_string(), "2".to_string())]);
+    }
+}
@@ -0,0 +1,367 @@
+//! Delphi compiler warnings.
+//!
+//! Each compiler warning has an identifier (`UNSAFE_TYPE`), used by
+//! `{$WARN UNSAFE_TYPE ON}` and the `-W` command-line switch, and a number
+//! (`W1046`) shown in compiler messages.  A project sets a warning with an
+//! element named after the identifier:
+//!
+//! ```xml
+//! <DCC_UNSAFE_TYPE>error</DCC_UNSAFE_TYPE>
+//! ```
+//!
+//! The parser keeps these elements in [`DccOptions::warning_directives`].
+//! [`WARNINGS`] catalogues the known warnings, and the methods added to
+//! [`DccOptions`] read and write their [`WarningState`] and produce the
+//! matching `-W` switches:
+//!
+//! ```
+//! use dproj_rs::warnings::WarningState;
+//!
+//! let dproj = dproj_rs::Dproj::from_file("example.dproj")?;
+//! let mut dcc = dproj.active_property_group_for("Release", "Win64")?.dcc_options;
+//! assert_eq!(dcc.warning_state("W1057")?, Some(WarningState::Disabled));
+//!
+//! dcc.set_warning_state("IMPLICIT_STRING_CAST", WarningState::Error);
+//! assert!(dcc.warning_switches()?.contains(&"-W^IMPLICIT_STRING_CAST".to_string()));
+//! # Ok::<(), dproj_rs::dproj::DprojError>(())
+//! ```
+//!
+//! Warnings the project does not set have the compiler's default state and
+//! are reported as `None`.
+
+use crate::condition;
+use crate::dcc::InvalidValue;
+use crate::dproj::DccOptions;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Catalogue
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// A Delphi compiler warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Warning {
+    /// The number in compiler messages, e.g. `1046` for `W1046`.
+    pub number: u16,
+    /// The `{$WARN}` identifier, e.g. `"UNSAFE_TYPE"`.
+    pub identifier: &'static str,
+    pub description: &'static str,
+}
+
+impl Warning {
+    /// The message code, e.g. `"W1046"`.
+    pub fn code(&self) -> String {
+        format!("W{}", self.number)
+    }
+
+    /// The `.dproj` element, e.g. `"DCC_UNSAFE_TYPE"`.
+    pub fn tag(&self) -> String {
+        format!("DCC_{}", self.identifier)
+    }
+
+    /// Look up a warning by identifier (`UNSAFE_TYPE`), element name
+    /// (`DCC_UNSAFE_TYPE`) or code (`W1046`), ignoring ASCII case.
+    pub fn find(name: &str) -> Option<&'static Warning> {
+        let name = name.trim();
+        let name = strip_prefix_ignore_case(name, "DCC_").unwrap_or(name);
+        if let Some(number) = strip_prefix_ignore_case(name, "W").and_then(|n| n.parse::<u16>().ok()) {
+            return WARNINGS.iter().find(|w| w.number == number);
+        }
+        WARNINGS.iter().find(|w| w.identifier.eq_ignore_ascii_case(name))
+    }
+}
+
+macro_rules! warnings {
+    ($($number:literal $identifier:ident $description:literal,)+) => {
+        /// The warnings that can be set per project, in number order.
+        pub const WARNINGS: &[Warning] = &[
+            $(Warning {
+                number: $number,
+                identifier: stringify!($identifier),
+                description: $description,
+            },)+
+        ];
+    };
+}
+
+warnings! {
+    1000 SYMBOL_DEPRECATED "Symbol is deprecated",
+    1001 SYMBOL_LIBRARY "Symbol is specific to a library",
+    1002 SYMBOL_PLATFORM "Symbol is specific to a platform",
+    1003 SYMBOL_EXPERIMENTAL "Symbol is experimental",
+    1004 UNIT_LIBRARY "Unit is specific to a library",
+    1005 UNIT_PLATFORM "Unit is specific to a platform",
+    1006 UNIT_DEPRECATED "Unit is deprecated",
+    1007 UNIT_EXPERIMENTAL "Unit is experimental",
+    1008 HRESULT_COMPAT "Integer and HRESULT interchanged",
+    1009 HIDING_MEMBER "Redeclaration hides a member in the base class",
+    1010 HIDDEN_VIRTUAL "Method hides virtual method of base type",
+    1011 GARBAGE "Text after final 'END.' ignored by compiler",
+    1012 BOUNDS_ERROR "Constant expression violates subrange bounds",
+    1013 ZERO_NIL_COMPAT "Constant 0 converted to NIL",
+    1014 STRING_CONST_TRUNCED "String constant truncated to fit STRING[n]",
+    1015 FOR_LOOP_VAR_VARPAR "FOR-Loop variable cannot be passed as var parameter",
+    1016 TYPED_CONST_VARPAR "Typed constant passed as var parameter",
+    1017 ASG_TO_TYPED_CONST "Assignment to typed constant",
+    1018 CASE_LABEL_RANGE "Case label outside of range of case expression",
+    1019 FOR_VARIABLE "For loop control variable must be simple local variable",
+    1020 CONSTRUCTING_ABSTRACT "Constructing instance containing abstract method",
+    1021 COMPARISON_FALSE "Comparison always evaluates to False",
+    1022 COMPARISON_TRUE "Comparison always evaluates to True",
+    1023 COMPARING_SIGNED_UNSIGNED "Comparing signed and unsigned types",
+    1024 COMBINING_SIGNED_UNSIGNED "Combining signed and unsigned types",
+    1025 UNSUPPORTED_CONSTRUCT "Unsupported language feature",
+    1026 FILE_OPEN "File not found",
+    1027 FILE_OPEN_UNITSRC "Unit source file not found",
+    1028 BAD_GLOBAL_SYMBOL "Bad global symbol definition",
+    1029 DUPLICATE_CTOR_DTOR "Duplicate constructor or destructor with identical parameters",
+    1030 INVALID_DIRECTIVE "Invalid compiler directive",
+    1031 PACKAGE_NO_LINK "Package will not be written to disk because -J option is enabled",
+    1032 PACKAGED_THREADVAR "Exported package threadvar cannot be used outside of this package",
+    1033 IMPLICIT_IMPORT "Unit implicitly imported into package",
+    1034 HPPEMIT_IGNORED "$HPPEMIT ignored",
+    1035 NO_RETVAL "Return value of function might be undefined",
+    1036 USE_BEFORE_DEF "Variable might not have been initialized",
+    1037 FOR_LOOP_VAR_UNDEF "FOR-Loop variable may be undefined after loop",
+    1038 UNIT_NAME_MISMATCH "Unit identifier does not match file name",
+    1039 NO_CFG_FILE_FOUND "No configuration files found",
+    1040 IMPLICIT_VARIANTS "Implicit use of Variants unit",
+    1041 UNICODE_TO_LOCALE "Error converting Unicode char to locale charset",
+    1042 LOCALE_TO_UNICODE "Error converting locale string to Unicode",
+    1043 IMAGEBASE_MULTIPLE "Imagebase is not a multiple of 64k",
+    1044 SUSPICIOUS_TYPECAST "Suspicious typecast of string to PAnsiChar or PWideChar",
+    1045 PRIVATE_PROPACCESSOR "Property accessor should be protected or public",
+    1046 UNSAFE_TYPE "Unsafe type",
+    1047 UNSAFE_CODE "Unsafe code",
+    1048 UNSAFE_CAST "Unsafe typecast",
+    1049 OPTION_TRUNCATED "Value for option was truncated",
+    1050 WIDECHAR_REDUCED "WideChar reduced to byte char in set expressions",
+    1051 DUPLICATES_IGNORED "Duplicate symbol names in namespace",
+    1052 UNIT_INIT_SEQ "Unit initialization order cannot be guaranteed",
+    1053 LOCAL_PINVOKE "Local PInvoke code has not been made",
+    1054 MESSAGE_DIRECTIVE "User-defined warning message",
+    1055 TYPEINFO_IMPLICITLY_ADDED "Published caused RTTI ($M+) to be added to type",
+    1056 RLINK_WARNING "Duplicate resource",
+    1057 IMPLICIT_STRING_CAST "Implicit string cast",
+    1058 IMPLICIT_STRING_CAST_LOSS "Implicit string cast with potential data loss",
+    1059 EXPLICIT_STRING_CAST "Explicit string cast",
+    1060 EXPLICIT_STRING_CAST_LOSS "Explicit string cast with potential data loss",
+    1061 CVT_WCHAR_TO_ACHAR "Narrowing WideChar constant to AnsiChar lost information",
+    1062 CVT_NARROWING_STRING_LOST "Narrowing wide string constant lost information",
+    1063 CVT_ACHAR_TO_WCHAR "Widening AnsiChar constant to WideChar lost information",
+    1064 CVT_WIDENING_STRING_LOST "Widening AnsiString constant lost information",
+    1068 IMMUTABLE_STRINGS "Modifying strings in-place may not be supported in the future",
+    1070 UNSAFE_VOID_POINTER "Use of untyped pointer can disrupt instance reference counts",
+    1071 IMPLICIT_INTEGER_CAST_LOSS "Implicit integer cast with potential data loss",
+    1072 IMPLICIT_CONVERSION_LOSS "Implicit conversion may lose significant digits",
+    1073 COMBINING_SIGNED_UNSIGNED64 "Combining signed type and unsigned 64-bit type",
+    1074 UNKNOWN_CUSTOM_ATTRIBUTE "Unknown custom attribute",
+    1201 XML_WHITESPACE_NOT_ALLOWED "XML: whitespace is not allowed at this location",
+    1202 XML_UNKNOWN_ENTITY "XML: reference to undefined entity",
+    1203 XML_INVALID_NAME_START "XML: character is not valid at the start of a name",
+    1204 XML_INVALID_NAME "XML: character is not valid in a name",
+    1205 XML_EXPECTED_CHARACTER "XML: expected character not found",
+    1206 XML_CREF_NO_RESOLVE "XML: cref attribute could not be resolved",
+    1207 XML_NO_PARM "XML: param tag for a parameter that does not exist",
+    1208 XML_NO_MATCHING_PARM "XML: parameter has no matching param tag",
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Warning state
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// What the compiler does with a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningState {
+    /// Reported as a warning (`true`, `{$WARN … ON}`).
+    Enabled,
+    /// Not reported (`false`, `{$WARN … OFF}`).
+    Disabled,
+    /// Reported as an error (`error`, `{$WARN … ERROR}`).
+    Error,
+}
+
+impl WarningState {
+    /// Read the text of a warning element: `true`, `false` or `error`
+    /// (any case; booleans in any MSBuild spelling).
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("error") {
+            return Some(Self::Error);
+        }
+        condition::parse_bool(text).map(|on| if on { Self::Enabled } else { Self::Disabled })
+    }
+
+    /// The text the IDE writes.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Enabled => "true",
+            Self::Disabled => "false",
+            Self::Error => "error",
+        }
+    }
+
+    /// The `-W` switch for `identifier`, e.g. `-W^UNSAFE_TYPE`.
+    pub fn switch(self, identifier: &str) -> String {
+        let sign = match self {
+            Self::Enabled => '+',
+            Self::Disabled => '-',
+            Self::Error => '^',
+        };
+        format!("-W{sign}{identifier}")
+    }
+}
+
+impl std::fmt::Display for WarningState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Accessors
+// ═══════════════════════════════════════════════════════════════════════════════
+
+impl DccOptions {
+    /// The state the project sets for `warning` — an identifier, element
+    /// name or code, see [`Warning::find`].  `Ok(None)` when the project
+    /// leaves it at the compiler default.
+    pub fn warning_state(&self, warning: &str) -> Result<Option<WarningState>, InvalidValue> {
+        let identifier = identifier(warning);
+        self.warning_directives
+            .iter()
+            .find(|(tag, _)| tag_identifier(tag).is_some_and(|id| id.eq_ignore_ascii_case(&identifier)))
+            .map_or(Ok(None), |(tag, value)| decode(tag, value))
+    }
+
+    /// Set `warning` to `state`, writing the element name the IDE uses.
+    pub fn set_warning_state(&mut self, warning: &str, state: WarningState) {
+        let identifier = identifier(warning);
+        self.warning_directives
+            .retain(|tag, _| !tag_identifier(tag).is_some_and(|id| id.eq_ignore_ascii_case(&identifier)));
+        self.warning_directives
+            .insert(format!("DCC_{identifier}"), state.as_str().to_string());
+    }
+
+    /// Every warning the project sets, by identifier, sorted.  Includes
+    /// warnings missing from [`WARNINGS`], e.g. those of newer compilers.
+    pub fn warning_states(&self) -> Result<Vec<(String, WarningState)>, InvalidValue> {
+        let mut states = Vec::new();
+        for (tag, value) in &self.warning_directives {
+            let Some(identifier) = tag_identifier(tag).filter(|id| is_warning(id)) else {
+                continue;
+            };
+            if let Some(state) = decode(tag, value)? {
+                states.push((identifier.to_ascii_uppercase(), state));
+            }
+        }
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(states)
+    }
+
+    /// The `-W` compiler switches for [`warning_states`](Self::warning_states):
+    /// `-W+ID`, `-W-ID` or `-W^ID`.
+    pub fn warning_switches(&self) -> Result<Vec<String>, InvalidValue> {
+        Ok(self
+            .warning_states()?
+            .into_iter()
+            .map(|(identifier, state)| state.switch(&identifier))
+            .collect())
+    }
+}
+
+/// The canonical identifier for a name accepted by [`Warning::find`].
+fn identifier(name: &str) -> String {
+    match Warning::find(name) {
+        Some(warning) => warning.identifier.to_string(),
+        None => {
+            let name = name.trim();
+            strip_prefix_ignore_case(name, "DCC_").unwrap_or(name).to_ascii_uppercase()
+        }
+    }
+}
+
+/// The identifier part of a `DCC_*` element name.
+fn tag_identifier(tag: &str) -> Option<&str> {
+    strip_prefix_ignore_case(tag, "DCC_")
+}
+
+/// Whether an unrecognised `DCC_*` element is a warning: catalogued, or
+/// spelled like one (`UPPER_CASE`, unlike option elements such as
+/// `DCC_SomeOption`).
+fn is_warning(identifier: &str) -> bool {
+    Warning::find(identifier).is_some()
+        || (identifier.len() > 1
+            && identifier
+                .bytes()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_'))
+}
+
+fn decode(tag: &str, value: &str) -> Result<Option<WarningState>, InvalidValue> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    WarningState::parse(value).map(Some).ok_or_else(|| InvalidValue {
+        property: tag.to_string(),
+        value: value.to_string(),
+        expected: "true, false or error",
+    })
+}
+
+fn strip_prefix_ignore_case<'s>(s: &'s str, prefix: &str) -> Option<&'s str> {
+    let (head, rest) = s.split_at_checked(prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then_some(rest)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogue_lookup() {
+        let unsafe_type = Warning::find("UNSAFE_TYPE").unwrap();
+        assert_eq!(unsafe_type.code(), "W1046");
+        assert_eq!(unsafe_type.tag(), "DCC_UNSAFE_TYPE");
+        assert_eq!(Warning::find("dcc_unsafe_type"), Some(unsafe_type));
+        assert_eq!(Warning::find("w1046"), Some(unsafe_type));
+        assert_eq!(Warning::find("W9999"), None);
+        assert!(WARNINGS.windows(2).all(|w| w[0].number < w[1].number));
+    }
+
+    #[test]
+    fn states_and_switches() {
+        let mut dcc = DccOptions::default();
+        assert_eq!(dcc.warning_state("UNSAFE_TYPE"), Ok(None));
+
+        dcc.warning_directives.insert("DCC_unsafe_type".into(), "Error".into());
+        dcc.warning_directives.insert("DCC_SYMBOL_PLATFORM".into(), "False".into());
+        dcc.warning_directives.insert("DCC_NEW_WARNING".into(), "true".into());
+        dcc.warning_directives.insert("DCC_SomeOption".into(), "3".into());
+        assert_eq!(dcc.warning_state("W1046"), Ok(Some(WarningState::Error)));
+        assert_eq!(
+            dcc.warning_switches().unwrap(),
+            ["-W+NEW_WARNING", "-W-SYMBOL_PLATFORM", "-W^UNSAFE_TYPE"]
+        );
+
+        dcc.set_warning_state("unsafe_type", WarningState::Enabled);
+        assert_eq!(dcc.warning_directives.get("DCC_UNSAFE_TYPE").map(String::as_str), Some("true"));
+        assert!(!dcc.warning_directives.contains_key("DCC_unsafe_type"));
+
+        dcc.warning_directives.insert("DCC_UNSAFE_CAST".into(), "sometimes".into());
+        let err = dcc.warning_states().unwrap_err();
+        assert_eq!(err.property, "DCC_UNSAFE_CAST");
+    }
+
+    #[test]
+    fn effective_states_follow_the_configuration() {
+        let dproj = crate::Dproj::from_file("example.dproj").unwrap();
+        let dcc = dproj.active_property_group_for("Debug", "Win32").unwrap().dcc_options;
+        let states = dcc.warning_states().unwrap();
+        assert!(!states.is_empty());
+        assert!(states.iter().all(|(id, _)| is_warning(id)));
+        assert_eq!(dcc.warning_state("COMBINING_SIGNED_UNSIGNED"), Ok(Some(WarningState::Disabled)));
+    }
+}